@@ -0,0 +1,101 @@
+// Spoken replacements for weather emoji commonly used in responses
+const EMOJI_WORDS: [(char, &str); 13] = [
+    ('☀', "clear sky"),
+    ('🌤', "mostly sunny"),
+    ('⛅', "partly cloudy"),
+    ('🌥', "mostly cloudy"),
+    ('☁', "cloudy"),
+    ('🌦', "sun and showers"),
+    ('🌧', "rain"),
+    ('⛈', "thunderstorm"),
+    ('🌩', "lightning"),
+    ('🌨', "snow"),
+    ('❄', "snow"),
+    ('🌫', "fog"),
+    ('💨', "wind"),
+];
+
+// Unit symbols and their spelled-out form
+const UNIT_WORDS: [(&str, &str); 4] = [
+    ("°C", " degrees Celsius"),
+    ("°F", " degrees Fahrenheit"),
+    ("km/h", " kilometers per hour"),
+    ("%", " percent"),
+];
+
+/// Rewrites text for screen readers.
+///
+/// Known weather emoji are replaced with their description, any other emoji are removed,
+/// and unit symbols are spelled out ("22°C" becomes "22 degrees Celsius", "60%" becomes
+/// "60 percent").
+pub fn accessible_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if let Some((_, words)) = EMOJI_WORDS.iter().find(|(emoji, _)| *emoji == c) {
+            result.push_str(words);
+        } else if !is_emoji(c) {
+            result.push(c);
+        }
+    }
+
+    let mut result = UNIT_WORDS
+        .iter()
+        .fold(result, |text, (symbol, words)| text.replace(symbol, words));
+
+    // Emoji removal can leave doubled spaces behind
+    while result.contains("  ") {
+        result = result.replace("  ", " ");
+    }
+    result.trim().to_string()
+}
+
+/// Whether a character is an emoji or an emoji modifier
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF // pictographs, emoticons, transport, flags
+            | 0x2600..=0x27BF // miscellaneous symbols and dingbats
+            | 0x2B00..=0x2BFF // arrows and stars
+            | 0xFE0F // emoji variation selector
+            | 0x200D // zero-width joiner
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temperatures_are_spelled_out() {
+        assert_eq!(accessible_text("22°C"), "22 degrees Celsius");
+        assert_eq!(accessible_text("It is 71°F"), "It is 71 degrees Fahrenheit");
+    }
+
+    #[test]
+    fn wind_speeds_and_percentages_are_spelled_out() {
+        assert_eq!(
+            accessible_text("Wind 15 km/h, humidity 60%"),
+            "Wind 15 kilometers per hour, humidity 60 percent"
+        );
+        assert_eq!(
+            accessible_text("Wind 15km/h"),
+            "Wind 15 kilometers per hour"
+        );
+    }
+
+    #[test]
+    fn weather_emoji_are_described_and_others_removed() {
+        assert_eq!(accessible_text("☀ 25°C"), "clear sky 25 degrees Celsius");
+        assert_eq!(accessible_text("Enjoy 😎 the day 👍🏽"), "Enjoy the day");
+        assert_eq!(accessible_text("❄️ snow"), "snow snow");
+    }
+
+    #[test]
+    fn plain_text_is_unchanged() {
+        assert_eq!(
+            accessible_text("It is sunny in Paris"),
+            "It is sunny in Paris"
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use std::str::FromStr;
+
+use crate::error::AppError;
+use crate::weather::response::ForecastDay;
+
+// WeatherAPI condition codes for clear and partly cloudy skies
+const CONDITION_SUNNY: i32 = 1000;
+const CONDITION_PARTLY_CLOUDY: i32 = 1003;
+
+/// Outdoor activities that forecast days can be scored for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Activity {
+    Hike,
+    Beach,
+    Picnic,
+    Cycling,
+}
+
+impl Activity {
+    /// Names accepted by `from_str`, advertised in the tool schema
+    pub const NAMES: [&'static str; 4] = ["hike", "beach", "picnic", "cycling"];
+
+    /// Weather thresholds used to score a day for this activity
+    fn rules(self) -> Rules {
+        match self {
+            Activity::Hike => Rules {
+                max_precip_chance: 50,
+                ideal_high_c: (10.0, 24.0),
+                max_wind_kph: 40.0,
+                wants_sun: false,
+            },
+            Activity::Beach => Rules {
+                max_precip_chance: 30,
+                ideal_high_c: (25.0, 35.0),
+                max_wind_kph: 30.0,
+                wants_sun: true,
+            },
+            Activity::Picnic => Rules {
+                max_precip_chance: 30,
+                ideal_high_c: (18.0, 28.0),
+                max_wind_kph: 25.0,
+                wants_sun: true,
+            },
+            Activity::Cycling => Rules {
+                max_precip_chance: 40,
+                ideal_high_c: (12.0, 26.0),
+                max_wind_kph: 30.0,
+                wants_sun: false,
+            },
+        }
+    }
+}
+
+impl FromStr for Activity {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "hike" | "hiking" => Ok(Activity::Hike),
+            "beach" => Ok(Activity::Beach),
+            "picnic" => Ok(Activity::Picnic),
+            "cycling" | "bike" | "biking" => Ok(Activity::Cycling),
+            _ => Err(AppError::InvalidParameter(format!(
+                "unknown activity '{}', expected one of: {}",
+                s,
+                Activity::NAMES.join(", ")
+            ))),
+        }
+    }
+}
+
+/// Weather thresholds for a single activity
+struct Rules {
+    /// Chance of rain or snow (%) above which a day is rejected
+    max_precip_chance: i32,
+    /// Comfortable range for the daily high in Celsius
+    ideal_high_c: (f64, f64),
+    /// Wind speed (kph) above which a day is rejected
+    max_wind_kph: f64,
+    /// Whether clear skies matter for the activity
+    wants_sun: bool,
+}
+
+// How far (in Celsius) the daily high may stray from the ideal range before a day is rejected
+const TEMPERATURE_TOLERANCE_C: f64 = 8.0;
+
+/// Suitability of a single forecast day for an activity
+#[derive(Debug)]
+pub struct DayScore {
+    /// Forecast date in format "YYYY-MM-DD"
+    pub date: String,
+    /// Suitability score from 0 (worst) to 100 (best)
+    pub score: u32,
+    /// Whether the day meets the minimum thresholds for the activity
+    pub acceptable: bool,
+    /// Human-readable explanation of the score
+    pub reasons: Vec<String>,
+}
+
+/// Scores a forecast day for an activity.
+///
+/// Temperatures in `reasons` are formatted in the requested unit ("C" or "F").
+pub fn score_day(activity: Activity, day: &ForecastDay, unit: &str) -> DayScore {
+    let rules = activity.rules();
+    let forecast = &day.day;
+    let mut reasons = Vec::new();
+    let mut acceptable = true;
+    let mut penalty = 0.0;
+
+    // Precipitation: the chance of rain or snow, whichever is higher; unreported counts as none
+    let precip_chance = forecast
+        .daily_chance_of_rain
+        .unwrap_or(0)
+        .max(forecast.daily_chance_of_snow.unwrap_or(0));
+    penalty += f64::from(precip_chance) * 0.5;
+    if precip_chance > rules.max_precip_chance {
+        acceptable = false;
+        reasons.push(format!(
+            "{}% chance of precipitation is too high",
+            precip_chance
+        ));
+    } else {
+        reasons.push(format!("{}% chance of precipitation", precip_chance));
+    }
+
+    // Temperature: distance of the daily high from the comfortable range
+    let (low_c, high_c) = rules.ideal_high_c;
+    let deviation = if forecast.maxtemp_c < low_c {
+        low_c - forecast.maxtemp_c
+    } else if forecast.maxtemp_c > high_c {
+        forecast.maxtemp_c - high_c
+    } else {
+        0.0
+    };
+    penalty += (deviation * 3.0).min(30.0);
+    let high = match unit {
+        "F" => format!("{}°F", forecast.maxtemp_f),
+        _ => format!("{}°C", forecast.maxtemp_c),
+    };
+    if deviation == 0.0 {
+        reasons.push(format!("comfortable high of {}", high));
+    } else if deviation > TEMPERATURE_TOLERANCE_C {
+        acceptable = false;
+        reasons.push(format!("high of {} is too far from comfortable", high));
+    } else {
+        reasons.push(format!(
+            "high of {} is a bit outside the comfortable range",
+            high
+        ));
+    }
+
+    // Wind: proportional to the activity's limit; an unreported wind is left out of the score
+    match forecast.maxwind_kph {
+        Some(wind) => {
+            penalty += (wind / rules.max_wind_kph * 10.0).min(10.0);
+            if wind > rules.max_wind_kph {
+                acceptable = false;
+                reasons.push(format!("wind up to {} kph is too strong", wind));
+            }
+        }
+        None => reasons.push("wind not reported".to_string()),
+    }
+
+    // Sky: only matters for activities that want sunshine
+    if rules.wants_sun {
+        match forecast.condition.code {
+            CONDITION_SUNNY => reasons.push("sunny".to_string()),
+            CONDITION_PARTLY_CLOUDY => {
+                penalty += 5.0;
+                reasons.push("partly cloudy".to_string());
+            }
+            _ => {
+                penalty += 10.0;
+                reasons.push(forecast.condition.text.to_lowercase());
+            }
+        }
+    }
+
+    DayScore {
+        date: day.date.clone(),
+        score: (100.0 - penalty).clamp(0.0, 100.0).round() as u32,
+        acceptable,
+        reasons,
+    }
+}
+
+/// Picks the most suitable day for an activity among the given forecast days.
+///
+/// # Returns
+/// * The highest-scoring day that meets the minimum thresholds (earliest wins ties)
+/// * `None` when no day is acceptable for the activity
+pub fn best_day<'a>(
+    activity: Activity,
+    days: impl IntoIterator<Item = &'a ForecastDay>,
+    unit: &str,
+) -> Option<DayScore> {
+    days.into_iter()
+        .map(|day| score_day(activity, day, unit))
+        .filter(|score| score.acceptable)
+        .fold(None, |best: Option<DayScore>, next| match best {
+            Some(best) if best.score >= next.score => Some(best),
+            _ => Some(next),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weather::response::{DayForecast, WeatherCondition};
+
+    fn day(maxwind_kph: Option<f64>) -> ForecastDay {
+        ForecastDay {
+            date: "2025-06-01".to_string(),
+            day: DayForecast {
+                maxtemp_c: 18.0,
+                maxtemp_f: 64.4,
+                mintemp_c: 9.0,
+                mintemp_f: 48.2,
+                maxwind_kph,
+                daily_chance_of_rain: Some(10),
+                daily_chance_of_snow: None,
+                condition: WeatherCondition {
+                    text: "Partly cloudy".to_string(),
+                    code: CONDITION_PARTLY_CLOUDY,
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn strong_wind_rejects_a_day() {
+        let score = score_day(Activity::Hike, &day(Some(55.0)), "C");
+        assert!(!score.acceptable);
+        assert!(
+            score
+                .reasons
+                .contains(&"wind up to 55 kph is too strong".to_string())
+        );
+    }
+
+    #[test]
+    fn unreported_wind_is_left_out_of_the_score() {
+        let unreported = score_day(Activity::Hike, &day(None), "C");
+        assert!(unreported.acceptable);
+        assert!(
+            unreported
+                .reasons
+                .contains(&"wind not reported".to_string())
+        );
+
+        // Without a wind penalty the day scores like a perfectly calm one
+        let calm = score_day(Activity::Hike, &day(Some(0.0)), "C");
+        assert_eq!(unreported.score, calm.score);
+        assert!(!calm.reasons.contains(&"wind not reported".to_string()));
+    }
+}
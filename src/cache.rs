@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use crate::error::AppError;
+use crate::tools::{ToolArgs, ToolKind};
+
+/// Key identifying a cached tool lookup
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    /// A lookup of one location by a tool, in a unit if it depends on one
+    Lookup {
+        tool: String,
+        location: String,
+        unit: Option<String>,
+    },
+    /// A whole tool result, by the tool's normalized arguments
+    Result { tool: ToolKind, args: ToolArgs },
+}
+
+impl CacheKey {
+    /// Creates a key for a tool lookup; the location is normalized to lowercase
+    pub fn new(tool: &str, location: &str, unit: Option<&str>) -> Self {
+        CacheKey::Lookup {
+            tool: tool.to_string(),
+            location: location.to_lowercase(),
+            unit: unit.map(str::to_string),
+        }
+    }
+
+    /// Creates a key for a whole tool result from its parsed arguments, asked on `today`.
+    ///
+    /// Parsing already normalized units and dropped the argument order, and normalizing
+    /// lowercases names and resolves relative days, so calls that must give the same result
+    /// share a key while "tomorrow" asked on different days does not.
+    pub fn for_args(tool: ToolKind, args: &ToolArgs, today: NaiveDate) -> Self {
+        CacheKey::Result {
+            tool,
+            args: args.normalized(today),
+        }
+    }
+}
+
+/// Result of a lookup stored in the cache
+#[derive(Debug, Clone)]
+pub enum Cached {
+    /// Successful tool result
+    Found(Value),
+    /// The API reported that the location does not exist, with its message
+    NotFound(String),
+}
+
+/// Cache entry with its fetch and expiration times
+struct Entry {
+    value: Cached,
+    fetched_at: DateTime<Local>,
+    expires_at: Instant,
+}
+
+/// In-memory TTL cache for tool lookups.
+///
+/// Expired entries are evicted lazily when they are next accessed. Concurrent lookups of
+/// the same key are coalesced so only one of them reaches the API.
+#[derive(Default)]
+pub struct Cache {
+    entries: Mutex<HashMap<CacheKey, Entry>>,
+    // The leader's outcome is shared as is, so waiters see the same error variant
+    in_flight: Mutex<HashMap<CacheKey, broadcast::Sender<Result<Value, AppError>>>>,
+}
+
+/// Removes an in-flight registration when the leading lookup finishes or is cancelled
+struct InFlight<'a> {
+    cache: &'a Cache,
+    key: CacheKey,
+}
+
+impl InFlight<'_> {
+    /// Unregisters the lookup and shares its result with the callers waiting on it
+    fn finish(self, result: &Result<Value, AppError>) {
+        let sender = self.cache.in_flight.lock().unwrap().remove(&self.key);
+        if let Some(sender) = sender {
+            // No receivers simply means nobody joined the lookup
+            let _ = sender.send(result.clone());
+        }
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        // Dropping the sender wakes up waiters, which then retry the lookup themselves
+        self.cache.in_flight.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl Cache {
+    /// Returns the cached value for a key, evicting it if it has expired.
+    ///
+    /// A `source` field in a cached result is rewritten to say it came from the cache.
+    pub fn get(&self, key: &CacheKey) -> Option<Cached> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => match &entry.value {
+                Cached::Found(value) => Some(Cached::Found(with_cache_source(
+                    value.clone(),
+                    entry.fetched_at,
+                ))),
+                not_found => Some(not_found.clone()),
+            },
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Stores a value for a key; a zero TTL disables caching
+    pub fn insert(&self, key: CacheKey, value: Cached, ttl: Duration) {
+        if ttl.is_zero() {
+            return;
+        }
+        let entry = Entry {
+            value,
+            fetched_at: Local::now(),
+            expires_at: Instant::now() + ttl,
+        };
+        self.entries.lock().unwrap().insert(key, entry);
+    }
+
+    /// Returns a cached result or runs `fetch` and caches its outcome.
+    ///
+    /// Successful results are kept for `ttl`, while `AppError::LocationNotFound` errors
+    /// are kept for `negative_ttl` so repeated lookups of a bad location skip the API.
+    /// Other errors are never cached.
+    ///
+    /// If an identical lookup is already in flight, this waits for it and returns its
+    /// result instead of calling `fetch`.
+    pub async fn get_or_fetch<F, Fut>(
+        &self,
+        key: CacheKey,
+        ttl: Duration,
+        negative_ttl: Duration,
+        fetch: F,
+    ) -> Result<Value, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, AppError>>,
+    {
+        loop {
+            match self.get(&key) {
+                Some(Cached::Found(value)) => {
+                    debug!("Cache hit for {:?}", key);
+                    return Ok(value);
+                }
+                Some(Cached::NotFound(message)) => {
+                    debug!("Negative cache hit for {:?}", key);
+                    return Err(AppError::LocationNotFound(message));
+                }
+                None => {}
+            }
+
+            // Join an identical lookup in flight, or register this one as the leader
+            let waiter = {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                match in_flight.get(&key) {
+                    Some(sender) => Some(sender.subscribe()),
+                    None => {
+                        in_flight.insert(key.clone(), broadcast::channel(1).0);
+                        None
+                    }
+                }
+            };
+
+            match waiter {
+                Some(mut receiver) => {
+                    debug!("Joining in-flight lookup for {:?}", key);
+                    if let Ok(result) = receiver.recv().await {
+                        return result;
+                    }
+                    // The leading lookup was cancelled; start over
+                }
+                None => break,
+            }
+        }
+
+        let in_flight = InFlight {
+            cache: self,
+            key: key.clone(),
+        };
+        let result = fetch().await;
+        match &result {
+            Ok(value) => self.insert(key, Cached::Found(value.clone()), ttl),
+            Err(AppError::LocationNotFound(message)) => {
+                self.insert(key, Cached::NotFound(message.clone()), negative_ttl)
+            }
+            Err(_) => {}
+        }
+        in_flight.finish(&result);
+        result
+    }
+}
+
+/// Marks a cached result's `source` as served from the cache, keeping the original source.
+fn with_cache_source(mut value: Value, fetched_at: DateTime<Local>) -> Value {
+    if let Some(source) = value.get_mut("source")
+        && let Some(original) = source.as_str()
+    {
+        *source = Value::String(format!(
+            "cache (fetched {} from {})",
+            fetched_at.format("%Y-%m-%d %H:%M"),
+            original
+        ));
+    }
+    value
+}
+
+/// Cache of final assistant answers keyed by a normalized form of the question.
+///
+/// Opt-in via `--semantic-cache`: answers are reused regardless of the conversation
+/// around the question, and answers about "right now" can go stale within the TTL.
+#[derive(Default)]
+pub struct AnswerCache {
+    entries: Mutex<HashMap<u64, (String, Instant)>>,
+}
+
+impl AnswerCache {
+    /// Returns the cached answer for a key, evicting it if it has expired
+    pub fn get(&self, key: u64) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some((answer, expires_at)) if *expires_at > Instant::now() => Some(answer.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Stores an answer for a key
+    pub fn insert(&self, key: u64, answer: String, ttl: Duration) {
+        let expires_at = Instant::now() + ttl;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, (answer, expires_at));
+    }
+}
+
+/// Derives an answer cache key from a question and the set of available tools.
+///
+/// The question is lowercased, trailing punctuation is dropped and whitespace is
+/// collapsed, so trivially reworded repeats ("Weather in Paris?" vs "weather  in paris")
+/// share a key. Tool names are part of the key so a different tool set never reuses
+/// an answer.
+pub fn semantic_key<'a>(question: &str, tool_names: impl IntoIterator<Item = &'a str>) -> u64 {
+    let normalized = question
+        .to_lowercase()
+        .trim_end_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace())
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut tool_names: Vec<&str> = tool_names.into_iter().collect();
+    tool_names.sort_unstable();
+
+    let mut hasher = DefaultHasher::new();
+    normalized.hash(&mut hasher);
+    tool_names.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use reqwest::header::HeaderMap;
+    use serde_json::json;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::http::Http;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn concurrent_identical_lookups_make_one_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "temp_c": 18.0 }))
+                    .set_delay(Duration::from_millis(100)),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let cache = Cache::default();
+        let http = Http::new(4, None, false);
+        let key = CacheKey::new("get_weather", "London,GB", Some("C"));
+        let lookup = || {
+            cache.get_or_fetch(key.clone(), TTL, TTL, || async {
+                let response = http.get(&server.uri(), &[], &HeaderMap::new()).await?;
+                Ok(serde_json::from_str(&response.body)?)
+            })
+        };
+
+        let (first, second) = tokio::join!(lookup(), lookup());
+
+        assert_eq!(first.unwrap(), json!({ "temp_c": 18.0 }));
+        assert_eq!(second.unwrap(), json!({ "temp_c": 18.0 }));
+        // The mock verifies on drop that exactly one request reached it
+    }
+
+    #[tokio::test]
+    async fn waiters_get_the_leaders_error_variant() {
+        let cache = Cache::default();
+        let fetches = AtomicUsize::new(0);
+        let key = CacheKey::new("get_current_time", "Tokyo,JP", None);
+        let lookup = || {
+            cache.get_or_fetch(key.clone(), TTL, TTL, || async {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Err(AppError::EnvVarNotSet("IP_GEOLOCATION_API_KEY".to_string()))
+            })
+        };
+
+        let (first, second) = tokio::join!(lookup(), lookup());
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        for result in [first, second] {
+            assert!(
+                matches!(result, Err(AppError::EnvVarNotSet(ref var)) if var == "IP_GEOLOCATION_API_KEY"),
+                "{:?}",
+                result
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn not_found_is_cached_for_the_negative_ttl() {
+        let cache = Cache::default();
+        let key = CacheKey::new("get_weather", "Nowhere,XX", None);
+        let result = cache
+            .get_or_fetch(key.clone(), TTL, TTL, || async {
+                Err(AppError::LocationNotFound(
+                    "No matching location".to_string(),
+                ))
+            })
+            .await;
+        assert!(matches!(result, Err(AppError::LocationNotFound(_))));
+
+        // A second lookup is answered from the cache without fetching
+        let result = cache
+            .get_or_fetch(key, TTL, TTL, || async { panic!("should not fetch") })
+            .await;
+        assert!(matches!(result, Err(AppError::LocationNotFound(_))));
+    }
+}
@@ -0,0 +1,230 @@
+use std::path::PathBuf;
+
+use clap::{ArgAction, Parser, ValueEnum};
+
+/// Command-line arguments for the weather and time assistant
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Gemini model answering the questions [default: gemini-2.0-flash]
+    #[arg(long, env = "GEMINI_MODEL")]
+    pub model: Option<String>,
+
+    /// TOML file with settings, keyed by environment variable names in lowercase; variables
+    /// and flags override it, and a missing file is ignored
+    #[arg(
+        long,
+        value_name = "FILE",
+        env = "CONFIG_FILE",
+        default_value = "config.toml"
+    )]
+    pub config: PathBuf,
+
+    /// Read the system prompt from this file instead of using the built-in one
+    #[arg(long, value_name = "FILE", env = "SYSTEM_PROMPT_FILE")]
+    pub system_prompt: Option<PathBuf>,
+
+    /// Screen-reader friendly output: no emoji and units spelled out in words
+    #[arg(long)]
+    pub accessible: bool,
+
+    /// Plain output without colors, also chosen by a non-empty NO_COLOR or a non-terminal stdout
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Skip the welcome banner
+    #[arg(long, short)]
+    pub quiet: bool,
+
+    /// Welcome message shown at startup instead of the built-in greeting; empty for none
+    #[arg(long, value_name = "TEXT")]
+    pub banner: Option<String>,
+
+    /// Text shown before each question is typed
+    #[arg(long, value_name = "TEXT", default_value = "> ")]
+    pub prompt_string: String,
+
+    /// Temperature unit used when the model doesn't ask for one, instead of the country's usual unit
+    #[arg(long, env = "TEMPERATURE_UNIT", value_parser = ["C", "F"], ignore_case = true)]
+    pub unit: Option<String>,
+
+    /// Print how long the model, each tool and the rest took after every turn
+    #[arg(long)]
+    pub profile: bool,
+
+    /// More output: -v for info, -vv for debug, -vvv for trace logs, instead of RUST_LOG
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Log line format; `json` emits one structured object per line with all span fields
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Print each answer to stdout as one JSON line and send logs to stderr, for scripts
+    #[arg(long, conflicts_with = "stream")]
+    pub json: bool,
+
+    /// Pretty-print JSON tool responses in debug logs
+    #[arg(long)]
+    pub pretty: bool,
+
+    /// Reuse answers to reworded repeats of a question (prefix a question with `!fresh` to bypass)
+    #[arg(long)]
+    pub semantic_cache: bool,
+
+    /// How long answers are kept by --semantic-cache, in seconds
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 600,
+        requires = "semantic_cache"
+    )]
+    pub semantic_cache_ttl: u64,
+
+    /// Answer simple "weather in City,CC" and "time in City,CC" questions directly from the tools
+    #[arg(long)]
+    pub fast_path: bool,
+
+    /// Recover known fields when an API response no longer matches the expected shape
+    #[arg(long)]
+    pub lenient_parse: bool,
+
+    /// How weather fields the API did not report appear in tool responses
+    #[arg(long, value_enum, default_value_t = MissingValue::Null)]
+    pub missing_value: MissingValue,
+
+    /// Probe each configured API at startup and exit with a clear error if one rejects the key
+    #[arg(long, overrides_with = "no_warmup")]
+    pub warmup: bool,
+
+    /// Skip the startup probe, overriding an earlier --warmup
+    #[arg(long)]
+    pub no_warmup: bool,
+
+    /// Download the latest WeatherAPI condition list at startup instead of the bundled one
+    #[arg(long)]
+    pub refresh_conditions: bool,
+
+    /// Answer tool calls from fixed sample data instead of the weather and time APIs, no keys needed
+    #[arg(long, conflicts_with = "refresh_conditions")]
+    pub offline: bool,
+
+    /// Append tool results in the order the model requested them, not completion order
+    #[arg(long)]
+    pub ordered_tools: bool,
+
+    /// Print answers as the model generates them instead of all at once
+    #[arg(long, conflicts_with = "accessible")]
+    pub stream: bool,
+
+    /// Show each compared city's weather as soon as it arrives, before the model's answer
+    #[arg(long)]
+    pub stream_progress: bool,
+
+    /// Send all tool results of a turn to the model in one message instead of one message each
+    #[arg(long)]
+    pub batch_tool_responses: bool,
+
+    /// Most tool calls run for a single model turn; extra calls get an error response
+    #[arg(long, value_name = "COUNT", default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_tool_calls: u32,
+
+    /// How many times to resend a request when the model returns an empty response
+    #[arg(long, value_name = "COUNT", default_value_t = 1)]
+    pub empty_retries: u32,
+
+    /// Ask the model to rephrase an answer that just repeats raw tool JSON
+    #[arg(long)]
+    pub require_natural_language: bool,
+
+    /// Answer shown when a turn fails; the technical error goes to the logs
+    #[arg(
+        long,
+        value_name = "TEXT",
+        default_value = "Sorry, something went wrong while answering. Please try again."
+    )]
+    pub failure_message: String,
+
+    /// Sampling temperature from 0.0 (most deterministic) to 2.0 (most varied)
+    #[arg(long, value_name = "VALUE")]
+    pub temperature: Option<f64>,
+
+    /// Most tokens the model may generate for one response
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_tokens: Option<u32>,
+
+    /// Stop generating when the model emits this sequence (repeatable)
+    #[arg(long = "stop", value_name = "SEQUENCE")]
+    pub stop_sequences: Vec<String>,
+
+    /// Send request, error and latency metrics to a StatsD server
+    #[arg(long, value_name = "HOST:PORT")]
+    pub statsd_addr: Option<String>,
+
+    /// Load and save the conversation as a named session (switch with `/switch <name>`)
+    #[arg(long, value_name = "NAME")]
+    pub session: Option<String>,
+
+    /// Directory where named sessions are stored
+    #[arg(long, value_name = "DIR", default_value = "sessions")]
+    pub sessions_dir: PathBuf,
+
+    /// Remember the conversation between runs in FILE (default ~/.gemini-tools/history.json)
+    #[arg(long, value_name = "FILE", num_args = 0..=1, conflicts_with = "session")]
+    pub history: Option<Option<PathBuf>>,
+
+    /// Re-run the user turns of a saved JSON transcript and compare old and new answers.
+    ///
+    /// Replays send every turn to the model, so options that only shape interactive
+    /// sessions are rejected alongside it rather than silently ignored.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["session", "sessions_dir", "history", "stream", "json", "idle_timeout", "fast_path", "semantic_cache"]
+    )]
+    pub replay: Option<PathBuf>,
+
+    /// Answer this one question, print the answer and exit instead of starting a session
+    #[arg(long, value_name = "QUESTION", conflicts_with_all = ["replay", "idle_timeout"])]
+    pub once: Option<String>,
+
+    /// End the session after this many seconds without input
+    #[arg(long, value_name = "SECS")]
+    pub idle_timeout: Option<u64>,
+
+    /// What to do when --idle-timeout elapses
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = IdleAction::Exit,
+        requires = "idle_timeout"
+    )]
+    pub idle_action: IdleAction,
+}
+
+/// Format of log output
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line, for log aggregation systems
+    Json,
+}
+
+/// Value given to a weather field the API did not report
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum MissingValue {
+    /// JSON `null`
+    Null,
+    /// The string "unavailable"
+    Unavailable,
+}
+
+/// Action taken when the interactive session has been idle for too long
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum IdleAction {
+    /// Exit the application
+    Exit,
+    /// Forget the conversation and wait for a new one
+    Reset,
+}
@@ -0,0 +1,54 @@
+use std::env;
+use std::io::{self, IsTerminal};
+
+use nu_ansi_term::Color;
+
+use crate::cli::{Cli, LogFormat};
+
+/// Who a line of terminal output comes from, each shown in its own color
+#[derive(Debug, Clone, Copy)]
+pub enum Role {
+    /// Answers and other assistant messages, in green
+    Assistant,
+    /// The user's questions, in cyan
+    User,
+    /// Tool calls and their timings, in yellow
+    Tool,
+}
+
+impl Role {
+    fn color(self) -> Color {
+        match self {
+            Role::Assistant => Color::Green,
+            Role::User => Color::Cyan,
+            Role::Tool => Color::Yellow,
+        }
+    }
+}
+
+/// Whether output is colored: never with `--no-color`, a non-empty `NO_COLOR`, JSON logs
+/// or when the logs don't go to a terminal, so piped output stays plain.
+pub fn enabled(cli: &Cli) -> bool {
+    if cli.no_color || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    if matches!(cli.log_format, LogFormat::Json) {
+        return false;
+    }
+
+    // With --json the logs go to stderr, leaving stdout to the answers
+    if cli.json {
+        io::stderr().is_terminal()
+    } else {
+        io::stdout().is_terminal()
+    }
+}
+
+/// Wraps `text` in the role's color, or returns it unchanged when color is off.
+pub fn paint(role: Role, text: &str, enabled: bool) -> String {
+    if enabled {
+        role.color().paint(text).to_string()
+    } else {
+        text.to_string()
+    }
+}
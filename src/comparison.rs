@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+
+use crate::units::UnitSystem;
+use crate::weather::response::ForecastDay;
+
+// Humidity difference, in percentage points, below which two cities count as similarly humid
+const SIMILAR_HUMIDITY_POINTS: i32 = 5;
+
+/// Current weather of one city in a comparison, with the temperature in both units
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CityWeather {
+    /// City name as requested
+    pub city: String,
+    /// ISO country code as requested
+    pub country: String,
+    /// Temperature in Celsius
+    pub temp_c: f64,
+    /// Temperature in Fahrenheit
+    pub temp_f: f64,
+    /// Human-readable description of the weather condition
+    pub condition: String,
+    /// Humidity percentage (0-100), if reported
+    pub humidity: Option<i32>,
+    /// Where the data came from (e.g. "weatherapi" or "cache (fetched ... from weatherapi)")
+    pub source: String,
+}
+
+/// Forecast temperature swing of one city in a comparison
+#[derive(Serialize, Debug, Clone)]
+pub struct CitySwing {
+    /// City name as requested
+    pub city: String,
+    /// ISO country code as requested
+    pub country: String,
+    /// Lowest forecast temperature over the period
+    pub low: f64,
+    /// Highest forecast temperature over the period
+    pub high: f64,
+    /// Difference between the highest and lowest temperature
+    pub swing: f64,
+}
+
+/// Computes the min-to-max temperature range over a forecast, or `None` if it has no days.
+pub fn temperature_swing(
+    city: &str,
+    country: &str,
+    days: &[ForecastDay],
+    units: UnitSystem,
+) -> Option<CitySwing> {
+    let (lows, highs): (Vec<f64>, Vec<f64>) = days
+        .iter()
+        .map(|day| match units {
+            UnitSystem::Metric => (day.day.mintemp_c, day.day.maxtemp_c),
+            UnitSystem::Imperial => (day.day.mintemp_f, day.day.maxtemp_f),
+        })
+        .unzip();
+    let low = lows.into_iter().reduce(f64::min)?;
+    let high = highs.into_iter().reduce(f64::max)?;
+
+    Some(CitySwing {
+        city: city.to_string(),
+        country: country.to_string(),
+        low,
+        high,
+        swing: high - low,
+    })
+}
+
+/// The city with the largest temperature swing, if any
+pub fn largest_swing(swings: &[CitySwing]) -> Option<&CitySwing> {
+    swings.iter().max_by(|a, b| a.swing.total_cmp(&b.swing))
+}
+
+/// Describes how every other city compares with the first one, in temperature and humidity.
+///
+/// e.g. "Rome is 5°C warmer than Madrid and less humid." Returns an empty string for
+/// fewer than two cities.
+pub fn summarize_comparison(cities: &[CityWeather], units: UnitSystem) -> String {
+    let Some((base, others)) = cities.split_first() else {
+        return String::new();
+    };
+
+    others
+        .iter()
+        .map(|city| compare_pair(city, base, units))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// One sentence comparing `city` with `base`.
+fn compare_pair(city: &CityWeather, base: &CityWeather, units: UnitSystem) -> String {
+    let (difference, symbol) = match units {
+        UnitSystem::Metric => (city.temp_c - base.temp_c, "°C"),
+        UnitSystem::Imperial => (city.temp_f - base.temp_f, "°F"),
+    };
+    let difference = difference.round();
+
+    let temperature = if difference > 0.0 {
+        format!(
+            "{} is {}{} warmer than {}",
+            city.city, difference, symbol, base.city
+        )
+    } else if difference < 0.0 {
+        format!(
+            "{} is {}{} cooler than {}",
+            city.city, -difference, symbol, base.city
+        )
+    } else {
+        format!("{} is about as warm as {}", city.city, base.city)
+    };
+
+    // Humidity is only compared when both cities report it
+    let (Some(humidity), Some(base_humidity)) = (city.humidity, base.humidity) else {
+        return format!("{}.", temperature);
+    };
+    let humidity = humidity - base_humidity;
+    let humidity = if humidity >= SIMILAR_HUMIDITY_POINTS {
+        "and more humid"
+    } else if humidity <= -SIMILAR_HUMIDITY_POINTS {
+        "and less humid"
+    } else {
+        "with similar humidity"
+    };
+
+    format!("{} {}.", temperature, humidity)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::units;
+
+    /// A compared city with the temperature given in Celsius
+    fn city(name: &str, temp_c: f64, humidity: Option<i32>) -> CityWeather {
+        CityWeather {
+            city: name.to_string(),
+            country: "XX".to_string(),
+            temp_c,
+            temp_f: units::fahrenheit(temp_c),
+            condition: "Sunny".to_string(),
+            humidity,
+            source: "weatherapi".to_string(),
+        }
+    }
+
+    #[test]
+    fn each_city_entry_has_both_temperatures() {
+        let entry = serde_json::to_value(city("Rome", 20.0, Some(40))).unwrap();
+        assert_eq!(
+            entry,
+            json!({
+                "city": "Rome",
+                "country": "XX",
+                "temp_c": 20.0,
+                "temp_f": 68.0,
+                "condition": "Sunny",
+                "humidity": 40,
+                "source": "weatherapi",
+            })
+        );
+
+        let entry = serde_json::to_value(city("Oslo", 3.0, None)).unwrap();
+        assert_eq!(entry["humidity"], Value::Null);
+        assert!(entry["temp_c"].is_f64() && entry["temp_f"].is_f64());
+    }
+
+    #[test]
+    fn summary_compares_every_city_with_the_first() {
+        let cities = [
+            city("Madrid", 25.0, Some(60)),
+            city("Rome", 30.0, Some(40)),
+            city("Oslo", 10.0, Some(80)),
+            city("Lisbon", 25.3, Some(62)),
+        ];
+        assert_eq!(
+            summarize_comparison(&cities, UnitSystem::Metric),
+            "Rome is 5°C warmer than Madrid and less humid. \
+             Oslo is 15°C cooler than Madrid and more humid. \
+             Lisbon is about as warm as Madrid with similar humidity."
+        );
+    }
+
+    #[test]
+    fn summary_uses_the_unit_system() {
+        let cities = [city("Madrid", 25.0, None), city("Rome", 30.0, None)];
+        assert_eq!(
+            summarize_comparison(&cities, UnitSystem::Imperial),
+            "Rome is 9°F warmer than Madrid."
+        );
+    }
+
+    #[test]
+    fn summary_skips_humidity_unless_both_cities_report_it() {
+        let cities = [city("Madrid", 25.0, None), city("Rome", 20.0, Some(40))];
+        assert_eq!(
+            summarize_comparison(&cities, UnitSystem::Metric),
+            "Rome is 5°C cooler than Madrid."
+        );
+    }
+
+    #[test]
+    fn summary_is_empty_without_a_second_city() {
+        assert_eq!(summarize_comparison(&[], UnitSystem::Metric), "");
+        let cities = [city("Madrid", 25.0, Some(60))];
+        assert_eq!(summarize_comparison(&cities, UnitSystem::Metric), "");
+    }
+}
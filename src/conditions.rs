@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use reqwest::header::HeaderMap;
+use tracing::{debug, warn};
+
+use crate::error::AppError;
+use crate::http::Http;
+
+// Official WeatherAPI list of condition codes with day and night texts
+const CONDITIONS_URL: &str = "https://www.weatherapi.com/docs/weather_conditions.json";
+
+// Snapshot of the official list, used offline and when a refresh fails
+const BUNDLED_CONDITIONS: &str = include_str!("../data/weather_conditions.json");
+
+/// One entry of the WeatherAPI condition list
+#[derive(serde::Deserialize, Debug)]
+struct Condition {
+    /// WeatherAPI condition code (e.g., 1000)
+    code: i32,
+    /// Text used during the day (e.g., "Sunny")
+    day: String,
+    /// Text used at night (e.g., "Clear")
+    night: String,
+}
+
+// Condition texts indexed by code, loaded once on first use or by `refresh`
+static CONDITIONS: OnceLock<HashMap<i32, Condition>> = OnceLock::new();
+
+/// Returns the official text for a condition code, in its day or night variant.
+///
+/// # Returns
+/// * The condition text, or `None` for a code missing from the list
+pub fn lookup_condition(code: i32, is_day: bool) -> Option<&'static str> {
+    let condition = conditions().get(&code)?;
+    Some(if is_day {
+        &condition.day
+    } else {
+        &condition.night
+    })
+}
+
+/// Replaces the bundled condition list with the latest official one.
+///
+/// Must be called before the first lookup; on failure the bundled snapshot stays in use.
+pub async fn refresh(http: &Http) {
+    match fetch(http).await {
+        Ok(table) => {
+            debug!("Loaded {} weather conditions from WeatherAPI", table.len());
+            if CONDITIONS.set(table).is_err() {
+                warn!("Weather conditions were already loaded, keeping them");
+            }
+        }
+        Err(e) => warn!(
+            "Failed to refresh weather conditions, using the bundled list: {}",
+            e
+        ),
+    }
+}
+
+/// Downloads and indexes the official condition list.
+async fn fetch(http: &Http) -> Result<HashMap<i32, Condition>, AppError> {
+    let response = http.get(CONDITIONS_URL, &[], &HeaderMap::new()).await?;
+    if !response.status.is_success() {
+        return Err(AppError::ApiRequestFailed(format!(
+            "Failed to fetch weather conditions: {}",
+            response.status
+        )));
+    }
+    index(&response.body)
+}
+
+/// The loaded condition list, falling back to the bundled snapshot.
+fn conditions() -> &'static HashMap<i32, Condition> {
+    CONDITIONS
+        .get_or_init(|| index(BUNDLED_CONDITIONS).expect("bundled weather conditions are valid"))
+}
+
+/// Parses a condition list and indexes it by code.
+fn index(json: &str) -> Result<HashMap<i32, Condition>, AppError> {
+    let conditions: Vec<Condition> = serde_json::from_str(json)?;
+    Ok(conditions.into_iter().map(|c| (c.code, c)).collect())
+}
@@ -0,0 +1,1179 @@
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, Command, CommandFactory, FromArgMatches};
+use genai::chat::ChatOptions;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tracing::warn;
+
+use crate::cli::{Cli, IdleAction, MissingValue};
+use crate::colors;
+use crate::dates::BusinessHours;
+use crate::error::AppError;
+use crate::gemini;
+use crate::geo_location;
+use crate::history;
+use crate::retry::RetryPolicy;
+use crate::rounding;
+use crate::weather;
+use crate::weather::openweathermap;
+
+// Gemini model used unless `--model` or `GEMINI_MODEL` names another
+const DEFAULT_MODEL: &str = "gemini-2.0-flash";
+
+// Default cache lifetimes in seconds
+const DEFAULT_WEATHER_CACHE_TTL_SECS: u64 = 300;
+const DEFAULT_TIME_CACHE_TTL_SECS: u64 = 60;
+const DEFAULT_NEGATIVE_CACHE_TTL_SECS: u64 = 30;
+const DEFAULT_FORECAST_CACHE_TTL_SECS: u64 = 600;
+
+// Default forecast horizon in days, matching the WeatherAPI free tier
+const DEFAULT_MAX_FORECAST_DAYS: usize = 3;
+
+// Default timeout for one outbound HTTP request in seconds
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 10;
+
+// Default local business hours for get_business_hours_status
+const DEFAULT_BUSINESS_HOURS: &str = "09:00-17:00";
+
+// Sampling temperatures accepted by the Gemini API
+const MIN_TEMPERATURE: f64 = 0.0;
+const MAX_TEMPERATURE: f64 = 2.0;
+
+// Default estimated token budget of a conversation, well within the Gemini context windows
+const DEFAULT_MAX_CONTEXT_TOKENS: usize = 500_000;
+
+// Default cap on simultaneous outbound HTTP requests
+const DEFAULT_MAX_HTTP_CONNECTIONS: usize = 8;
+
+// Default retry policy for transient API failures
+const DEFAULT_RETRY_ATTEMPTS: usize = 3;
+const DEFAULT_RETRY_BASE_DELAY_MS: u64 = 250;
+
+// Default WeatherAPI version path segment; IPGeolocation is unversioned by default
+const DEFAULT_WEATHER_API_VERSION: &str = "v1";
+
+// Default Gemini API version path segment, the one serving tools and streaming
+const DEFAULT_GEMINI_API_VERSION: &str = "v1beta";
+
+/// Application settings resolved once at startup
+#[derive(Debug)]
+pub struct Config {
+    /// Gemini model used for every call (`--model`, `GEMINI_MODEL`)
+    pub model: String,
+    /// Custom system prompt, replacing the built-in one (`--system-prompt`)
+    pub system_prompt: Option<String>,
+    /// Screen-reader friendly output (`--accessible`)
+    pub accessible: bool,
+    /// Color output by role (off with `--no-color`, `NO_COLOR` or a non-terminal)
+    pub color: bool,
+    /// Skip the welcome banner (`--quiet`)
+    pub quiet: bool,
+    /// Banner and prompt shown in interactive sessions (`--banner`, `--prompt-string`)
+    pub persona: Persona,
+    /// Temperature unit used when a tool call omits one (`--unit`)
+    pub default_unit: Option<&'static str>,
+    /// Print a timing breakdown after every turn (`--profile`)
+    pub profile: bool,
+    /// Pretty-print JSON tool responses in debug logs (`--pretty`)
+    pub pretty: bool,
+    /// How long final answers are reused, when enabled (`--semantic-cache`)
+    pub semantic_cache_ttl: Option<Duration>,
+    /// Answer simple weather and time questions without the model (`--fast-path`)
+    pub fast_path: bool,
+    /// Probe the APIs at startup (`--warmup`, `--no-warmup`)
+    pub warmup: bool,
+    /// Download the latest condition list at startup (`--refresh-conditions`)
+    pub refresh_conditions: bool,
+    /// Append tool results in request order (`--ordered-tools`)
+    pub ordered_tools: bool,
+    /// File the conversation is kept in between runs (`--history`)
+    pub history: Option<PathBuf>,
+    /// How long the session may go without input (`--idle-timeout`)
+    pub idle_timeout: Option<Duration>,
+    /// What happens when the idle timeout elapses (`--idle-action`)
+    pub idle_action: IdleAction,
+    /// Answer tool calls from canned data instead of the weather and time APIs (`--offline`)
+    pub offline: bool,
+    /// Print answers as JSON lines on stdout (`--json`)
+    pub json: bool,
+    /// Print answers as they are generated (`--stream`)
+    pub stream: bool,
+    /// Show compared cities as they arrive (`--stream-progress`)
+    pub stream_progress: bool,
+    /// Bundle a turn's tool results into one message (`--batch-tool-responses`)
+    pub batch_tool_responses: bool,
+    /// Value of weather fields the API did not report (`--missing-value`)
+    pub missing_value: MissingValue,
+    /// Most tool calls run per model turn (`--max-tool-calls`)
+    pub max_tool_calls: usize,
+    /// Retries of a model call that returned no content (`--empty-retries`)
+    pub empty_response_retries: u32,
+    /// Re-prompt answers that quote raw tool output (`--require-natural-language`)
+    pub require_natural_language: bool,
+    /// Answer shown in place of a failed turn (`--failure-message`)
+    pub failure_message: String,
+    /// Options passed with every model call (`--temperature`, `--max-tokens`, `--stop`)
+    pub chat_options: ChatOptions,
+    /// Gemini connection settings (`GEMINI_API_BASE`, `GEMINI_API_VERSION`,
+    /// `GEMINI_EXTRA_HEADERS`)
+    pub gemini: ApiConfig,
+    /// WeatherAPI connection settings (`WEATHER_API_BASE`, `WEATHER_API_VERSION`,
+    /// `WEATHER_EXTRA_HEADERS`)
+    pub weather: ApiConfig,
+    /// Service answering current weather lookups (`WEATHER_PROVIDER`)
+    pub weather_provider: weather::Provider,
+    /// OpenWeatherMap connection settings (`OPENWEATHERMAP_API_BASE`,
+    /// `OPENWEATHERMAP_EXTRA_HEADERS`)
+    pub openweathermap: ApiConfig,
+    /// IPGeolocation connection settings (`GEO_API_BASE`, `GEO_API_VERSION`,
+    /// `GEO_EXTRA_HEADERS`)
+    pub geo: ApiConfig,
+    /// Most forecast days requested from WeatherAPI (`WEATHER_MAX_FORECAST_DAYS`)
+    pub max_forecast_days: u32,
+    /// How long weather lookups are cached (`WEATHER_CACHE_TTL_SECS`)
+    pub weather_cache_ttl: Duration,
+    /// How long time lookups are cached (`TIME_CACHE_TTL_SECS`)
+    pub time_cache_ttl: Duration,
+    /// How long forecast-based tool results are cached (`FORECAST_CACHE_TTL_SECS`)
+    pub forecast_cache_ttl: Duration,
+    /// How long "location not found" results are cached (`NEGATIVE_CACHE_TTL_SECS`)
+    pub negative_cache_ttl: Duration,
+    /// Estimated tokens a conversation may take before its oldest turns are dropped
+    /// (`MAX_CONTEXT_TOKENS`)
+    pub max_context_tokens: usize,
+    /// Cap on simultaneous outbound HTTP requests (`MAX_HTTP_CONNECTIONS`)
+    pub max_http_connections: usize,
+    /// Longest an outbound HTTP request may take, if limited (`REQUEST_TIMEOUT_SECS`)
+    pub request_timeout: Option<Duration>,
+    /// Window counted as business hours in every city (`BUSINESS_HOURS`)
+    pub business_hours: BusinessHours,
+    /// Decimal places numbers in tool responses are rounded to (`OUTPUT_PRECISION`)
+    pub output_precision: u32,
+    /// Weather and time API keys that are set (`WEATHER_API_KEY`, `OPENWEATHERMAP_API_KEY`,
+    /// `IP_GEOLOCATION_API_KEY`)
+    pub api_keys: ApiKeys,
+}
+
+impl Config {
+    /// Builds the configuration from command-line arguments, environment variables and the
+    /// config file (`--config`), in that order of precedence.
+    ///
+    /// # Returns
+    /// * `Config` with defaults for every unset variable
+    /// * Error if a variable is set to an invalid value
+    pub fn new(cli: &Cli, settings: &Settings) -> Result<Config, AppError> {
+        let retry = retry_policy_setting(settings)?;
+        let system_prompt = match &cli.system_prompt {
+            Some(path) => Some(path.clone()),
+            None => settings.get("SYSTEM_PROMPT_FILE")?.map(PathBuf::from),
+        };
+        let unit = match &cli.unit {
+            Some(unit) => Some(unit.clone()),
+            None => settings.get("TEMPERATURE_UNIT")?,
+        };
+
+        Ok(Config {
+            model: match &cli.model {
+                Some(model) => model.clone(),
+                None => settings
+                    .get("GEMINI_MODEL")?
+                    .unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            },
+            system_prompt: match system_prompt {
+                Some(path) => system_prompt_from_file(&path)?,
+                None => None,
+            },
+            accessible: cli.accessible,
+            color: colors::enabled(cli),
+            quiet: cli.quiet,
+            persona: Persona {
+                banner: cli.banner.clone(),
+                prompt: cli.prompt_string.clone(),
+            },
+            default_unit: match unit.as_deref() {
+                Some(unit) if unit.eq_ignore_ascii_case("F") => Some("F"),
+                Some(unit) if unit.eq_ignore_ascii_case("C") => Some("C"),
+                Some(unit) => {
+                    return Err(AppError::InvalidConfig(format!(
+                        "TEMPERATURE_UNIT: expected 'C' or 'F', got '{}'",
+                        unit
+                    )));
+                }
+                None => None,
+            },
+            profile: cli.profile,
+            pretty: cli.pretty,
+            semantic_cache_ttl: cli
+                .semantic_cache
+                .then(|| Duration::from_secs(cli.semantic_cache_ttl)),
+            fast_path: cli.fast_path,
+            warmup: cli.warmup && !cli.no_warmup,
+            refresh_conditions: cli.refresh_conditions,
+            ordered_tools: cli.ordered_tools,
+            history: match &cli.history {
+                Some(Some(path)) => Some(path.clone()),
+                Some(None) => Some(history::default_path()?),
+                None => None,
+            },
+            idle_timeout: cli.idle_timeout.map(Duration::from_secs),
+            idle_action: cli.idle_action,
+            offline: cli.offline,
+            json: cli.json,
+            stream: cli.stream,
+            stream_progress: cli.stream_progress,
+            batch_tool_responses: cli.batch_tool_responses,
+            missing_value: cli.missing_value,
+            max_tool_calls: cli.max_tool_calls as usize,
+            empty_response_retries: cli.empty_retries,
+            require_natural_language: cli.require_natural_language,
+            failure_message: cli.failure_message.clone(),
+            chat_options: chat_options(cli)?,
+            gemini: ApiConfig {
+                base_url: base_url_setting(settings, "GEMINI_API_BASE", gemini::GEMINI_BASE_URL)?,
+                version: version_setting(
+                    settings,
+                    "GEMINI_API_VERSION",
+                    Some(DEFAULT_GEMINI_API_VERSION),
+                )?,
+                headers: headers_setting(settings, "GEMINI_EXTRA_HEADERS")?,
+                lenient: cli.lenient_parse,
+                retry,
+            },
+            weather: ApiConfig {
+                base_url: base_url_setting(
+                    settings,
+                    "WEATHER_API_BASE",
+                    weather::WEATHER_BASE_URL,
+                )?,
+                version: version_setting(
+                    settings,
+                    "WEATHER_API_VERSION",
+                    Some(DEFAULT_WEATHER_API_VERSION),
+                )?,
+                headers: headers_setting(settings, "WEATHER_EXTRA_HEADERS")?,
+                lenient: cli.lenient_parse,
+                retry,
+            },
+            weather_provider: weather_provider_setting(settings)?,
+            openweathermap: ApiConfig {
+                base_url: base_url_setting(
+                    settings,
+                    "OPENWEATHERMAP_API_BASE",
+                    openweathermap::OPENWEATHERMAP_BASE_URL,
+                )?,
+                version: None,
+                headers: headers_setting(settings, "OPENWEATHERMAP_EXTRA_HEADERS")?,
+                lenient: cli.lenient_parse,
+                retry,
+            },
+            geo: ApiConfig {
+                base_url: base_url_setting(
+                    settings,
+                    "GEO_API_BASE",
+                    geo_location::GEO_LOCATION_BASE_URL,
+                )?,
+                version: version_setting(settings, "GEO_API_VERSION", None)?,
+                headers: headers_setting(settings, "GEO_EXTRA_HEADERS")?,
+                lenient: cli.lenient_parse,
+                retry,
+            },
+            max_forecast_days: max_forecast_days_setting(settings)?,
+            weather_cache_ttl: secs_setting(
+                settings,
+                "WEATHER_CACHE_TTL_SECS",
+                DEFAULT_WEATHER_CACHE_TTL_SECS,
+            )?,
+            time_cache_ttl: secs_setting(
+                settings,
+                "TIME_CACHE_TTL_SECS",
+                DEFAULT_TIME_CACHE_TTL_SECS,
+            )?,
+            forecast_cache_ttl: secs_setting(
+                settings,
+                "FORECAST_CACHE_TTL_SECS",
+                DEFAULT_FORECAST_CACHE_TTL_SECS,
+            )?,
+            negative_cache_ttl: secs_setting(
+                settings,
+                "NEGATIVE_CACHE_TTL_SECS",
+                DEFAULT_NEGATIVE_CACHE_TTL_SECS,
+            )?,
+            max_context_tokens: count_setting(
+                settings,
+                "MAX_CONTEXT_TOKENS",
+                DEFAULT_MAX_CONTEXT_TOKENS,
+            )?,
+            max_http_connections: count_setting(
+                settings,
+                "MAX_HTTP_CONNECTIONS",
+                DEFAULT_MAX_HTTP_CONNECTIONS,
+            )?,
+            request_timeout: Some(secs_setting(
+                settings,
+                "REQUEST_TIMEOUT_SECS",
+                DEFAULT_REQUEST_TIMEOUT_SECS,
+            )?)
+            .filter(|timeout| !timeout.is_zero()),
+            business_hours: business_hours_setting(settings)?,
+            output_precision: precision_setting(settings)?,
+            api_keys: ApiKeys(
+                API_KEY_VARS
+                    .iter()
+                    .filter_map(|var| Some((var.to_string(), settings.env.get(*var)?.clone())))
+                    .collect(),
+            ),
+        })
+    }
+}
+
+/// Reads a custom system prompt, falling back to the built-in one if the file can't be read.
+///
+/// Trailing whitespace is trimmed; a file with nothing else in it is an error rather than
+/// a silent fallback, since an empty prompt is almost certainly a mistake.
+fn system_prompt_from_file(path: &Path) -> Result<Option<String>, AppError> {
+    let prompt = match fs::read_to_string(path) {
+        Ok(prompt) => prompt,
+        Err(e) => {
+            warn!(
+                "Can't read system prompt from {}, using the default: {}",
+                path.display(),
+                e
+            );
+            return Ok(None);
+        }
+    };
+    let prompt = prompt.trim_end();
+    if prompt.trim().is_empty() {
+        return Err(AppError::InvalidConfig(format!(
+            "--system-prompt: {} is empty",
+            path.display()
+        )));
+    }
+    Ok(Some(prompt.to_string()))
+}
+
+/// Builds the model call options from command-line arguments.
+fn chat_options(cli: &Cli) -> Result<ChatOptions, AppError> {
+    if cli.stop_sequences.iter().any(|s| s.is_empty()) {
+        return Err(AppError::InvalidConfig(
+            "--stop: stop sequences must not be empty".to_string(),
+        ));
+    }
+
+    let mut options = ChatOptions::default().with_stop_sequences(cli.stop_sequences.clone());
+    if let Some(temperature) = cli.temperature {
+        if !(MIN_TEMPERATURE..=MAX_TEMPERATURE).contains(&temperature) {
+            return Err(AppError::InvalidConfig(format!(
+                "--temperature: expected a value from {:.1} to {:.1}, got {}",
+                MIN_TEMPERATURE, MAX_TEMPERATURE, temperature
+            )));
+        }
+        options = options.with_temperature(temperature);
+    }
+    if let Some(max_tokens) = cli.max_tokens {
+        options = options.with_max_tokens(max_tokens);
+    }
+    Ok(options)
+}
+
+/// How the assistant presents itself in an interactive session
+#[derive(Debug)]
+pub struct Persona {
+    /// Welcome message replacing the built-in greeting; empty to show none
+    pub banner: Option<String>,
+    /// Prompt printed before each question (default "> ")
+    pub prompt: String,
+}
+
+/// Connection settings for one external API
+#[derive(Debug)]
+pub struct ApiConfig {
+    /// Scheme and host the endpoints live under, without a trailing slash
+    pub base_url: String,
+    /// Version path segment (e.g. "v1"), if the API uses one
+    pub version: Option<String>,
+    /// Extra headers sent with every request
+    pub headers: HeaderMap,
+    /// Attempt best-effort recovery when a response fails to parse
+    pub lenient: bool,
+    /// Retries of rate-limited, unavailable or unreachable requests
+    pub retry: RetryPolicy,
+}
+
+impl ApiConfig {
+    /// Builds the URL of an endpoint, inserting the version segment after the base URL
+    pub fn endpoint(&self, path: &str) -> String {
+        match &self.version {
+            Some(version) => format!("{}/{}/{}", self.base_url, version, path),
+            None => format!("{}/{}", self.base_url, path),
+        }
+    }
+}
+
+// Environment variables holding the weather and time API keys; they are only read from the
+// environment, so keys stay out of config files
+const API_KEY_VARS: &[&str] = &[
+    "WEATHER_API_KEY",
+    "OPENWEATHERMAP_API_KEY",
+    "IP_GEOLOCATION_API_KEY",
+];
+
+/// API keys by the variable they were read from
+pub struct ApiKeys(HashMap<String, String>);
+
+impl ApiKeys {
+    /// The key in `var`, if it is set
+    pub fn get(&self, var: &str) -> Option<&str> {
+        self.0.get(var).map(String::as_str)
+    }
+}
+
+// Lists which keys are set without printing them
+impl std::fmt::Debug for ApiKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+// Environment variables that can also be set in the config file, under their lowercase names
+const FILE_VARS: &[&str] = &[
+    "GEMINI_MODEL",
+    "GEMINI_API_BASE",
+    "GEMINI_API_VERSION",
+    "GEMINI_EXTRA_HEADERS",
+    "SYSTEM_PROMPT_FILE",
+    "TEMPERATURE_UNIT",
+    "WEATHER_PROVIDER",
+    "WEATHER_API_BASE",
+    "WEATHER_API_VERSION",
+    "WEATHER_EXTRA_HEADERS",
+    "OPENWEATHERMAP_API_BASE",
+    "OPENWEATHERMAP_EXTRA_HEADERS",
+    "GEO_API_BASE",
+    "GEO_API_VERSION",
+    "GEO_EXTRA_HEADERS",
+    "WEATHER_MAX_FORECAST_DAYS",
+    "WEATHER_CACHE_TTL_SECS",
+    "TIME_CACHE_TTL_SECS",
+    "FORECAST_CACHE_TTL_SECS",
+    "NEGATIVE_CACHE_TTL_SECS",
+    "MAX_CONTEXT_TOKENS",
+    "MAX_HTTP_CONNECTIONS",
+    "REQUEST_TIMEOUT_SECS",
+    "HTTP_RETRY_ATTEMPTS",
+    "HTTP_RETRY_BASE_DELAY_MS",
+    "BUSINESS_HOURS",
+    "OUTPUT_PRECISION",
+];
+
+// Options that choose what a run does rather than how, so they are only taken from the
+// command line
+const COMMAND_LINE_ONLY: &[&str] = &["config", "replay", "once", "help", "version"];
+
+/// Settings from the environment, backed by the optional TOML config file (`--config`).
+///
+/// File keys are the environment variable names in lowercase, so `request_timeout_secs = 30`
+/// stands in for `REQUEST_TIMEOUT_SECS=30`; a variable that is set wins over the file.
+/// Command-line options without a variable are keyed by their long name with `_` for `-`
+/// (`max_tool_calls = 4`), and a flag given on the command line wins over the file.
+pub struct Settings {
+    /// Top-level values of the config file, empty without one
+    file: toml::Table,
+    /// Environment variables of the process, captured when the settings are loaded
+    env: HashMap<String, String>,
+}
+
+impl Settings {
+    /// Parses the config file at `path`; a missing file just leaves every setting unset.
+    ///
+    /// # Returns
+    /// * Error if the file can't be read or parsed, or has a key that is not a setting
+    pub fn load(path: &Path) -> Result<Settings, AppError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(AppError::InvalidConfig(format!(
+                    "--config: can't read {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+
+        let file = text.parse::<toml::Table>().map_err(|e| {
+            AppError::InvalidConfig(format!(
+                "--config: {} is not valid TOML: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let settings = Settings {
+            file,
+            env: env::vars_os()
+                .filter_map(|(var, value)| {
+                    Some((var.into_string().ok()?, value.into_string().ok()?))
+                })
+                .collect(),
+        };
+        settings.check_keys().map_err(|reason| {
+            AppError::InvalidConfig(format!("--config: {}: {}", path.display(), reason))
+        })?;
+        Ok(settings)
+    }
+
+    /// Rejects file keys that are neither a variable nor a command-line option, so a typo
+    /// doesn't silently leave a setting at its default.
+    fn check_keys(&self) -> Result<(), String> {
+        let command = Cli::command();
+        for key in self.file.keys() {
+            if FILE_VARS.iter().any(|var| var.to_lowercase() == *key) {
+                continue;
+            }
+            match command
+                .get_arguments()
+                .find(|arg| arg_key(arg) == Some(key.clone()))
+            {
+                Some(arg) if COMMAND_LINE_ONLY.contains(&arg.get_id().as_str()) => {
+                    return Err(format!("'{}' can only be given on the command line", key));
+                }
+                Some(arg) => {
+                    if let Some(var) = arg.get_env() {
+                        return Err(format!(
+                            "'{}' is set as '{}'",
+                            key,
+                            var.to_string_lossy().to_lowercase()
+                        ));
+                    }
+                }
+                None => return Err(format!("unknown setting '{}'", key)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses the command line `args`, filling in the options it doesn't give from the file.
+    ///
+    /// The command line wins: a file option is dropped when the command line gives it, or an
+    /// option it conflicts with (`--json` over `stream = true`), and a file flag is turned off
+    /// with `--no-<flag>`. The file's options are otherwise checked like typed ones.
+    pub fn cli<I, T>(&self, args: I) -> Result<Cli, AppError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let invalid =
+            |e: clap::Error| AppError::InvalidConfig(format!("--config: {}", clap_reason(&e)));
+        let command = without_env(command());
+        let matches = command
+            .clone()
+            .try_get_matches_from(&args)
+            .map_err(invalid)?;
+        let given: Vec<&Arg> = command
+            .get_arguments()
+            .filter(|arg| {
+                matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            })
+            .collect();
+
+        let mut file_args = Vec::new();
+        for arg in command.get_arguments() {
+            let id = arg.get_id().as_str();
+            if COMMAND_LINE_ONLY.contains(&id)
+                || given.iter().any(|other| other.get_id() == arg.get_id())
+                || given.iter().any(|other| conflicting(&command, arg, other))
+            {
+                continue;
+            }
+            if let Some(key) = arg_key(arg)
+                && let Some(value) = self.file.get(&key)
+            {
+                file_args.extend(file_arg(arg, &key, value)?);
+            }
+        }
+        if file_args.is_empty() {
+            return Cli::from_arg_matches(&matches).map_err(invalid);
+        }
+
+        // Given before the command line, so a `--no-<flag>` there overrides the file's flag
+        let mut args = args.into_iter();
+        let program = args.next().unwrap_or_default();
+        let matches = command
+            .try_get_matches_from(std::iter::once(program).chain(file_args).chain(args))
+            .map_err(invalid)?;
+        Cli::from_arg_matches(&matches).map_err(invalid)
+    }
+
+    /// Value of the setting `var`: the environment variable if set, otherwise the file's value.
+    fn get(&self, var: &str) -> Result<Option<String>, AppError> {
+        debug_assert!(
+            FILE_VARS.contains(&var),
+            "{} is missing from FILE_VARS",
+            var
+        );
+        if let Some(value) = self.env.get(var) {
+            return Ok(Some(value.clone()));
+        }
+
+        let key = var.to_lowercase();
+        match self.file.get(&key) {
+            None => Ok(None),
+            Some(toml::Value::String(value)) => Ok(Some(value.clone())),
+            Some(toml::Value::Integer(value)) => Ok(Some(value.to_string())),
+            Some(toml::Value::Float(value)) => Ok(Some(value.to_string())),
+            Some(toml::Value::Boolean(value)) => Ok(Some(value.to_string())),
+            Some(value) => Err(AppError::InvalidConfig(format!(
+                "{} in the config file: expected a string or a number, got {}",
+                key,
+                value.type_str()
+            ))),
+        }
+    }
+}
+
+/// Parses the process's command line, exiting with the usage on invalid arguments like
+/// `Cli::parse`; variables are still read, e.g. `CONFIG_FILE` to find the config file.
+pub fn parse_args() -> Cli {
+    let matches = command().get_matches();
+    Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
+
+/// The command-line parser with a hidden `--no-<flag>` for every flag, so a flag set in the
+/// config file can be turned off.
+fn command() -> Command {
+    let command = Cli::command();
+
+    // Flags like --no-color are negations already, and --warmup has its own --no-warmup
+    let flags: Vec<(String, String)> = command
+        .get_arguments()
+        .filter(|arg| matches!(arg.get_action(), ArgAction::SetTrue))
+        .filter_map(|arg| Some((arg.get_id().to_string(), arg.get_long()?.to_string())))
+        .filter(|(_, long)| !long.starts_with("no-"))
+        .filter(|(_, long)| {
+            let negation = format!("no-{}", long);
+            !command
+                .get_arguments()
+                .any(|arg| arg.get_long() == Some(negation.as_str()))
+        })
+        .collect();
+    flags.into_iter().fold(command, |command, (id, long)| {
+        let negation = format!("no_{}", id);
+        command
+            .mut_arg(&id, |arg| arg.overrides_with(negation.clone()))
+            .arg(
+                Arg::new(negation)
+                    .long(format!("no-{}", long))
+                    .action(ArgAction::SetTrue)
+                    .overrides_with(id)
+                    .hide(true),
+            )
+    })
+}
+
+/// `command` without its `env` fallbacks.
+///
+/// Variables are read through `Settings`, which captured the environment when it was loaded,
+/// so what the parser sees never depends on the process environment on its own.
+fn without_env(command: Command) -> Command {
+    let env_backed: Vec<String> = command
+        .get_arguments()
+        .filter(|arg| arg.get_env().is_some())
+        .map(|arg| arg.get_id().to_string())
+        .collect();
+    env_backed.iter().fold(command, |command, id| {
+        command.mut_arg(id, |arg| arg.env(None::<&str>))
+    })
+}
+
+/// Whether `arg` and `other` can't be given together, whichever of them declares it
+fn conflicting(command: &Command, arg: &Arg, other: &Arg) -> bool {
+    let declares = |arg: &Arg, other: &Arg| {
+        command
+            .get_arg_conflicts_with(arg)
+            .iter()
+            .any(|conflict| conflict.get_id() == other.get_id())
+    };
+    declares(arg, other) || declares(other, arg)
+}
+
+/// Config file key of a command-line option: its long name with `_` for `-`
+fn arg_key(arg: &Arg) -> Option<String> {
+    arg.get_long().map(|long| long.replace('-', "_"))
+}
+
+/// Turns a config file value into the command-line arguments giving it to `arg`.
+///
+/// Flags take `true` or `false`, `--verbose` a count, repeatable options a list, and
+/// everything else a string or a number.
+fn file_arg(arg: &Arg, key: &str, value: &toml::Value) -> Result<Vec<OsString>, AppError> {
+    let flag = format!("--{}", arg.get_long().unwrap_or(key));
+    let scalar = |value: &toml::Value| match value {
+        toml::Value::String(text) => Some(text.clone()),
+        toml::Value::Integer(number) => Some(number.to_string()),
+        toml::Value::Float(number) => Some(number.to_string()),
+        _ => None,
+    };
+    let expected = |what: &str| {
+        AppError::InvalidConfig(format!(
+            "{} in the config file: expected {}, got {}",
+            key,
+            what,
+            value.type_str()
+        ))
+    };
+
+    match arg.get_action() {
+        ArgAction::SetTrue => match value {
+            toml::Value::Boolean(true) => Ok(vec![flag.into()]),
+            toml::Value::Boolean(false) => Ok(Vec::new()),
+            _ => Err(expected("true or false")),
+        },
+        ArgAction::Count => match value {
+            toml::Value::Integer(count) if *count >= 0 => Ok(vec![flag.into(); *count as usize]),
+            _ => Err(expected("a count")),
+        },
+        ArgAction::Append => {
+            let items = match value {
+                toml::Value::Array(items) => items.iter().map(scalar).collect(),
+                value => scalar(value).map(|item| vec![item]),
+            };
+            let items = items.ok_or_else(|| expected("a list of strings"))?;
+            Ok(items
+                .into_iter()
+                .map(|item| format!("{}={}", flag, item).into())
+                .collect())
+        }
+        _ => match value {
+            // An option with an optional value, like --history, given without one
+            toml::Value::Boolean(true)
+                if arg
+                    .get_num_args()
+                    .is_some_and(|range| range.min_values() == 0) =>
+            {
+                Ok(vec![flag.into()])
+            }
+            value => {
+                let value = scalar(value).ok_or_else(|| expected("a string or a number"))?;
+                Ok(vec![format!("{}={}", flag, value).into()])
+            }
+        },
+    }
+}
+
+/// The reason clap gives for rejecting arguments, without its usage text
+fn clap_reason(error: &clap::Error) -> String {
+    let text = error.to_string();
+    let reason = text.lines().next().unwrap_or_default();
+    reason.trim_start_matches("error: ").to_string()
+}
+
+/// Reads an API base URL from a setting, using `default` when unset.
+///
+/// Lets the APIs be pointed at a proxy or a local mock server.
+fn base_url_setting(settings: &Settings, var: &str, default: &str) -> Result<String, AppError> {
+    match settings.get(var)? {
+        Some(value) => {
+            let base_url = value.trim().trim_end_matches('/');
+            if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+                return Err(AppError::InvalidConfig(format!(
+                    "{}: expected an http:// or https:// URL, got '{}'",
+                    var, value
+                )));
+            }
+            Ok(base_url.to_string())
+        }
+        None => Ok(default.to_string()),
+    }
+}
+
+/// Reads an API version path segment from a setting, using `default` when unset.
+fn version_setting(
+    settings: &Settings,
+    var: &str,
+    default: Option<&str>,
+) -> Result<Option<String>, AppError> {
+    match settings.get(var)? {
+        Some(value) => {
+            let version = value.trim().trim_matches('/');
+            if version.is_empty() || version.contains('/') {
+                return Err(AppError::InvalidConfig(format!(
+                    "{}: expected a single path segment like 'v1', got '{}'",
+                    var, value
+                )));
+            }
+            Ok(Some(version.to_string()))
+        }
+        None => Ok(default.map(str::to_string)),
+    }
+}
+
+/// Reads a duration in whole seconds from a setting, using `default` when unset.
+fn secs_setting(settings: &Settings, var: &str, default: u64) -> Result<Duration, AppError> {
+    match settings.get(var)? {
+        Some(value) => value.trim().parse().map(Duration::from_secs).map_err(|_| {
+            AppError::InvalidConfig(format!("{}: expected whole seconds, got '{}'", var, value))
+        }),
+        None => Ok(Duration::from_secs(default)),
+    }
+}
+
+/// Reads the retry policy for transient API failures (`HTTP_RETRY_ATTEMPTS`,
+/// `HTTP_RETRY_BASE_DELAY_MS`).
+fn retry_policy_setting(settings: &Settings) -> Result<RetryPolicy, AppError> {
+    let var = "HTTP_RETRY_ATTEMPTS";
+    let max_attempts = count_setting(settings, var, DEFAULT_RETRY_ATTEMPTS)?;
+    let max_attempts = u32::try_from(max_attempts).map_err(|_| {
+        AppError::InvalidConfig(format!("{}: too many attempts, got {}", var, max_attempts))
+    })?;
+
+    let var = "HTTP_RETRY_BASE_DELAY_MS";
+    let base_delay = match settings.get(var)? {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map(Duration::from_millis)
+            .map_err(|_| {
+                AppError::InvalidConfig(format!(
+                    "{}: expected whole milliseconds, got '{}'",
+                    var, value
+                ))
+            })?,
+        None => Duration::from_millis(DEFAULT_RETRY_BASE_DELAY_MS),
+    };
+
+    Ok(RetryPolicy {
+        max_attempts,
+        base_delay,
+    })
+}
+
+/// Reads the forecast horizon, which must be within what WeatherAPI serves.
+fn max_forecast_days_setting(settings: &Settings) -> Result<u32, AppError> {
+    let var = "WEATHER_MAX_FORECAST_DAYS";
+    let days = count_setting(settings, var, DEFAULT_MAX_FORECAST_DAYS)?;
+    u32::try_from(days)
+        .ok()
+        .filter(|&days| days <= weather::MAX_FORECAST_DAYS)
+        .ok_or_else(|| {
+            AppError::InvalidConfig(format!(
+                "{}: at most {} days are supported, got {}",
+                var,
+                weather::MAX_FORECAST_DAYS,
+                days
+            ))
+        })
+}
+
+/// Reads which service answers current weather lookups, WeatherAPI unless set.
+fn weather_provider_setting(settings: &Settings) -> Result<weather::Provider, AppError> {
+    let var = "WEATHER_PROVIDER";
+    match settings.get(var)? {
+        Some(value) => weather::Provider::from_name(&value).ok_or_else(|| {
+            AppError::InvalidConfig(format!(
+                "{}: expected 'weatherapi' or 'openweathermap', got '{}'",
+                var, value
+            ))
+        }),
+        None => Ok(weather::Provider::WeatherApi),
+    }
+}
+
+/// Reads the local business hours window, like "09:00-17:00".
+fn business_hours_setting(settings: &Settings) -> Result<BusinessHours, AppError> {
+    let var = "BUSINESS_HOURS";
+    let spec = settings
+        .get(var)?
+        .unwrap_or_else(|| DEFAULT_BUSINESS_HOURS.to_string());
+    BusinessHours::parse(&spec)
+        .map_err(|reason| AppError::InvalidConfig(format!("{}: {}", var, reason)))
+}
+
+/// Reads the decimal places for numeric tool outputs, at most `rounding::MAX_PRECISION`.
+fn precision_setting(settings: &Settings) -> Result<u32, AppError> {
+    let var = "OUTPUT_PRECISION";
+    match settings.get(var)? {
+        Some(value) => value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&precision| precision <= rounding::MAX_PRECISION)
+            .ok_or_else(|| {
+                AppError::InvalidConfig(format!(
+                    "{}: expected a whole number from 0 to {}, got '{}'",
+                    var,
+                    rounding::MAX_PRECISION,
+                    value
+                ))
+            }),
+        None => Ok(rounding::DEFAULT_PRECISION),
+    }
+}
+
+/// Reads a positive count from a setting, using `default` when unset.
+fn count_setting(settings: &Settings, var: &str, default: usize) -> Result<usize, AppError> {
+    match settings.get(var)? {
+        Some(value) => value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&count| count > 0)
+            .ok_or_else(|| {
+                AppError::InvalidConfig(format!(
+                    "{}: expected a positive whole number, got '{}'",
+                    var, value
+                ))
+            }),
+        None => Ok(default),
+    }
+}
+
+/// Reads a header spec from a setting, returning an empty map when unset.
+fn headers_setting(settings: &Settings, var: &str) -> Result<HeaderMap, AppError> {
+    match settings.get(var)? {
+        Some(spec) => parse_headers(&spec)
+            .map_err(|reason| AppError::InvalidConfig(format!("{}: {}", var, reason))),
+        None => Ok(HeaderMap::new()),
+    }
+}
+
+/// Parses a header spec like `"X-Tenant: acme; X-Api-Version: 2"` into a `HeaderMap`.
+///
+/// Headers are separated by `;` and each one must be in `Name: value` form.
+fn parse_headers(spec: &str) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+
+    for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, value) = entry
+            .split_once(':')
+            .ok_or_else(|| format!("expected 'Name: value', got '{}'", entry))?;
+
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| format!("invalid header name '{}'", name.trim()))?;
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|_| format!("invalid value for header '{}'", name))?;
+
+        headers.append(name, value);
+    }
+
+    Ok(headers)
+}
+
+#[cfg(test)]
+impl Settings {
+    /// Settings backed by a config file with the given TOML text and no environment
+    pub fn from_file_text(text: &str) -> Settings {
+        Settings {
+            file: text.parse().unwrap(),
+            env: HashMap::new(),
+        }
+    }
+}
+
+// Set in the child process of `tests_ignore_the_process_environment`
+#[cfg(test)]
+pub const POLLUTED_ENV: &str = "CLOUD_GEMINI_POLLUTED_ENV";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_is_overridden_by_env_and_env_by_command_line() {
+        let mut settings = Settings::from_file_text("gemini_model = \"file-model\"");
+        let model = |settings: &Settings, args: &[&str]| {
+            let cli = settings.cli(args.iter().copied()).unwrap();
+            Config::new(&cli, settings).unwrap().model
+        };
+        assert_eq!(model(&settings, &["app"]), "file-model");
+
+        settings
+            .env
+            .insert("GEMINI_MODEL".to_string(), "env-model".to_string());
+        assert_eq!(model(&settings, &["app"]), "env-model");
+        assert_eq!(
+            model(&settings, &["app", "--model", "cli-model"]),
+            "cli-model"
+        );
+    }
+
+    #[test]
+    fn api_keys_are_read_from_the_settings_environment() {
+        let mut settings = Settings::from_file_text("");
+        let api_keys = |settings: &Settings| {
+            let cli = settings.cli(["app"]).unwrap();
+            Config::new(&cli, settings).unwrap().api_keys
+        };
+        assert_eq!(api_keys(&settings).get("WEATHER_API_KEY"), None);
+
+        settings
+            .env
+            .insert("WEATHER_API_KEY".to_string(), "secret".to_string());
+        let keys = api_keys(&settings);
+        assert_eq!(keys.get("WEATHER_API_KEY"), Some("secret"));
+        assert_eq!(keys.get("IP_GEOLOCATION_API_KEY"), None);
+        assert!(!format!("{:?}", keys).contains("secret"));
+    }
+
+    #[test]
+    fn command_line_ignores_variables_the_settings_do_not_have() {
+        let cli = Settings::from_file_text("").cli(["app"]).unwrap();
+        assert_eq!(cli.model, None);
+        assert_eq!(cli.unit, None);
+        assert_eq!(cli.system_prompt, None);
+        assert_eq!(cli.config, PathBuf::from("config.toml"));
+    }
+
+    /// Tests whose outcome must not depend on the developer's environment or `.env`
+    const HERMETIC_TESTS: &[&str] = &["config::tests::", "tests::missing_unit_"];
+
+    #[test]
+    fn tests_ignore_the_process_environment() {
+        if env::var_os(POLLUTED_ENV).is_some() {
+            return;
+        }
+        let output = std::process::Command::new(env::current_exe().unwrap())
+            .args(HERMETIC_TESTS)
+            .env(POLLUTED_ENV, "1")
+            .env("GEMINI_MODEL", "polluted-model")
+            .env("TEMPERATURE_UNIT", "F")
+            .env("SYSTEM_PROMPT_FILE", "polluted-prompt.txt")
+            .env("CONFIG_FILE", "polluted.toml")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+
+    #[test]
+    fn command_line_options_can_be_set_in_the_file() {
+        let settings = Settings::from_file_text(
+            "max_tool_calls = 4\nprofile = true\nstop = [\"END\", \"STOP\"]\n\
+             missing_value = \"unavailable\"\nhistory = \"chat.json\"",
+        );
+        let cli = settings.cli(["app"]).unwrap();
+        assert_eq!(cli.max_tool_calls, 4);
+        assert!(cli.profile);
+        assert_eq!(cli.stop_sequences, ["END", "STOP"]);
+        assert!(matches!(cli.missing_value, MissingValue::Unavailable));
+        assert_eq!(cli.history, Some(Some(PathBuf::from("chat.json"))));
+
+        let cli = settings.cli(["app", "--max-tool-calls", "6"]).unwrap();
+        assert_eq!(cli.max_tool_calls, 6);
+    }
+
+    #[test]
+    fn file_options_are_checked_like_typed_ones() {
+        let invalid = |text: &str, args: &[&str]| {
+            matches!(
+                Settings::from_file_text(text).cli(args.iter().copied()),
+                Err(AppError::InvalidConfig(_))
+            )
+        };
+        assert!(invalid("max_tool_calls = 0", &["app"]));
+        assert!(invalid("profile = \"yes\"", &["app"]));
+        assert!(invalid("stream = true\njson = true", &["app"]));
+    }
+
+    #[test]
+    fn command_line_wins_over_conflicting_file_options() {
+        let settings = Settings::from_file_text("stream = true\nhistory = true");
+        let cli = settings.cli(["app", "--json"]).unwrap();
+        assert!(cli.json);
+        assert!(!cli.stream);
+        assert_eq!(cli.history, Some(None));
+
+        let cli = settings.cli(["app", "--session", "work"]).unwrap();
+        assert!(cli.stream);
+        assert_eq!(cli.history, None);
+        assert_eq!(cli.session.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn file_flags_are_turned_off_from_the_command_line() {
+        let settings =
+            Settings::from_file_text("profile = true\nordered_tools = true\nwarmup = true");
+        let cli = settings
+            .cli(["app", "--no-profile", "--no-warmup"])
+            .unwrap();
+        assert!(!cli.profile);
+        assert!(!cli.warmup);
+        assert!(cli.ordered_tools);
+
+        let cli = settings.cli(["app", "--no-ordered-tools"]).unwrap();
+        assert!(cli.profile);
+        assert!(!cli.ordered_tools);
+
+        // Without a file value the negation just keeps the flag off
+        let cli = Settings::from_file_text("")
+            .cli(["app", "--no-stream", "--stream"])
+            .unwrap();
+        assert!(cli.stream);
+    }
+
+    #[test]
+    fn unknown_file_keys_are_rejected() {
+        assert!(
+            Settings::from_file_text("request_timeout_secs = 5\nquiet = true")
+                .check_keys()
+                .is_ok()
+        );
+        for (text, reason) in [
+            ("request_timeout = 5", "unknown setting 'request_timeout'"),
+            ("model = \"gemini\"", "'model' is set as 'gemini_model'"),
+            (
+                "once = \"weather?\"",
+                "'once' can only be given on the command line",
+            ),
+        ] {
+            assert_eq!(
+                Settings::from_file_text(text).check_keys().unwrap_err(),
+                reason
+            );
+        }
+    }
+
+    #[test]
+    fn precision_defaults_when_unset() {
+        assert_eq!(
+            precision_setting(&Settings::from_file_text("")).unwrap(),
+            rounding::DEFAULT_PRECISION
+        );
+    }
+
+    #[test]
+    fn precision_is_read_from_the_file() {
+        assert_eq!(
+            precision_setting(&Settings::from_file_text("output_precision = 3")).unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn invalid_precision_is_an_error() {
+        for text in [
+            "output_precision = -1",
+            "output_precision = 16",
+            "output_precision = \"two\"",
+        ] {
+            assert!(
+                matches!(
+                    precision_setting(&Settings::from_file_text(text)),
+                    Err(AppError::InvalidConfig(_))
+                ),
+                "{} was accepted",
+                text
+            );
+        }
+    }
+}
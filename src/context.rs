@@ -0,0 +1,67 @@
+use std::sync::atomic::AtomicBool;
+
+use crate::cache::{AnswerCache, Cache};
+use crate::config::Config;
+use crate::error::AppError;
+use crate::http::Http;
+use crate::output::Output;
+use crate::profile::Profile;
+use crate::{offline, units};
+
+/// Shared state available to every tool call
+pub struct Context {
+    /// Settings resolved at startup
+    pub config: Config,
+    /// Cache of recent tool lookups
+    pub cache: Cache,
+    /// Cache of final answers, used with `--semantic-cache`
+    pub answers: AnswerCache,
+    /// HTTP client shared by all API calls
+    pub http: Http,
+    /// Time spent in each phase of the current turn
+    pub profile: Profile,
+    /// Whether the current turn's answer was already printed while streaming
+    pub streamed: AtomicBool,
+    /// Where assistant messages are shown
+    pub output: Box<dyn Output>,
+}
+
+impl Context {
+    /// Creates a context with an empty cache, showing messages through `output`
+    pub fn new(config: Config, output: Box<dyn Output>) -> Self {
+        Context {
+            http: Http::new(
+                config.max_http_connections,
+                config.request_timeout,
+                config.offline,
+            ),
+            config,
+            profile: Profile::default(),
+            cache: Cache::default(),
+            answers: AnswerCache::default(),
+            streamed: AtomicBool::new(false),
+            output,
+        }
+    }
+
+    /// The API key in the environment variable `var`, as read at startup; `--offline` runs
+    /// without real keys.
+    pub fn api_key(&self, var: &str) -> Result<String, AppError> {
+        if self.config.offline {
+            return Ok(offline::API_KEY.to_string());
+        }
+        self.config
+            .api_keys
+            .get(var)
+            .map(str::to_string)
+            .ok_or_else(|| AppError::EnvVarNotSet(var.to_string()))
+    }
+
+    /// The temperature unit used when none is requested: `--unit`, or else the country's
+    /// usual unit.
+    pub fn default_unit(&self, country: &str) -> &'static str {
+        self.config
+            .default_unit
+            .unwrap_or_else(|| units::default_unit_for_country(country))
+    }
+}
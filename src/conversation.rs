@@ -0,0 +1,438 @@
+use std::collections::HashSet;
+
+use genai::chat::{ChatMessage, ChatRole, ContentPart, MessageContent};
+use serde_json::{Value, json};
+use tracing::warn;
+
+// Characters per token assumed when estimating the size of a conversation
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Repairs tool call bookkeeping in a conversation before it is sent to the model.
+///
+/// The API rejects conversations where tool responses and tool calls don't line up,
+/// which can happen after a history reload. This pass:
+/// - removes tool responses without a matching call in the preceding `ToolCalls` message
+///   (orphans) or answering a call that was already answered (duplicates)
+/// - removes tool calls that never received a response
+/// - drops messages left empty by the above
+///
+/// Call ids are counted rather than treated as unique because the Gemini adapter uses
+/// the function name as the call id, so parallel calls to one tool share an id.
+pub fn repair(messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    // First pass: keep only tool responses answering a pending call
+    let mut pending: Vec<String> = Vec::new();
+    let mut answered: Vec<(usize, String)> = Vec::new();
+    let mut checked = Vec::with_capacity(messages.len());
+    let mut calls_index = 0;
+
+    for message in messages {
+        match message.content {
+            MessageContent::ToolCalls(ref tool_calls) => {
+                pending = tool_calls.iter().map(|c| c.call_id.clone()).collect();
+                calls_index = checked.len();
+                checked.push(message);
+            }
+            MessageContent::ToolResponses(tool_responses) => {
+                let mut kept = Vec::with_capacity(tool_responses.len());
+                for tool_response in tool_responses {
+                    if take(&mut pending, &tool_response.call_id) {
+                        answered.push((calls_index, tool_response.call_id.clone()));
+                        kept.push(tool_response);
+                    } else {
+                        warn!(
+                            "Removed orphaned or duplicate tool response: {}",
+                            tool_response.call_id
+                        );
+                    }
+                }
+                if !kept.is_empty() {
+                    checked.push(ChatMessage {
+                        content: MessageContent::ToolResponses(kept),
+                        ..message
+                    });
+                }
+            }
+            _ => {
+                pending.clear();
+                checked.push(message);
+            }
+        }
+    }
+
+    // Second pass: drop tool calls that were never answered
+    checked
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, message)| match message.content {
+            MessageContent::ToolCalls(tool_calls) => {
+                let mut kept = Vec::with_capacity(tool_calls.len());
+                for tool_call in tool_calls {
+                    if let Some(position) = answered
+                        .iter()
+                        .position(|(i, id)| *i == index && *id == tool_call.call_id)
+                    {
+                        answered.remove(position);
+                        kept.push(tool_call);
+                    } else {
+                        warn!("Removed unanswered tool call: {}", tool_call.call_id);
+                    }
+                }
+                if kept.is_empty() {
+                    None
+                } else {
+                    Some(ChatMessage {
+                        content: MessageContent::ToolCalls(kept),
+                        ..message
+                    })
+                }
+            }
+            _ => Some(message),
+        })
+        .collect()
+}
+
+/// Whether the final answer quotes raw JSON shaped like one of the current turn's tool results.
+///
+/// Every `{` in the last assistant text is tried as the start of a JSON object; one that
+/// shares a key with a tool response given since the last user message counts as an echo.
+pub fn echoes_tool_json(messages: &[ChatMessage]) -> bool {
+    let Some((last, earlier)) = messages.split_last() else {
+        return false;
+    };
+    let MessageContent::Text(text) = &last.content else {
+        return false;
+    };
+
+    // Keys of this turn's tool results, at any depth
+    let mut keys = HashSet::new();
+    for message in earlier
+        .iter()
+        .rev()
+        .take_while(|message| !matches!(message.role, ChatRole::User))
+    {
+        if let MessageContent::ToolResponses(tool_responses) = &message.content {
+            for tool_response in tool_responses {
+                if let Ok(value) = serde_json::from_str(&tool_response.content) {
+                    collect_keys(&value, &mut keys);
+                }
+            }
+        }
+    }
+    if keys.is_empty() {
+        return false;
+    }
+
+    text.match_indices('{').any(|(start, _)| {
+        let mut objects = serde_json::Deserializer::from_str(&text[start..]).into_iter::<Value>();
+        match objects.next() {
+            Some(Ok(Value::Object(object))) => object.keys().any(|key| keys.contains(key)),
+            _ => false,
+        }
+    })
+}
+
+/// The text of a multi-part model answer, with non-text parts left out.
+///
+/// Returns `None` if the answer has no text at all.
+pub fn parts_text(parts: &[ContentPart]) -> Option<String> {
+    let texts: Vec<&str> = parts
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    (!texts.is_empty()).then(|| texts.concat())
+}
+
+/// Tool calls the model made since the last user message, as `{"name", "arguments"}` objects.
+pub fn turn_tool_calls(messages: &[ChatMessage]) -> Vec<Value> {
+    let mut tool_calls: Vec<Value> = messages
+        .iter()
+        .rev()
+        .take_while(|message| !matches!(message.role, ChatRole::User))
+        .filter_map(|message| match &message.content {
+            MessageContent::ToolCalls(tool_calls) => Some(tool_calls),
+            _ => None,
+        })
+        .flat_map(|tool_calls| tool_calls.iter().rev())
+        .map(|tool_call| json!({ "name": tool_call.fn_name, "arguments": tool_call.fn_arguments }))
+        .collect();
+    tool_calls.reverse();
+    tool_calls
+}
+
+/// Rough token count of a text, at about four characters per token.
+pub fn estimated_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Rough token count of a message, tool call arguments and results included.
+pub fn message_tokens(message: &ChatMessage) -> usize {
+    match &message.content {
+        MessageContent::Text(text) => estimated_tokens(text),
+        MessageContent::Parts(parts) => parts_text(parts).map_or(0, |text| estimated_tokens(&text)),
+        MessageContent::ToolCalls(tool_calls) => tool_calls
+            .iter()
+            .map(|tool_call| {
+                estimated_tokens(&tool_call.fn_name)
+                    + estimated_tokens(&tool_call.fn_arguments.to_string())
+            })
+            .sum(),
+        MessageContent::ToolResponses(tool_responses) => tool_responses
+            .iter()
+            .map(|tool_response| estimated_tokens(&tool_response.content))
+            .sum(),
+    }
+}
+
+/// Drops the oldest turns until the conversation fits an estimated token budget.
+///
+/// `reserved` is what the rest of the request (the system prompt) already takes up.
+/// Turns are dropped whole, from one user message up to the next, so no tool response
+/// loses its call. System messages and the latest turn are always kept, even when they
+/// alone exceed the budget.
+///
+/// # Returns
+/// * The messages kept
+/// * How many messages were dropped
+pub fn trim_to_budget(
+    messages: Vec<ChatMessage>,
+    reserved: usize,
+    max_tokens: usize,
+) -> (Vec<ChatMessage>, usize) {
+    let total = reserved + messages.iter().map(message_tokens).sum::<usize>();
+    let mut excess = total.saturating_sub(max_tokens);
+    if excess == 0 {
+        return (messages, 0);
+    }
+
+    let latest_turn = messages
+        .iter()
+        .rposition(|message| matches!(message.role, ChatRole::User))
+        .unwrap_or(messages.len());
+    let mut kept = Vec::with_capacity(messages.len());
+    let mut dropped = 0;
+    let mut trimming = true;
+
+    for (index, message) in messages.into_iter().enumerate() {
+        // Stop at the start of a turn once enough is gone, or at the latest turn
+        if matches!(message.role, ChatRole::User) && (excess == 0 || index >= latest_turn) {
+            trimming = false;
+        }
+        if trimming && !matches!(message.role, ChatRole::System) {
+            excess = excess.saturating_sub(message_tokens(&message));
+            dropped += 1;
+        } else {
+            kept.push(message);
+        }
+    }
+
+    (kept, dropped)
+}
+
+/// Adds the keys of every object within `value` to `keys`.
+fn collect_keys(value: &Value, keys: &mut HashSet<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                keys.insert(key.clone());
+                collect_keys(value, keys);
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_keys(value, keys)),
+        _ => {}
+    }
+}
+
+/// Removes one occurrence of `call_id` from `ids`, returning whether it was present.
+fn take(ids: &mut Vec<String>, call_id: &str) -> bool {
+    match ids.iter().position(|id| id == call_id) {
+        Some(index) => {
+            ids.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use genai::chat::{ToolCall, ToolResponse};
+
+    use super::*;
+
+    /// An assistant message calling tools with the given call ids
+    fn calls(ids: &[&str]) -> ChatMessage {
+        let tool_calls = ids
+            .iter()
+            .map(|id| ToolCall {
+                call_id: id.to_string(),
+                fn_name: id.to_string(),
+                fn_arguments: json!({}),
+            })
+            .collect();
+        ChatMessage {
+            role: ChatRole::Assistant,
+            content: MessageContent::ToolCalls(tool_calls),
+            options: None,
+        }
+    }
+
+    /// A tool message answering the given call ids
+    fn responses(ids: &[&str]) -> ChatMessage {
+        let tool_responses = ids
+            .iter()
+            .map(|id| ToolResponse::new(id.to_string(), "{}".to_string()))
+            .collect();
+        ChatMessage {
+            role: ChatRole::Tool,
+            content: MessageContent::ToolResponses(tool_responses),
+            options: None,
+        }
+    }
+
+    /// One line per message, like `calls a b` or `responses a`, for comparing conversations
+    fn outline(messages: &[ChatMessage]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|message| match &message.content {
+                MessageContent::ToolCalls(tool_calls) => tool_calls
+                    .iter()
+                    .fold("calls".to_string(), |line, c| line + " " + &c.call_id),
+                MessageContent::ToolResponses(tool_responses) => tool_responses
+                    .iter()
+                    .fold("responses".to_string(), |line, r| line + " " + &r.call_id),
+                MessageContent::Text(text) => format!("{:?} {}", message.role, text),
+                MessageContent::Parts(_) => format!("{:?} parts", message.role),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn repair_keeps_a_consistent_conversation() {
+        let messages = vec![
+            ChatMessage::user("weather in Oslo and Rome"),
+            calls(&["get_weather", "get_weather"]),
+            responses(&["get_weather", "get_weather"]),
+            ChatMessage::assistant("Oslo 3°C, Rome 18°C"),
+        ];
+        let expected = outline(&messages);
+        assert_eq!(outline(&repair(messages)), expected);
+    }
+
+    #[test]
+    fn repair_removes_an_orphaned_tool_response() {
+        let repaired = repair(vec![
+            ChatMessage::user("hi"),
+            responses(&["get_weather"]),
+            ChatMessage::assistant("Hello"),
+        ]);
+        assert_eq!(outline(&repaired), ["User hi", "Assistant Hello"]);
+    }
+
+    #[test]
+    fn repair_removes_a_duplicate_response() {
+        let repaired = repair(vec![
+            ChatMessage::user("weather in Oslo"),
+            calls(&["get_weather"]),
+            responses(&["get_weather"]),
+            responses(&["get_weather"]),
+            ChatMessage::assistant("3°C"),
+        ]);
+        assert_eq!(
+            outline(&repaired),
+            [
+                "User weather in Oslo",
+                "calls get_weather",
+                "responses get_weather",
+                "Assistant 3°C"
+            ]
+        );
+    }
+
+    #[test]
+    fn repair_removes_unanswered_calls() {
+        let repaired = repair(vec![
+            ChatMessage::user("weather and time in Oslo"),
+            calls(&["get_weather", "get_current_time"]),
+            responses(&["get_weather"]),
+            ChatMessage::assistant("3°C"),
+            ChatMessage::user("and tomorrow?"),
+            calls(&["get_forecast"]),
+        ]);
+        assert_eq!(
+            outline(&repaired),
+            [
+                "User weather and time in Oslo",
+                "calls get_weather",
+                "responses get_weather",
+                "Assistant 3°C",
+                "User and tomorrow?"
+            ]
+        );
+    }
+
+    #[test]
+    fn repair_does_not_match_responses_to_calls_of_an_earlier_turn() {
+        let repaired = repair(vec![
+            ChatMessage::user("weather in Oslo"),
+            calls(&["get_weather"]),
+            ChatMessage::user("never mind"),
+            responses(&["get_weather"]),
+        ]);
+        assert_eq!(
+            outline(&repaired),
+            ["User weather in Oslo", "User never mind"]
+        );
+    }
+
+    /// A conversation of `turns` questions with their answers, each message 25 tokens long
+    fn long_conversation(turns: usize) -> Vec<ChatMessage> {
+        let mut messages = vec![ChatMessage::system("Be brief")];
+        for turn in 1..=turns {
+            messages.push(ChatMessage::user(format!("{:<100}", turn)));
+            messages.push(ChatMessage::assistant(format!("{:<100}", turn)));
+        }
+        messages
+    }
+
+    /// Estimated tokens of `messages` on top of `reserved`
+    fn request_tokens(messages: &[ChatMessage], reserved: usize) -> usize {
+        reserved + messages.iter().map(message_tokens).sum::<usize>()
+    }
+
+    #[test]
+    fn trim_leaves_a_request_within_the_budget_alone() {
+        let messages = long_conversation(3);
+        let budget = request_tokens(&messages, 10);
+        let (kept, dropped) = trim_to_budget(messages, 10, budget);
+        assert_eq!((kept.len(), dropped), (7, 0));
+    }
+
+    #[test]
+    fn trim_drops_the_oldest_turns_of_an_oversized_request() {
+        let messages = long_conversation(3);
+        assert!(request_tokens(&messages, 10) > 80);
+
+        let (kept, dropped) = trim_to_budget(messages, 10, 80);
+        assert_eq!(dropped, 4);
+        assert!(request_tokens(&kept, 10) <= 80);
+        assert!(matches!(kept[0].role, ChatRole::System));
+        assert_eq!(outline(&kept[1..]), outline(&long_conversation(3)[5..]));
+    }
+
+    #[test]
+    fn trim_keeps_the_system_prompt_and_latest_turn_over_the_budget() {
+        let mut messages = long_conversation(2);
+        messages.push(calls(&["get_weather"]));
+        messages.push(responses(&["get_weather"]));
+        messages.push(ChatMessage::assistant("3°C"));
+        let latest = outline(&messages[3..]);
+
+        let (kept, dropped) = trim_to_budget(messages, 10, 20);
+        assert_eq!(dropped, 2);
+        assert!(matches!(kept[0].role, ChatRole::System));
+        assert_eq!(outline(&kept[1..]), latest);
+    }
+}
@@ -0,0 +1,292 @@
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+
+use crate::error::AppError;
+
+/// Resolves a day expression relative to `today`.
+///
+/// Accepts:
+/// - "today" and "tomorrow"
+/// - a weekday name or abbreviation ("saturday", "sat"), resolved to its next occurrence
+///   including today
+/// - "next <weekday>", resolved to its next occurrence after today
+/// - an ISO date ("2025-06-01")
+pub fn resolve_day(input: &str, today: NaiveDate) -> Result<NaiveDate, AppError> {
+    let normalized = input.trim().to_lowercase();
+
+    match normalized.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return Ok(today + Days::new(1)),
+        _ => {}
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(&normalized, "%Y-%m-%d") {
+        return Ok(date);
+    }
+
+    let (name, skip_today) = match normalized.strip_prefix("next ") {
+        Some(name) => (name.trim(), true),
+        None => (normalized.as_str(), false),
+    };
+    let weekday = name.parse::<Weekday>().map_err(|_| {
+        AppError::InvalidParameter(format!(
+            "unrecognized day '{}', expected today, tomorrow, a weekday name or YYYY-MM-DD",
+            input
+        ))
+    })?;
+
+    Ok(next_weekday(today, weekday, skip_today))
+}
+
+/// Resolves a moment like "tomorrow 9am" or "2025-06-01 15:30" relative to `now`.
+///
+/// The day part is anything `resolve_day` accepts and may be left out for today; the time
+/// part is "15:30", "3:30 pm" or "3pm" and may be left out for the current time of day.
+/// "2025-06-01T15:30" is accepted as well.
+pub fn resolve_moment(input: &str, now: NaiveDateTime) -> Result<NaiveDateTime, AppError> {
+    let normalized = input.trim().to_lowercase();
+    if let Ok(moment) = NaiveDateTime::parse_from_str(&normalized, "%Y-%m-%dt%H:%M") {
+        return Ok(moment);
+    }
+
+    // The time is the last word, or the last two for a separate "am"/"pm"
+    let words: Vec<&str> = normalized.split_whitespace().collect();
+    for time_words in [2, 1] {
+        let Some(split) = words.len().checked_sub(time_words) else {
+            continue;
+        };
+        if let Some(time) = parse_time_of_day(&words[split..].join(" ")) {
+            let day = words[..split].join(" ");
+            let date = if day.is_empty() {
+                now.date()
+            } else {
+                resolve_day(&day, now.date()).map_err(|_| unrecognized_moment(input))?
+            };
+            return Ok(NaiveDateTime::new(date, time));
+        }
+    }
+
+    let date = resolve_day(&normalized, now.date()).map_err(|_| unrecognized_moment(input))?;
+    Ok(NaiveDateTime::new(date, now.time()))
+}
+
+/// Parses a time of day in 24-hour ("15:30") or 12-hour ("3:30 pm", "3pm") form.
+fn parse_time_of_day(text: &str) -> Option<NaiveTime> {
+    let compact = text.replace(' ', "");
+    if let Ok(time) = NaiveTime::parse_from_str(&compact, "%H:%M") {
+        return Some(time);
+    }
+
+    // 12-hour times, with or without minutes
+    let (clock, meridiem) = match compact.strip_suffix("am") {
+        Some(clock) => (clock, "am"),
+        None => (compact.strip_suffix("pm")?, "pm"),
+    };
+    let clock = if clock.contains(':') {
+        clock.to_string()
+    } else {
+        format!("{}:00", clock)
+    };
+    NaiveTime::parse_from_str(&format!("{}{}", clock, meridiem), "%I:%M%p").ok()
+}
+
+/// Error for a moment `resolve_moment` can't make sense of.
+fn unrecognized_moment(input: &str) -> AppError {
+    AppError::InvalidParameter(format!(
+        "unrecognized date '{}', expected a day (today, tomorrow, a weekday name or YYYY-MM-DD) \
+         optionally followed by a time like 15:30 or 3pm",
+        input
+    ))
+}
+
+/// Returns the next date falling on `weekday`, starting from today or tomorrow.
+fn next_weekday(today: NaiveDate, weekday: Weekday, skip_today: bool) -> NaiveDate {
+    let mut days_ahead =
+        (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    if days_ahead == 0 && skip_today {
+        days_ahead = 7;
+    }
+    today + Days::new(u64::from(days_ahead))
+}
+
+/// Shifts a local date and 12-hour time by a number of hours, forward or backward.
+///
+/// The date rolls over in either direction when the shift crosses midnight, so
+/// `("2025-06-01", "00:30:00 AM", -1.0)` gives "2025-05-31 11:30:00 PM". Fractional
+/// offsets are rounded to the nearest minute.
+pub fn shift_local_time(date: &str, time_12: &str, offset_hours: f64) -> Result<String, AppError> {
+    let local = parse_local_time(date, time_12)?;
+
+    let offset = TimeDelta::try_minutes((offset_hours * 60.0).round() as i64)
+        .filter(|_| offset_hours.is_finite())
+        .ok_or_else(|| {
+            AppError::InvalidParameter(format!("offset_hours out of range: {}", offset_hours))
+        })?;
+    let shifted = local.checked_add_signed(offset).ok_or_else(|| {
+        AppError::InvalidParameter(format!("offset_hours out of range: {}", offset_hours))
+    })?;
+
+    Ok(shifted.format("%Y-%m-%d %I:%M:%S %p").to_string())
+}
+
+/// Parses the geolocation API's local date ("2025-06-01") and 12-hour time ("08:30:00 PM",
+/// seconds optional).
+pub fn parse_local_time(date: &str, time_12: &str) -> Result<NaiveDateTime, AppError> {
+    let invalid =
+        || AppError::ResponseParseError(format!("unrecognized time '{} {}'", date, time_12));
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| invalid())?;
+    let time = NaiveTime::parse_from_str(time_12, "%I:%M:%S %p")
+        .or_else(|_| NaiveTime::parse_from_str(time_12, "%I:%M %p"))
+        .map_err(|_| invalid())?;
+    Ok(NaiveDateTime::new(date, time))
+}
+
+/// Daily window of business hours, in local time
+#[derive(Debug, Clone, Copy)]
+pub struct BusinessHours {
+    /// When business opens
+    pub start: NaiveTime,
+    /// When business closes, after `start`
+    pub end: NaiveTime,
+}
+
+impl BusinessHours {
+    /// Parses a window like "09:00-17:00".
+    pub fn parse(spec: &str) -> Result<BusinessHours, String> {
+        let (start, end) = spec
+            .split_once('-')
+            .ok_or_else(|| format!("expected 'HH:MM-HH:MM', got '{}'", spec))?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|_| format!("invalid time '{}', expected HH:MM", time.trim()))
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+        if start >= end {
+            return Err(format!("window must end after it starts, got '{}'", spec));
+        }
+        Ok(BusinessHours { start, end })
+    }
+
+    /// Whether `time` falls within the window; the closing time itself is outside it.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+impl std::fmt::Display for BusinessHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// Whether `date` falls on a Saturday or Sunday
+pub fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A date written as "YYYY-MM-DD"
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    // A Wednesday
+    const TODAY: &str = "2025-06-04";
+
+    #[test]
+    fn relative_days_resolve_from_today() {
+        let today = date(TODAY);
+        assert_eq!(resolve_day("today", today).unwrap(), today);
+        assert_eq!(
+            resolve_day(" Tomorrow ", today).unwrap(),
+            date("2025-06-05")
+        );
+        assert_eq!(
+            resolve_day("2025-07-01", today).unwrap(),
+            date("2025-07-01")
+        );
+    }
+
+    #[test]
+    fn weekdays_resolve_to_their_next_occurrence_including_today() {
+        let today = date(TODAY);
+        assert_eq!(resolve_day("saturday", today).unwrap(), date("2025-06-07"));
+        assert_eq!(resolve_day("Mon", today).unwrap(), date("2025-06-09"));
+        assert_eq!(resolve_day("wednesday", today).unwrap(), today);
+    }
+
+    #[test]
+    fn next_weekday_skips_today() {
+        let today = date(TODAY);
+        assert_eq!(
+            resolve_day("next wednesday", today).unwrap(),
+            date("2025-06-11")
+        );
+        assert_eq!(resolve_day("next sat", today).unwrap(), date("2025-06-07"));
+        assert_eq!(
+            resolve_day("Next  Tuesday", today).unwrap(),
+            date("2025-06-10")
+        );
+    }
+
+    #[test]
+    fn unknown_days_are_rejected() {
+        for day in ["someday", "next", "2025-13-01", ""] {
+            assert!(
+                matches!(
+                    resolve_day(day, date(TODAY)),
+                    Err(AppError::InvalidParameter(_))
+                ),
+                "day {:?} was accepted",
+                day
+            );
+        }
+    }
+
+    #[test]
+    fn negative_offsets_roll_back_past_midnight() {
+        assert_eq!(
+            shift_local_time("2025-06-01", "12:30:00 AM", -1.0).unwrap(),
+            "2025-05-31 11:30:00 PM"
+        );
+        assert_eq!(
+            shift_local_time("2025-03-01", "02:00 AM", -3.5).unwrap(),
+            "2025-02-28 10:30:00 PM"
+        );
+    }
+
+    #[test]
+    fn positive_offsets_roll_over_to_the_next_day() {
+        assert_eq!(
+            shift_local_time("2025-12-31", "11:15:00 PM", 1.0).unwrap(),
+            "2026-01-01 12:15:00 AM"
+        );
+        assert_eq!(
+            shift_local_time("2025-06-01", "08:00:00 AM", 0.25).unwrap(),
+            "2025-06-01 08:15:00 AM"
+        );
+    }
+
+    #[test]
+    fn unusable_offsets_and_times_are_rejected() {
+        assert!(matches!(
+            shift_local_time("2025-06-01", "08:00:00 AM", f64::NAN),
+            Err(AppError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            shift_local_time("2025-06-01", "08:00:00 AM", 1e18),
+            Err(AppError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            shift_local_time("2025-06-01", "25:00", 1.0),
+            Err(AppError::ResponseParseError(_))
+        ));
+    }
+}
@@ -1,13 +1,25 @@
 use std::io;
+use std::sync::Arc;
 use thiserror::Error;
 
 /// Custom error types for the cloud-gemini application
-#[derive(Error, Debug)]
+///
+/// Errors are `Clone`, with wrapped library errors behind an `Arc`, so the outcome of a
+/// coalesced lookup reaches every caller waiting on it unchanged.
+#[derive(Error, Debug, Clone)]
 pub enum AppError {
     /// Error when a required parameter is missing from a tool call
     #[error("Missing parameter: {0}")]
     MissingParameter(String),
 
+    /// Error when a tool call parameter has an invalid value
+    #[error("Invalid parameter: {0}")]
+    InvalidParameter(String),
+
+    /// Error when a tool call asks for a temperature unit other than C or F
+    #[error("Unsupported temperature unit: {0} (expected C or F)")]
+    UnsupportedUnit(String),
+
     /// Error when a tool call function is not implemented
     #[error("Tool call function not implemented: {0}")]
     UnsupportedToolCall(String),
@@ -16,23 +28,58 @@ pub enum AppError {
     #[error("API request failed: {0}")]
     ApiRequestFailed(String),
 
+    /// Error when an API did not respond within the request timeout
+    #[error("Request timed out: {0}")]
+    Timeout(String),
+
+    /// Error when an API answers with an unsuccessful HTTP status, with its error message or the
+    /// response body
+    #[error("API returned HTTP {code}: {body}")]
+    ApiStatus { code: u16, body: String },
+
+    /// Error when the API reports that the requested location does not exist
+    #[error("Location not found: {0}")]
+    LocationNotFound(String),
+
     /// Error when environment variable is not set
     #[error("Environment variable not set: {0}")]
     EnvVarNotSet(String),
 
+    /// Error when a configuration value is invalid
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
     /// Error when parsing API response
     #[error("Failed to parse API response: {0}")]
     ResponseParseError(String),
 
     /// Wrapper for reqwest errors
     #[error("HTTP request error: {0}")]
-    RequestError(#[from] reqwest::Error),
-    
+    RequestError(#[source] Arc<reqwest::Error>),
+
     /// Wrapper for I/O errors
     #[error("I/O error: {0}")]
-    IoError(#[from] io::Error),
-    
+    IoError(#[source] Arc<io::Error>),
+
     /// Wrapper for JSON serialization/deserialization errors
     #[error("JSON error: {0}")]
-    JsonError(#[from] serde_json::Error),
+    JsonError(#[source] Arc<serde_json::Error>),
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(error: reqwest::Error) -> Self {
+        AppError::RequestError(Arc::new(error))
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(error: io::Error) -> Self {
+        AppError::IoError(Arc::new(error))
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(error: serde_json::Error) -> Self {
+        AppError::JsonError(Arc::new(error))
+    }
 }
@@ -0,0 +1,128 @@
+use genai::chat::ToolCall;
+use serde_json::{Value, json};
+
+use crate::tools::ToolKind;
+
+// Optional leading words accepted before the topic, longest first
+const PREFIXES: [&str; 8] = [
+    "what's the current ",
+    "what is the current ",
+    "whats the current ",
+    "what's the ",
+    "what is the ",
+    "whats the ",
+    "current ",
+    "the ",
+];
+
+/// An unambiguous weather or time question that can be answered without the model
+#[derive(Debug, PartialEq)]
+pub enum Intent {
+    /// Current weather, e.g. "weather in London,GB"
+    Weather { city: String, country: String },
+    /// Current time, e.g. "what time is it in Tokyo, JP?"
+    Time { city: String, country: String },
+}
+
+/// Recognizes simple current weather and time questions with an explicit city and country.
+///
+/// Deliberately conservative: only a few fixed phrasings are accepted and the location
+/// must be `City,CC` with an ISO country code, so anything else (other days, several
+/// places, extra words) returns `None` and goes to the model.
+pub fn classify(question: &str) -> Option<Intent> {
+    if !question.is_ascii() {
+        return None;
+    }
+    let text = question.trim().trim_end_matches(['?', '.', '!']).trim_end();
+    let lower = text.to_lowercase();
+
+    let start = PREFIXES
+        .iter()
+        .find(|prefix| lower.starts_with(*prefix))
+        .map_or(0, |prefix| prefix.len());
+
+    let topics = [
+        ("weather in ", true),
+        ("time in ", false),
+        ("what time is it in ", false),
+    ];
+    let (location_start, is_weather) = topics.iter().find_map(|(topic, is_weather)| {
+        lower[start..]
+            .starts_with(topic)
+            .then_some((start + topic.len(), *is_weather))
+    })?;
+
+    let (city, country) = parse_location(&text[location_start..])?;
+    Some(if is_weather {
+        Intent::Weather { city, country }
+    } else {
+        Intent::Time { city, country }
+    })
+}
+
+/// Parses `City,CC` into a city name and an uppercase country code.
+fn parse_location(location: &str) -> Option<(String, String)> {
+    let (city, country) = location.split_once(',')?;
+    let (city, country) = (city.trim(), country.trim());
+
+    let valid_city = !city.is_empty()
+        && city
+            .chars()
+            .all(|c| c.is_ascii_alphabetic() || matches!(c, ' ' | '-' | '\'' | '.'));
+    let valid_country = country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic());
+
+    (valid_city && valid_country).then(|| (city.to_string(), country.to_uppercase()))
+}
+
+impl Intent {
+    /// The country code of the location asked about
+    pub fn country(&self) -> &str {
+        match self {
+            Intent::Weather { country, .. } | Intent::Time { country, .. } => country,
+        }
+    }
+
+    /// The tool call answering this question
+    pub fn tool_call(&self, unit: &str) -> ToolCall {
+        let (fn_name, fn_arguments) = match self {
+            Intent::Weather { city, country } => (
+                ToolKind::Weather.name(),
+                json!({ "city": city, "country": country, "unit": unit }),
+            ),
+            Intent::Time { city, country } => (
+                ToolKind::CurrentTime.name(),
+                json!({ "city": city, "country": country }),
+            ),
+        };
+        ToolCall {
+            call_id: fn_name.to_string(),
+            fn_name: fn_name.to_string(),
+            fn_arguments,
+        }
+    }
+
+    /// Phrases the tool result as a one-sentence answer, or `None` if it is incomplete
+    pub fn answer(&self, result: &Value, unit: &str) -> Option<String> {
+        match self {
+            Intent::Weather { city, .. } => {
+                let weather = format!(
+                    "It's {} and {}°{} in {}",
+                    result.get("condition")?.as_str()?.to_lowercase(),
+                    result.get("temperature")?.as_f64()?,
+                    unit,
+                    city
+                );
+                // Humidity may be reported as missing; leave it out of the answer then
+                Some(match result.get("humidity").and_then(Value::as_i64) {
+                    Some(humidity) => format!("{}, with {}% humidity.", weather, humidity),
+                    None => format!("{}.", weather),
+                })
+            }
+            Intent::Time { city, .. } => Some(format!(
+                "It's {} in {}.",
+                result.get("time")?.as_str()?,
+                city
+            )),
+        }
+    }
+}
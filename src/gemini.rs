@@ -0,0 +1,511 @@
+use std::env;
+
+use genai::chat::{ChatOptions, ChatRequest, ChatRole, ContentPart, MessageContent, ToolCall};
+use reqwest::StatusCode;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{Value, json};
+use tracing::debug;
+
+use crate::config::ApiConfig;
+use crate::error::AppError;
+use crate::retry;
+
+/// Default base URL of the Gemini API; the version segment comes from configuration
+pub const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
+// Environment variable holding the Gemini API key
+const API_KEY_VAR: &str = "GEMINI_API_KEY";
+
+/// Reads the Gemini API key from `GEMINI_API_KEY`.
+pub fn api_key() -> Result<String, AppError> {
+    env::var(API_KEY_VAR).map_err(|_| AppError::EnvVarNotSet(API_KEY_VAR.to_string()))
+}
+
+/// Text and tool calls of one model response, gathered from all of its parts
+#[derive(Debug, Default)]
+pub struct ModelResponse {
+    /// Text parts joined in order: the answer, or what the model said along with its calls
+    pub text: String,
+    /// Function calls the model asked for, in order
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl ModelResponse {
+    /// Whether the model sent neither text nor tool calls
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty() && self.tool_calls.is_empty()
+    }
+}
+
+/// Client for Gemini's streaming `generateContent` endpoint.
+///
+/// Every part of a response is kept, so a streamed response can carry tool calls and text
+/// sent along with them reaches the user; the genai client only reads the first part.
+#[derive(Default)]
+pub struct Gemini {
+    client: reqwest::Client,
+}
+
+impl Gemini {
+    /// Sends the conversation to `model` and reads the streamed response.
+    ///
+    /// # Arguments
+    /// * `api` - Connection settings for the API
+    /// * `api_key` - The Gemini API key
+    /// * `model` - Gemini model name, e.g. "gemini-2.0-flash"
+    /// * `chat_req` - System prompt, conversation and tools
+    /// * `options` - Sampling temperature, output limit and stop sequences
+    /// * `on_text` - Called with each piece of text as it arrives
+    ///
+    /// # Returns
+    /// * `ModelResponse` with the text and tool calls of all streamed parts
+    /// * Error if the request fails or the API reports an error
+    pub async fn generate(
+        &self,
+        api: &ApiConfig,
+        api_key: &str,
+        model: &str,
+        chat_req: &ChatRequest,
+        options: &ChatOptions,
+        mut on_text: impl FnMut(&str),
+    ) -> Result<ModelResponse, AppError> {
+        let url = format!(
+            "{}?alt=sse",
+            api.endpoint(&format!("models/{}:streamGenerateContent", model))
+        );
+        let body = request_body(chat_req, options).to_string();
+
+        // Only the request is retried; once text is streaming it may already be on screen
+        let mut response = retry::with_backoff(
+            &api.retry,
+            "model",
+            || async {
+                self.client
+                    .post(&url)
+                    .headers(api.headers.clone())
+                    .header("x-goog-api-key", api_key)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(body.clone())
+                    .send()
+                    .await
+                    .map_err(AppError::from)
+            },
+            is_transient,
+        )
+        .await
+        .map_err(request_failed)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::ApiRequestFailed(format!(
+                "Failed to call Gemini API: {} {}",
+                status,
+                error_message(&body)
+            )));
+        }
+
+        // Server-sent events: one `data: {...}` line per streamed response chunk
+        let mut parsed = ModelResponse::default();
+        let mut pending = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| request_failed(e.into()))?
+        {
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                read_event(&String::from_utf8_lossy(&line), &mut parsed, &mut on_text)?;
+            }
+        }
+        read_event(
+            &String::from_utf8_lossy(&pending),
+            &mut parsed,
+            &mut on_text,
+        )?;
+
+        debug!(
+            "Model response with {} characters of text and {} tool calls",
+            parsed.text.len(),
+            parsed.tool_calls.len()
+        );
+        Ok(parsed)
+    }
+}
+
+/// Whether a model request hit a rate limit, a gateway error or a failed connection
+fn is_transient(result: &Result<reqwest::Response, AppError>) -> bool {
+    match result {
+        Ok(response) => matches!(
+            response.status(),
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        ),
+        Err(AppError::RequestError(e)) => e.is_connect(),
+        Err(_) => false,
+    }
+}
+
+/// Names the Gemini API in a failed request's error
+fn request_failed(error: AppError) -> AppError {
+    AppError::ApiRequestFailed(format!("Failed to call Gemini API: {}", error))
+}
+
+/// The `error.message` of an error response, or the body as it is
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|error| error["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// Adds the parts of one server-sent event line to `response`; other lines are ignored.
+fn read_event(
+    line: &str,
+    response: &mut ModelResponse,
+    on_text: &mut impl FnMut(&str),
+) -> Result<(), AppError> {
+    let Some(data) = line.trim().strip_prefix("data:") else {
+        return Ok(());
+    };
+    let event: Value = serde_json::from_str(data.trim()).map_err(|e| {
+        AppError::ResponseParseError(format!("Gemini API sent an unreadable event: {}", e))
+    })?;
+    if event.get("error").is_some() {
+        return Err(AppError::ApiRequestFailed(format!(
+            "Failed to call Gemini API: {}",
+            error_message(data)
+        )));
+    }
+
+    let parts = event["candidates"][0]["content"]["parts"].as_array();
+    for part in parts.into_iter().flatten() {
+        if let Some(call) = part.get("functionCall") {
+            let name = call["name"].as_str().unwrap_or_default().to_string();
+            // Gemini doesn't return call ids, so the function name stands in for one
+            response.tool_calls.push(ToolCall {
+                call_id: name.clone(),
+                fn_name: name,
+                fn_arguments: call.get("args").cloned().unwrap_or(Value::Null),
+            });
+        } else if let Some(text) = part["text"].as_str()
+            && part["thought"].as_bool() != Some(true)
+        {
+            on_text(text);
+            response.text.push_str(text);
+        }
+    }
+    Ok(())
+}
+
+/// Builds the `generateContent` request body for a conversation.
+fn request_body(chat_req: &ChatRequest, options: &ChatOptions) -> Value {
+    let mut systems: Vec<String> = chat_req.system.iter().cloned().collect();
+    let mut contents = Vec::new();
+
+    for message in &chat_req.messages {
+        let role = match message.role {
+            ChatRole::Assistant => "model",
+            _ => "user",
+        };
+        let parts: Vec<Value> = match &message.content {
+            MessageContent::Text(text) if matches!(message.role, ChatRole::System) => {
+                systems.push(text.clone());
+                continue;
+            }
+            MessageContent::Text(text) => vec![json!({ "text": text })],
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text(text) => Some(json!({ "text": text })),
+                    _ => None,
+                })
+                .collect(),
+            MessageContent::ToolCalls(tool_calls) => tool_calls
+                .iter()
+                .map(|tool_call| {
+                    json!({
+                        "functionCall": { "name": tool_call.fn_name, "args": tool_call.fn_arguments }
+                    })
+                })
+                .collect(),
+            MessageContent::ToolResponses(tool_responses) => tool_responses
+                .iter()
+                .map(|tool_response| {
+                    let content = serde_json::from_str(&tool_response.content)
+                        .unwrap_or_else(|_| Value::String(tool_response.content.clone()));
+                    json!({
+                        "functionResponse": {
+                            "name": tool_response.call_id,
+                            "response": { "name": tool_response.call_id, "content": content },
+                        }
+                    })
+                })
+                .collect(),
+        };
+        if !parts.is_empty() {
+            contents.push(json!({ "role": role, "parts": parts }));
+        }
+    }
+
+    let mut body = json!({ "contents": contents });
+    if !systems.is_empty() {
+        body["systemInstruction"] = json!({ "parts": [{ "text": systems.join("\n") }] });
+    }
+    if let Some(tools) = &chat_req.tools {
+        let declarations: Vec<Value> = tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.schema,
+                })
+            })
+            .collect();
+        body["tools"] = json!([{ "function_declarations": declarations }]);
+    }
+
+    let mut generation = serde_json::Map::new();
+    if let Some(temperature) = options.temperature {
+        generation.insert("temperature".to_string(), json!(temperature));
+    }
+    if let Some(max_tokens) = options.max_tokens {
+        generation.insert("maxOutputTokens".to_string(), json!(max_tokens));
+    }
+    if !options.stop_sequences.is_empty() {
+        generation.insert("stopSequences".to_string(), json!(options.stop_sequences));
+    }
+    if !generation.is_empty() {
+        body["generationConfig"] = Value::Object(generation);
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use genai::chat::{ChatMessage, ToolResponse};
+    use reqwest::header::HeaderMap;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::retry::RetryPolicy;
+
+    fn mock_api(server: &MockServer) -> ApiConfig {
+        ApiConfig {
+            base_url: server.uri(),
+            version: Some("v1beta".to_string()),
+            headers: HeaderMap::new(),
+            lenient: false,
+            retry: RetryPolicy {
+                max_attempts: 1,
+                base_delay: Duration::ZERO,
+            },
+        }
+    }
+
+    /// A server-sent events body with one event per JSON chunk
+    fn events(chunks: &[Value]) -> String {
+        chunks
+            .iter()
+            .map(|chunk| format!("data: {}\r\n\r\n", chunk))
+            .collect()
+    }
+
+    fn parts(parts: Value) -> Value {
+        json!({ "candidates": [{ "content": { "role": "model", "parts": parts } }] })
+    }
+
+    async fn generate(server: &MockServer, on_text: impl FnMut(&str)) -> ModelResponse {
+        Gemini::default()
+            .generate(
+                &mock_api(server),
+                "test-key",
+                "gemini-test",
+                &ChatRequest::from_user("Weather in Paris?"),
+                &ChatOptions::default(),
+                on_text,
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn streamed_answers_arrive_piece_by_piece() {
+        let server = MockServer::start().await;
+        let body = events(&[
+            parts(json!([{ "text": "It is " }])),
+            parts(json!([{ "text": "22°C in Paris." }])),
+        ]);
+        Mock::given(method("POST"))
+            .and(path("/v1beta/models/gemini-test:streamGenerateContent"))
+            .and(header("x-goog-api-key", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut chunks = Vec::new();
+        let response = generate(&server, |text| chunks.push(text.to_string())).await;
+        assert_eq!(chunks, ["It is ", "22°C in Paris."]);
+        assert_eq!(response.text, "It is 22°C in Paris.");
+        assert!(response.tool_calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_read_from_the_streamed_response() {
+        let server = MockServer::start().await;
+        let body = events(&[parts(json!([
+            { "functionCall": { "name": "get_weather", "args": { "city": "Paris", "country": "FR" } } },
+            { "functionCall": { "name": "get_current_time", "args": { "city": "Paris", "country": "FR" } } },
+        ]))]);
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = generate(&server, |_| {}).await;
+        let names: Vec<&str> = response
+            .tool_calls
+            .iter()
+            .map(|call| call.fn_name.as_str())
+            .collect();
+        assert_eq!(names, ["get_weather", "get_current_time"]);
+        assert_eq!(response.tool_calls[0].call_id, "get_weather");
+        assert_eq!(
+            response.tool_calls[0].fn_arguments,
+            json!({ "city": "Paris", "country": "FR" })
+        );
+        assert!(response.text.is_empty());
+    }
+
+    #[tokio::test]
+    async fn text_sent_with_tool_calls_is_kept_and_the_calls_read() {
+        let server = MockServer::start().await;
+        let body = events(&[
+            parts(json!([{ "text": "Let me check " }])),
+            parts(json!([
+                { "text": "the weather." },
+                { "functionCall": { "name": "get_weather", "args": { "city": "Paris", "country": "FR" } } },
+            ])),
+        ]);
+        Mock::given(method("POST"))
+            .and(path("/v1beta/models/gemini-test:streamGenerateContent"))
+            .and(header("x-goog-api-key", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut chunks = Vec::new();
+        let response = generate(&server, |text| chunks.push(text.to_string())).await;
+
+        assert_eq!(response.text, "Let me check the weather.");
+        assert_eq!(chunks, ["Let me check ", "the weather."]);
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].fn_name, "get_weather");
+        assert_eq!(response.tool_calls[0].call_id, "get_weather");
+        assert_eq!(
+            response.tool_calls[0].fn_arguments,
+            json!({ "city": "Paris", "country": "FR" })
+        );
+    }
+
+    #[tokio::test]
+    async fn error_responses_carry_the_api_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(400).set_body_json(
+                    json!({ "error": { "code": 400, "message": "API key not valid" } }),
+                ),
+            )
+            .mount(&server)
+            .await;
+
+        let error = Gemini::default()
+            .generate(
+                &mock_api(&server),
+                "bad-key",
+                "gemini-test",
+                &ChatRequest::from_user("Weather in Paris?"),
+                &ChatOptions::default(),
+                |_| {},
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, AppError::ApiRequestFailed(message) if message.contains("API key not valid")),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn lines_other_than_events_are_ignored() {
+        let mut response = ModelResponse::default();
+        let mut on_text = |_: &str| {};
+        let event = format!("data: {}", parts(json!([{ "text": "22°C" }])));
+        read_event(&event, &mut response, &mut on_text).unwrap();
+        read_event("", &mut response, &mut on_text).unwrap();
+        read_event(": keep-alive", &mut response, &mut on_text).unwrap();
+        assert_eq!(response.text, "22°C");
+        assert!(response.tool_calls.is_empty());
+    }
+
+    #[test]
+    fn thoughts_are_not_shown() {
+        let mut response = ModelResponse::default();
+        let mut shown = String::new();
+        let event = format!(
+            "data: {}",
+            parts(json!([{ "text": "planning", "thought": true }, { "text": "Sunny" }]))
+        );
+        read_event(&event, &mut response, &mut |text: &str| {
+            shown.push_str(text)
+        })
+        .unwrap();
+        assert_eq!(response.text, "Sunny");
+        assert_eq!(shown, "Sunny");
+    }
+
+    #[test]
+    fn request_body_pairs_tool_responses_with_their_calls() {
+        let call = ToolCall {
+            call_id: "get_weather".to_string(),
+            fn_name: "get_weather".to_string(),
+            fn_arguments: json!({ "city": "Paris" }),
+        };
+        let chat_req = ChatRequest::default()
+            .with_system("Be brief")
+            .append_message(ChatMessage::user("Weather in Paris?"))
+            .append_message(ChatMessage::assistant(MessageContent::ToolCalls(vec![
+                call,
+            ])))
+            .append_message(ToolResponse::new(
+                "get_weather".to_string(),
+                json!({ "temp_c": 22.0 }).to_string(),
+            ));
+        let options = ChatOptions::default().with_temperature(0.5);
+
+        let body = request_body(&chat_req, &options);
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief");
+        assert_eq!(body["contents"][1]["role"], "model");
+        assert_eq!(
+            body["contents"][1]["parts"][0]["functionCall"]["name"],
+            "get_weather"
+        );
+        assert_eq!(
+            body["contents"][2]["parts"][0]["functionResponse"]["response"]["content"],
+            json!({ "temp_c": 22.0 })
+        );
+        assert_eq!(body["generationConfig"]["temperature"], 0.5);
+    }
+}
@@ -1,42 +1,66 @@
 // Module containing response data structures for time information
 mod response;
 
+use crate::config::ApiConfig;
 use crate::error::AppError;
+use crate::http::Http;
+use crate::lenient;
+use crate::retry;
 use tracing::{debug, error, info};
 
-// API endpoint for the IPGeolocation timezone service
-const GEO_LOCATION_ENDPOINT: &str = "https://api.ipgeolocation.io/timezone";
+/// Default base URL of the IPGeolocation service; the optional version segment comes from
+/// configuration
+pub const GEO_LOCATION_BASE_URL: &str = "https://api.ipgeolocation.io";
+
+// API path for the IPGeolocation timezone service
+const TIMEZONE_PATH: &str = "timezone";
 
 /// Fetches current time information for a specific location using the IPGeolocation API.
 ///
 /// # Arguments
+/// * `http` - Shared HTTP client
+/// * `api` - Connection settings for the API
 /// * `api_key` - The API key for accessing the IPGeolocation service
 /// * `location` - Location string in format "city,country" (e.g., "London,GB")
 ///
 /// # Returns
 /// * `TimeResponse` containing date and time information for the specified location
 /// * Error if the API request fails or returns an unsuccessful status code
-pub async fn get_time(api_key: &str, location: &str) -> Result<response::TimeResponse, AppError> {
+pub async fn get_time(
+    http: &Http,
+    api: &ApiConfig,
+    api_key: &str,
+    location: &str,
+) -> Result<response::TimeResponse, AppError> {
     info!("Fetching time data for location: {}", location);
 
-    // Construct the API URL with query parameters
-    let url = format!(
-        "{}?apiKey={}&location={}",
-        GEO_LOCATION_ENDPOINT, api_key, location
-    );
+    let url = api.endpoint(TIMEZONE_PATH);
+    let query = [("apiKey", api_key), ("location", location)];
 
-    // Create HTTP client and send the request
-    let client = reqwest::Client::new();
-    let response = client.get(&url).send().await?;
+    // Send the request through the shared client, retrying transient failures
+    let response = retry::with_backoff(
+        &api.retry,
+        "time",
+        || http.get(&url, &query, &api.headers),
+        retry::is_transient_response,
+    )
+    .await?;
 
-    if response.status().is_success() {
+    if response.status.is_success() {
         // Parse successful response into TimeResponse struct
-        let time_response: response::TimeResponse = response.json().await?;
+        let time_response: response::TimeResponse =
+            lenient::parse(&response.body, api.lenient, "time")?;
         debug!("Time data fetched successfully: {:?}", time_response);
         Ok(time_response)
     } else {
         // Log and return error for unsuccessful responses
-        error!("Failed to fetch time data: {}", response.status());
-        Err(AppError::ApiRequestFailed(format!("Failed to fetch time data: {}", response.status())))
+        error!("Failed to fetch time data: {}", response.status);
+        if response.status == reqwest::StatusCode::NOT_FOUND {
+            return Err(AppError::LocationNotFound(location.to_string()));
+        }
+        Err(AppError::ApiStatus {
+            code: response.status.as_u16(),
+            body: response.body,
+        })
     }
 }
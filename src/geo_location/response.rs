@@ -1,3 +1,8 @@
+use chrono::NaiveTime;
+use serde_json::Value;
+
+use crate::lenient::{Lenient, Recovered};
+
 /// Response structure for the IPGeolocation timezone API
 /// Contains date and time information for a specific location
 #[derive(serde::Deserialize, Debug)]
@@ -6,4 +11,120 @@ pub struct TimeResponse {
     pub date: String,
     /// Current time in 12-hour format (e.g., "08:30 PM")
     pub time_12: String,
+    /// IANA timezone name (e.g., "Europe/London"), if reported
+    pub timezone: Option<String>,
+    /// Standard offset from UTC in hours, without daylight saving time, if reported
+    pub timezone_offset: Option<f64>,
+    /// Current offset from UTC in hours, daylight saving time included, if reported
+    pub timezone_offset_with_dst: Option<f64>,
+    /// Whether daylight saving time is in effect, if reported
+    pub is_dst: Option<bool>,
+}
+
+impl TimeResponse {
+    /// Current offset from UTC in hours, falling back to the standard offset plus an hour of
+    /// daylight saving time when the API sends no offset with DST
+    pub fn utc_offset_hours(&self) -> Option<f64> {
+        self.timezone_offset_with_dst.or_else(|| {
+            let dst = if self.is_dst? { 1.0 } else { 0.0 };
+            Some(self.timezone_offset? + dst)
+        })
+    }
+
+    /// Current UTC offset like "UTC+05:30"
+    pub fn utc_offset(&self) -> Option<String> {
+        let hours = self.utc_offset_hours()?;
+        let minutes = (hours * 60.0).round() as i64;
+        let sign = if minutes < 0 { '-' } else { '+' };
+        Some(format!(
+            "UTC{}{:02}:{:02}",
+            sign,
+            minutes.abs() / 60,
+            minutes.abs() % 60
+        ))
+    }
+}
+
+// Lenient recovery for responses whose shape changed upstream (`--lenient-parse`)
+impl Lenient for TimeResponse {
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self> {
+        let date = recovered.string(body, &["date"])?;
+
+        // Derive the 12-hour time from the 24-hour one if needed
+        let time_12 = recovered.string(body, &["time_12"]).or_else(|| {
+            let time_24 = recovered.string(body, &["time_24"])?;
+            let time = NaiveTime::parse_from_str(&time_24, "%H:%M:%S").ok()?;
+            recovered.note("time_12 (derived from time_24)");
+            Some(time.format("%I:%M:%S %p").to_string())
+        })?;
+
+        Some(TimeResponse {
+            date,
+            time_12,
+            timezone: recovered.string(body, &["timezone"]),
+            timezone_offset: recovered.number(body, &["timezone_offset"]),
+            timezone_offset_with_dst: recovered.number(body, &["timezone_offset_with_dst"]),
+            is_dst: body.get("is_dst").and_then(Value::as_bool),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn time_response_reads_the_timezone_fields() {
+        let response: TimeResponse = serde_json::from_value(json!({
+            "geo": { "city": "London", "country": "United Kingdom" },
+            "timezone": "Europe/London",
+            "timezone_offset": 0,
+            "timezone_offset_with_dst": 1,
+            "date": "2025-06-01",
+            "date_time": "2025-06-01 20:30:00",
+            "time_24": "20:30:00",
+            "time_12": "08:30:00 PM",
+            "is_dst": true,
+            "dst_savings": 1
+        }))
+        .unwrap();
+        assert_eq!(response.date, "2025-06-01");
+        assert_eq!(response.time_12, "08:30:00 PM");
+        assert_eq!(response.timezone.as_deref(), Some("Europe/London"));
+        assert_eq!(response.timezone_offset, Some(0.0));
+        assert_eq!(response.is_dst, Some(true));
+        assert_eq!(response.utc_offset().as_deref(), Some("UTC+01:00"));
+    }
+
+    #[test]
+    fn time_response_tolerates_missing_timezone_fields() {
+        let response: TimeResponse =
+            serde_json::from_value(json!({ "date": "2025-06-01", "time_12": "08:30 AM" })).unwrap();
+        assert_eq!(response.timezone, None);
+        assert_eq!(response.is_dst, None);
+        assert_eq!(response.utc_offset(), None);
+    }
+
+    #[test]
+    fn utc_offset_falls_back_to_the_standard_offset() {
+        let response: TimeResponse = serde_json::from_value(json!({
+            "date": "2025-06-01",
+            "time_12": "08:30:00 PM",
+            "timezone_offset": -3.5,
+            "is_dst": true
+        }))
+        .unwrap();
+        assert_eq!(response.utc_offset().as_deref(), Some("UTC-02:30"));
+
+        let response: TimeResponse = serde_json::from_value(json!({
+            "date": "2025-06-01",
+            "time_12": "08:30:00 PM",
+            "timezone_offset": 5.5,
+            "is_dst": false
+        }))
+        .unwrap();
+        assert_eq!(response.utc_offset().as_deref(), Some("UTC+05:30"));
+    }
 }
@@ -0,0 +1,56 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use genai::chat::ChatMessage;
+use tracing::{info, warn};
+
+use crate::error::AppError;
+use crate::transcript;
+
+/// Default history file under the home directory, used by a bare `--history`
+pub fn default_path() -> Result<PathBuf, AppError> {
+    let home = env::var("HOME").map_err(|_| {
+        AppError::InvalidConfig("--history: HOME is not set, pass a file path".to_string())
+    })?;
+    Ok(Path::new(&home).join(".gemini-tools").join("history.json"))
+}
+
+/// Loads the conversation saved by an earlier run.
+///
+/// A missing file starts a fresh conversation; so does an unreadable or corrupt one,
+/// with a warning, so a damaged history never keeps the assistant from starting.
+pub fn load(path: &Path) -> Vec<ChatMessage> {
+    if !path.exists() {
+        info!("No history at {}, starting fresh", path.display());
+        return Vec::new();
+    }
+    match transcript::read_messages(path) {
+        Ok(messages) => {
+            info!(
+                "Loaded {} messages of history from {}",
+                messages.len(),
+                path.display()
+            );
+            messages
+        }
+        Err(e) => {
+            warn!(
+                "Ignoring unreadable history at {}, starting fresh: {}",
+                path.display(),
+                e
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// Saves the conversation for the next run, creating the file's directory if needed
+pub fn save(path: &Path, messages: &[ChatMessage]) -> Result<(), AppError> {
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        fs::create_dir_all(dir)?;
+    }
+    transcript::write_messages(path, messages)
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use tokio::sync::Semaphore;
+use tracing::{debug, warn};
+
+use crate::error::AppError;
+use crate::offline;
+
+/// Response status and body of a completed request
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+/// Shared HTTP client with a global cap on simultaneous outbound requests.
+///
+/// A permit is held from sending a request until its body has been read, so the cap
+/// bounds open connections no matter which tool or cache refresh started them.
+/// Requests over the cap wait for a permit rather than failing.
+///
+/// Each request, including reading its body, is bounded by an optional timeout; a
+/// request that runs over it fails with `AppError::Timeout`.
+///
+/// With `--offline` no request leaves the machine: each one gets a canned response instead.
+pub struct Http {
+    client: reqwest::Client,
+    permits: Semaphore,
+    limit: usize,
+    timeout: Option<Duration>,
+    offline: bool,
+}
+
+impl Http {
+    /// Creates a client allowing at most `limit` requests in flight, each taking at most
+    /// `timeout` when one is set, or answering from canned data when `offline`
+    pub fn new(limit: usize, timeout: Option<Duration>, offline: bool) -> Self {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        Http {
+            // Building only fails if the TLS backend can't be initialized
+            client: builder.build().expect("HTTP client can be built"),
+            permits: Semaphore::new(limit),
+            limit,
+            timeout,
+            offline,
+        }
+    }
+
+    /// Sends a GET request and reads the whole response body.
+    ///
+    /// # Arguments
+    /// * `url` - Request URL without query parameters
+    /// * `query` - Query parameters, percent-encoded when the request is sent
+    /// * `headers` - Extra headers to send with the request
+    pub async fn get(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        headers: &HeaderMap,
+    ) -> Result<HttpResponse, AppError> {
+        if self.offline {
+            debug!("Offline, answering with canned data");
+            return Ok(offline::response(url, query));
+        }
+
+        let _permit = match self.permits.try_acquire() {
+            Ok(permit) => permit,
+            Err(_) => {
+                warn!(
+                    "HTTP connection limit ({}) reached, waiting for a free connection",
+                    self.limit
+                );
+                self.permits
+                    .acquire()
+                    .await
+                    .expect("HTTP semaphore is never closed")
+            }
+        };
+
+        let response = self
+            .client
+            .get(url)
+            .query(query)
+            .headers(headers.clone())
+            .send()
+            .await
+            .map_err(|e| self.request_error(e))?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| self.request_error(e))?;
+        debug!("HTTP request finished with {}", status);
+        Ok(HttpResponse { status, body })
+    }
+
+    /// Tells timeouts apart from other request failures
+    fn request_error(&self, error: reqwest::Error) -> AppError {
+        match self.timeout {
+            Some(timeout) if error.is_timeout() => {
+                AppError::Timeout(format!("no response within {}s", timeout.as_secs_f64()))
+            }
+            _ => error.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn slow_responses_time_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&server)
+            .await;
+
+        let http = Http::new(1, Some(Duration::from_millis(1)), false);
+        let result = http.get(&server.uri(), &[], &HeaderMap::new()).await;
+        assert!(matches!(result, Err(AppError::Timeout(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn responses_within_the_timeout_are_read() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+
+        let http = Http::new(1, Some(Duration::from_secs(5)), false);
+        let response = http
+            .get(&server.uri(), &[], &HeaderMap::new())
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, "ok");
+    }
+}
@@ -0,0 +1,61 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+use crate::error::AppError;
+
+/// Lines read from stdin on a background thread, so reads can be raced against a timer.
+///
+/// A dedicated thread is used instead of tokio's stdin because a pending blocking read
+/// would otherwise keep the runtime from shutting down until the next line arrives.
+pub struct Input {
+    lines: mpsc::UnboundedReceiver<io::Result<String>>,
+}
+
+impl Input {
+    /// Starts reading stdin line by line in the background
+    pub fn spawn() -> Self {
+        let (sender, lines) = mpsc::unbounded_channel();
+        thread::spawn(move || {
+            loop {
+                let mut line = String::new();
+                let result = io::stdin().read_line(&mut line);
+                let done = !matches!(result, Ok(n) if n > 0);
+                if sender.send(result.map(|_| line)).is_err() || done {
+                    break;
+                }
+            }
+        });
+        Input { lines }
+    }
+
+    /// Returns a line typed while the previous answer was being produced, without waiting
+    pub fn typed_ahead(&mut self) -> Result<Option<String>, AppError> {
+        match self.lines.try_recv() {
+            Ok(line) => Ok(Some(line?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Waits for the next line, giving up after `idle_timeout` if one is set.
+    ///
+    /// # Returns
+    /// * The line, or an empty string once stdin is closed
+    /// * `None` if the idle timeout elapsed first
+    pub async fn read_line(
+        &mut self,
+        idle_timeout: Option<Duration>,
+    ) -> Result<Option<String>, AppError> {
+        let next = self.lines.recv();
+        let line = match idle_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, next).await {
+                Ok(line) => line,
+                Err(_) => return Ok(None),
+            },
+            None => next.await,
+        };
+        Ok(Some(line.transpose()?.unwrap_or_default()))
+    }
+}
@@ -0,0 +1,48 @@
+use std::process;
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+use tracing::warn;
+
+// Exit status after a second Ctrl-C, the shell convention for death by SIGINT
+const FORCED_EXIT_CODE: i32 = 130;
+
+/// Ctrl-C handling for the interactive session.
+///
+/// The first Ctrl-C asks the session to stop at the next guarded step, so the conversation
+/// can still be saved; a second one exits immediately.
+pub struct Interrupt {
+    requested: Arc<Notify>,
+}
+
+impl Interrupt {
+    /// Starts listening for Ctrl-C in the background
+    pub fn install() -> Self {
+        let requested = Arc::new(Notify::new());
+        let notify = requested.clone();
+        tokio::spawn(async move {
+            let mut presses = 0;
+            loop {
+                if let Err(e) = tokio::signal::ctrl_c().await {
+                    warn!("Ctrl-C handling unavailable: {}", e);
+                    return;
+                }
+                presses += 1;
+                if presses > 1 {
+                    process::exit(FORCED_EXIT_CODE);
+                }
+                // A stored permit is picked up by the next guard even if none is waiting now
+                notify.notify_one();
+            }
+        });
+        Interrupt { requested }
+    }
+
+    /// Runs `future` to completion, or abandons it and returns `None` on Ctrl-C.
+    pub async fn guard<F: Future>(&self, future: F) -> Option<F::Output> {
+        tokio::select! {
+            output = future => Some(output),
+            _ = self.requested.notified() => None,
+        }
+    }
+}
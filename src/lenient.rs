@@ -0,0 +1,177 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::error::AppError;
+use crate::units;
+
+/// Best-effort recovery of a response from JSON that failed strict deserialization.
+///
+/// Implementations pull known fields out of the raw JSON, tolerating moved fields,
+/// numbers sent as strings and values that can be derived from others.
+pub trait Lenient: Sized {
+    /// Extracts the response from raw JSON, recording how each field was recovered
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self>;
+}
+
+/// Log of fields extracted during a lenient parse
+#[derive(Default)]
+pub struct Recovered(Vec<String>);
+
+impl Recovered {
+    /// Reads a number at `path`, accepting numeric strings
+    pub fn number(&mut self, value: &Value, path: &[&str]) -> Option<f64> {
+        match lookup(value, path)? {
+            Value::Number(number) => {
+                self.0.push(path.join("."));
+                number.as_f64()
+            }
+            Value::String(text) => {
+                let number = text.trim().parse().ok()?;
+                self.0.push(format!("{} (from string)", path.join(".")));
+                Some(number)
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads a string at `path`, accepting numbers
+    pub fn string(&mut self, value: &Value, path: &[&str]) -> Option<String> {
+        match lookup(value, path)? {
+            Value::String(text) => {
+                self.0.push(path.join("."));
+                Some(text.clone())
+            }
+            Value::Number(number) => {
+                self.0.push(format!("{} (from number)", path.join(".")));
+                Some(number.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads a Celsius/Fahrenheit pair, deriving a missing one from the other.
+    ///
+    /// Derived values are unrounded `f64` results, so they may differ from the API's own
+    /// figure past the first few decimals; tool responses are rounded to
+    /// `OUTPUT_PRECISION` places (one by default) before the model sees them.
+    pub fn temperature_pair(
+        &mut self,
+        value: &Value,
+        celsius: &str,
+        fahrenheit: &str,
+    ) -> Option<(f64, f64)> {
+        match (
+            self.number(value, &[celsius]),
+            self.number(value, &[fahrenheit]),
+        ) {
+            (Some(c), Some(f)) => Some((c, f)),
+            (Some(c), None) => {
+                self.note(format!("{} (derived from {})", fahrenheit, celsius));
+                Some((c, units::fahrenheit(c)))
+            }
+            (None, Some(f)) => {
+                self.note(format!("{} (derived from {})", celsius, fahrenheit));
+                Some((units::celsius(f), f))
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Records a field recovered in a custom way
+    pub fn note(&mut self, description: impl Into<String>) {
+        self.0.push(description.into());
+    }
+}
+
+/// Follows a path of object keys through a JSON value
+fn lookup<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| value.get(key))
+}
+
+/// Parses an API response body, falling back to a lenient parse when enabled.
+///
+/// # Arguments
+/// * `body` - Raw response body
+/// * `lenient` - Whether to attempt recovery after a strict parse failure
+/// * `what` - Name of the response, used in logs and errors
+pub fn parse<T: DeserializeOwned + Lenient>(
+    body: &str,
+    lenient: bool,
+    what: &str,
+) -> Result<T, AppError> {
+    let strict_error = match serde_json::from_str::<T>(body) {
+        Ok(parsed) => return Ok(parsed),
+        Err(e) => e,
+    };
+    let error = || AppError::ResponseParseError(format!("{} response: {}", what, strict_error));
+
+    if !lenient {
+        return Err(error());
+    }
+
+    warn!(
+        "Failed to parse {} response ({}), trying lenient parse",
+        what, strict_error
+    );
+    let value: Value = serde_json::from_str(body).map_err(|_| error())?;
+    let mut recovered = Recovered::default();
+    let parsed = T::recover(&value, &mut recovered).ok_or_else(error)?;
+    warn!(
+        "Lenient parse of {} response recovered: {}",
+        what,
+        recovered.0.join(", ")
+    );
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::assert_temp_eq;
+
+    #[test]
+    fn temperature_pair_derives_fahrenheit_from_celsius() {
+        let mut recovered = Recovered::default();
+        let (c, f) = recovered
+            .temperature_pair(&json!({ "temp_c": 21.3 }), "temp_c", "temp_f")
+            .unwrap();
+
+        assert_temp_eq(c, 21.3);
+        assert_temp_eq(f, 70.34);
+    }
+
+    #[test]
+    fn temperature_pair_derives_celsius_from_fahrenheit() {
+        let mut recovered = Recovered::default();
+        let (c, f) = recovered
+            .temperature_pair(&json!({ "temp_f": "-40" }), "temp_c", "temp_f")
+            .unwrap();
+
+        assert_temp_eq(c, -40.0);
+        assert_temp_eq(f, -40.0);
+    }
+
+    #[test]
+    fn temperature_pair_keeps_both_reported_values() {
+        let mut recovered = Recovered::default();
+        let pair = recovered.temperature_pair(
+            &json!({ "temp_c": 10.0, "temp_f": 50.1 }),
+            "temp_c",
+            "temp_f",
+        );
+
+        assert_eq!(pair, Some((10.0, 50.1)));
+    }
+
+    #[test]
+    fn temperature_pair_needs_one_of_the_two() {
+        let mut recovered = Recovered::default();
+        assert_eq!(
+            recovered.temperature_pair(&json!({}), "temp_c", "temp_f"),
+            None
+        );
+    }
+}
@@ -1,23 +1,85 @@
 // External modules for API integration
+mod accessibility; // Screen-reader friendly output
+mod activity; // Activity suitability scoring
+mod cache; // TTL cache for tool lookups
+mod cli; // Command-line arguments
+mod colors; // Role colors for terminal output
+mod comparison; // Multi-city weather comparisons
+mod conditions; // WeatherAPI condition texts
+mod config; // Startup configuration
+mod context; // Shared state for tool calls
+mod conversation; // Conversation consistency checks
+mod dates; // Relative date resolution
+mod error; // Custom error types
+mod fast_path; // Direct answers to simple questions
+mod gemini; // Streaming Gemini API calls
 mod geo_location; // Time API integration
+mod history; // Conversation kept between runs
+mod http; // Shared HTTP client with a connection cap
+mod input; // Interactive input with an idle timeout
+mod interrupt; // Ctrl-C handling
+mod lenient; // Best-effort response parsing
+mod offline; // Canned API responses for --offline
+mod output; // Where assistant messages are shown
+mod profile; // Per-turn timing breakdown
+mod retry; // Backoff for transient API failures
+mod rounding; // Precision control for numeric tool outputs
+mod script; // Latin-script checks for city names
+mod sessions; // Named conversations saved to disk
+mod telemetry; // Metrics export
+#[cfg(test)]
+mod test_util; // Shared test helpers
+mod tools; // Tool declarations, typed arguments and implementations
+mod transcript; // Saved conversations for --replay
+mod units; // Temperature unit defaults
+mod validation; // Tool argument checks
+mod warmup; // Startup API probes
 mod weather; // Weather API integration
-mod error; // Custom error types
 
+use cache::CacheKey;
+use cli::{Cli, IdleAction, LogFormat};
+use colors::Role;
+use config::{Config, Settings};
+use context::Context;
 use error::AppError;
+use gemini::Gemini;
+use input::Input;
+use interrupt::Interrupt;
+use output::{JsonLines, Output, Terminal};
+use sessions::Sessions;
+use tools::{ToolArgs, ToolKind};
+
+use std::{env, path::Path, sync::atomic::Ordering, time::Instant};
 
-use std::{env, io::Write};
+use chrono::{Local, Timelike};
 
 use futures::stream::{self, StreamExt};
-use genai::{
-    Client,
-    chat::{ChatMessage, ChatRequest, ChatResponse, MessageContent, Tool, ToolCall, ToolResponse},
+use genai::chat::{
+    ChatMessage, ChatRequest, ChatRole, MessageContent, Tool, ToolCall, ToolResponse,
 };
 use serde_json::json;
-use tracing::{Instrument, debug, error, info, span};
-use tracing_subscriber::EnvFilter;
+use tracing::{Instrument, debug, error, info, span, warn};
+use tracing_subscriber::{EnvFilter, fmt::writer::BoxMakeWriter};
+
+// Default system prompt for the assistant, unless `--system-prompt` gives another
+const SYSTEM_PROMPT: &str = "Answer with one sentence or tool call. Send `exit` to stop.";
+
+// Extra instruction appended to the system prompt in accessible mode
+const ACCESSIBLE_PROMPT: &str =
+    "Never use emoji. Always write units in words, e.g. \"22 degrees Celsius\".";
 
-// Gemini model version used for this application
-const MODEL: &str = "gemini-2.0-flash";
+// Prefix that makes a question bypass the semantic answer cache
+const FRESH_PREFIX: &str = "!fresh";
+
+// Prefixes that ask for a detailed answer to a single question
+const DETAIL_PREFIXES: [&str; 2] = ["!long", "!detail"];
+
+// Extra instruction added to the system prompt for a `!long` or `!detail` question
+const DETAIL_PROMPT: &str =
+    "For this answer only, ignore the one-sentence limit and answer in as much detail as helpful.";
+
+// Follow-up sent when an answer just repeats raw tool output (`--require-natural-language`)
+const NATURAL_LANGUAGE_PROMPT: &str = "Answer again in plain natural language, summarizing the tool results instead of quoting raw JSON.";
 
 /// Entry point for the Gemini-powered weather and time assistant.
 ///
@@ -28,75 +90,203 @@ const MODEL: &str = "gemini-2.0-flash";
 /// 4. Processes user input in a continuous loop until 'exit' is received
 #[tokio::main]
 async fn main() -> Result<(), AppError> {
-    // Initialize logging with environment-based filter configuration
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .init();
+    // Parse command-line arguments first, then fill in what they leave out from the config
+    // file, since either can choose the log format
+    let cli = config::parse_args();
+    let settings = Settings::load(&cli.config)?;
+    let cli = settings.cli(env::args_os())?;
+
+    // Initialize logging with environment-based filter configuration; with --json stdout
+    // carries only the answers, so logs go to stderr
+    let writer = if cli.json {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(log_filter(cli.verbose))
+        .with_ansi(colors::enabled(&cli))
+        .with_writer(writer);
+    match cli.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+    }
+
+    // Resolve configuration, failing fast on invalid settings
+    let config = Config::new(&cli, &settings)?;
+    let output: Box<dyn Output> = if config.json {
+        Box::new(JsonLines::new(
+            std::io::stdout(),
+            config.accessible,
+            config.color,
+        ))
+    } else {
+        Box::new(Terminal::new(
+            std::io::stdout(),
+            config.accessible,
+            config.color,
+        ))
+    };
+    let ctx = Context::new(config, output);
+    info!("Using model {}", ctx.config.model);
+    if ctx.config.offline {
+        info!("Offline: tool calls are answered from sample data, not the weather and time APIs");
+    }
+
+    // Send metrics to StatsD when configured
+    if let Some(addr) = &cli.statsd_addr {
+        telemetry::install_statsd(addr)?;
+    }
+
+    // Optionally replace the bundled condition list with the latest official one
+    if ctx.config.refresh_conditions {
+        conditions::refresh(&ctx.http).await;
+    }
+
+    // Probe the APIs up front so a bad key fails now rather than mid-conversation
+    if ctx.config.warmup {
+        warmup::warm_up(&ctx).await?;
+    }
+
+    // Check the API keys now, so a missing one disables its tools instead of failing the
+    // first time the model calls them
+    preflight(&ctx);
 
     // Initialize the Gemini API client
-    let client = Client::default();
-
-    // Define tool for weather information queries
-    // This tool requires city, country, and temperature unit parameters
-    let weather_tool = Tool::new("get_weather")
-        .with_description("Get the current weather for a location")
-        .with_schema(json!({
-            "type": "object",
-            "properties": {
-                "city": {
-                    "type": "string",
-                    "description": "City name in English, Latin script (e.g., \"Seattle\")."
-                },
-                "country": {
-                    "type": "string",
-                    "description": "ISO‑3166‑1 alpha‑2 country code, e.g., \"US\"."
-                },
-                "unit": {
-                    "type": "string",
-                    "enum": ["C", "F"],
-                    "description": "Temperature unit (C for Celsius, F for Fahrenheit)"
-                }
-            },
-            "required": ["city", "country", "unit"]
-        }));
-
-    // Define tool for time information queries
-    // This tool requires city and country parameters
-    let current_time_tool: Tool = Tool::new("get_current_time")
-        .with_description("Get the current time for a location")
-        .with_schema(json!({
-            "type": "object",
-            "properties": {
-                "city": {
-                    "type": "string",
-                    "description": "City name in English, Latin script (e.g., \"Seattle\")."
-                },
-                "country": {
-                    "type": "string",
-                    "description": "ISO‑3166‑1 alpha‑2 country code, e.g., \"US\"."
-                }
-            },
-            "required": ["city", "country"]
-        }));
+    let client = Gemini::default();
 
     // Initialize chat request with system prompt and available tools
-    let mut chat_req = ChatRequest::default()
-        .with_system("Answer with one sentence or tool call. Send `exit` to stop.")
-        .with_tools(vec![weather_tool, current_time_tool]);
-
-    // Display welcome message to the user
-    span!(tracing::Level::INFO, "chat", role = "assistant").in_scope(|| {
-        info!("Hi, I'm a weather bot. I can help you with the weather forecast");
-        info!("Send `exit` to stop");
-    });
+    let system_prompt = ctx.config.system_prompt.as_deref().unwrap_or(SYSTEM_PROMPT);
+    let mut system_prompt = system_prompt.to_string();
+    if ctx.config.accessible {
+        system_prompt = format!("{} {}", system_prompt, ACCESSIBLE_PROMPT);
+    }
+    if let Some(unit) = ctx.config.default_unit {
+        system_prompt = format!("{} {}", system_prompt, unit_prompt(unit));
+    }
+    // Only offer the model tools whose API key is set
+    let tools = enabled_tools(
+        ToolKind::ALL
+            .into_iter()
+            .map(|kind| kind.declaration(&ctx.config))
+            .collect(),
+        ctx.config.weather_provider,
+        |var| ctx.api_key(var).is_ok(),
+    );
+    let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
+    if names.is_empty() {
+        warn!("No tools enabled, the model can only answer from its own knowledge");
+    } else {
+        info!("Enabled tools: {}", names.join(", "));
+    }
+    let mut chat_req = ChatRequest::default().with_system(system_prompt);
+    if !tools.is_empty() {
+        chat_req = chat_req.with_tools(tools);
+    }
 
-    // Main interaction loop - process user requests until 'exit' is received
-    let mut buffer = String::new();
-    print!("> ");
-    std::io::stdout().flush()?;
-    std::io::stdin().read_line(&mut buffer)?;
+    // Every way out reports how the session ended, so scripts can tell a finished
+    // session from one that was cut off
+    let mut turns = 0;
+    let end = run_session(&client, &ctx, &cli, chat_req, &mut turns).await;
+    report_session_end(
+        &ctx,
+        turns,
+        end.as_ref().map_or(SessionEnd::Error, |end| *end),
+    );
+    end.map(|_| ())
+}
 
-    while buffer.trim() != "exit" {
+/// Run the session: a `--replay`, a `--once` question or the interactive loop.
+///
+/// `turns` counts the answered turns as they go, so it is still known when the
+/// session fails part way.
+async fn run_session(
+    client: &Gemini,
+    ctx: &Context,
+    cli: &Cli,
+    mut chat_req: ChatRequest,
+    turns: &mut usize,
+) -> Result<SessionEnd, AppError> {
+    // Re-run a saved transcript instead of starting an interactive session
+    if let Some(path) = &cli.replay {
+        replay(client, ctx, chat_req, path, turns).await?;
+        return Ok(SessionEnd::Replayed);
+    }
+
+    // Restore the named session, if one was given
+    let mut sessions = Sessions::new(&cli.sessions_dir, cli.session.as_deref())?;
+    chat_req.messages = sessions.load()?;
+
+    // Or pick up the conversation where the previous run left it
+    if let Some(path) = &ctx.config.history {
+        chat_req.messages = history::load(path);
+    }
+
+    // Answer a single question and exit instead of starting an interactive session
+    if let Some(question) = &cli.once {
+        chat_req = chat_req.append_message(ChatMessage::user(question.clone()));
+        let answered;
+        (chat_req, answered) = answer_turn(client, ctx, chat_req).await;
+        if let Some(answer) = last_text(&chat_req)
+            && !ctx.streamed.swap(false, Ordering::Relaxed)
+        {
+            ctx.output
+                .answer(answer, &conversation::turn_tool_calls(&chat_req.messages));
+        }
+        sessions.save(&chat_req.messages)?;
+        if let Some(path) = &ctx.config.history {
+            history::save(path, &chat_req.messages)?;
+        }
+        *turns = 1;
+        // A failed answer still exits with an error status, for scripts to notice
+        if !answered {
+            return Err(AppError::ApiRequestFailed(
+                "the question could not be answered".to_string(),
+            ));
+        }
+        return Ok(SessionEnd::Once);
+    }
+
+    // Display welcome message to the user, greeting according to the local time of day
+    // An empty --banner suppresses the greeting like --quiet
+    let banner = match &ctx.config.persona.banner {
+        Some(banner) => banner.clone(),
+        None => format!(
+            "{}, I'm a weather bot. I can help you with the weather forecast",
+            greeting(Local::now().hour())
+        ),
+    };
+    if !ctx.config.quiet && !banner.is_empty() {
+        ctx.output.notice(&banner);
+        ctx.output.notice("Send `exit` to stop");
+    }
+
+    // Main interaction loop - process user requests until 'exit' is received
+    let mut input = Input::spawn();
+    let interrupt = Interrupt::install();
+    let end = loop {
+        let Some(request) = interrupt
+            .guard(next_request(ctx, &mut input, &mut chat_req))
+            .await
+        else {
+            break SessionEnd::Interrupted;
+        };
+        let Some(buffer) = request? else {
+            break SessionEnd::IdleTimeout;
+        };
+        // Not even a newline means stdin was closed (e.g. the end of piped input);
+        // finish the prompt line so the shell's prompt starts on a fresh one
+        if buffer.is_empty() {
+            ctx.output.end_line();
+            break SessionEnd::Eof;
+        }
+        if buffer.trim() == "exit" {
+            break SessionEnd::Exit;
+        }
         let user_request = buffer.trim_start_matches('>').trim();
 
         // Skip empty requests
@@ -104,42 +294,470 @@ async fn main() -> Result<(), AppError> {
             continue;
         }
 
+        // Conversation and session commands are handled locally, without the model
+        if let Some(reply) = conversation_command(user_request, &mut chat_req) {
+            ctx.output.answer(&reply, &[]);
+            sessions.save(&chat_req.messages)?;
+            continue;
+        }
+        if let Some(reply) = session_command(&mut sessions, user_request, &mut chat_req) {
+            ctx.output.answer(&reply, &[]);
+            continue;
+        }
+
+        // A `!fresh` prefix bypasses the semantic answer cache for this question, and
+        // `!long` or `!detail` relaxes the answer length; they can be combined in any order
+        let (mut user_request, mut fresh, mut detailed) = (user_request, false, false);
+        loop {
+            if let Some(rest) = user_request.strip_prefix(FRESH_PREFIX) {
+                (user_request, fresh) = (rest.trim(), true);
+            } else if let Some(rest) = DETAIL_PREFIXES
+                .iter()
+                .find_map(|prefix| user_request.strip_prefix(prefix))
+            {
+                (user_request, detailed) = (rest.trim(), true);
+            } else {
+                break;
+            }
+        }
+
         // Log user input with appropriate tracing span
         span!(tracing::Level::INFO, "chat", role = "user").in_scope(|| {
-            info!(user_request);
+            info!(user_request = %colors::paint(Role::User, user_request, ctx.config.color));
         });
+        let turn_started = Instant::now();
+
+        // Key for reusing answers to reworded repeats, when the semantic cache is enabled
+        let answer_key = ctx
+            .config
+            .semantic_cache_ttl
+            .filter(|_| !fresh && !detailed)
+            .map(|ttl| {
+                let tools = chat_req.tools.iter().flatten().map(|t| t.name.as_str());
+                (cache::semantic_key(user_request, tools), ttl)
+            });
 
         // Add user message to the ongoing conversation
         let chat_message = ChatMessage::user(user_request.to_string());
         chat_req = chat_req.append_message(chat_message);
 
-        // Process the request through the Gemini model
-        // This may involve multiple calls if tool usage is required
-        chat_req = call_loop(&client, chat_req)
-            .instrument(span!(tracing::Level::INFO, "call_loop"))
-            .await?;
+        match answer_key.and_then(|(key, _)| ctx.answers.get(key)) {
+            Some(answer) => {
+                debug!("Answer served from the semantic cache");
+                chat_req = chat_req.append_message(ChatMessage::assistant(answer));
+            }
+            None => {
+                // Answer unambiguous weather and time questions straight from the tool
+                let intent =
+                    fast_path::classify(user_request).filter(|_| ctx.config.fast_path && !detailed);
+                let direct = match intent {
+                    Some(intent) => match interrupt.guard(fast_answer(ctx, &intent)).await {
+                        Some(direct) => direct,
+                        None => {
+                            chat_req.messages.pop();
+                            break SessionEnd::Interrupted;
+                        }
+                    },
+                    None => None,
+                };
 
-        // Check if the assistant response is 'exit' to terminate the conversation
-        if let Some(last_message) = chat_req.messages.last() {
-            if let MessageContent::Text(text) = &last_message.content {
-                span!(tracing::Level::INFO, "chat", role = "assistant")
-                    .in_scope(|| info!("{}", text));
-                if text.as_str() == "exit" {
-                    break;
+                match direct {
+                    Some(answer) => {
+                        debug!("Answered without calling the model");
+                        chat_req = chat_req.append_message(ChatMessage::assistant(answer));
+                    }
+                    None => {
+                        // Relax the length limit for this turn only
+                        let system = chat_req.system.clone();
+                        if detailed {
+                            chat_req.system = Some(format!(
+                                "{} {}",
+                                system.as_deref().unwrap_or_default(),
+                                DETAIL_PROMPT
+                            ));
+                        }
+
+                        // Process the request through the Gemini model
+                        // This may involve multiple calls if tool usage is required
+                        // On Ctrl-C the unanswered question is dropped, so the saved
+                        // conversation ends with a complete turn
+                        let answer = interrupt.guard(answer_turn(client, ctx, chat_req.clone()));
+                        let Some((answered_req, answered)) = answer.await else {
+                            chat_req.system = system;
+                            chat_req.messages.pop();
+                            break SessionEnd::Interrupted;
+                        };
+                        chat_req = answered_req;
+                        chat_req.system = system;
+
+                        if answered
+                            && let Some((key, ttl)) = answer_key
+                            && let Some(answer) = last_text(&chat_req)
+                        {
+                            ctx.answers.insert(key, answer.to_string(), ttl);
+                        }
+                    }
                 }
             }
         }
 
-        // Prepare for next user input
-        buffer.clear();
-        print!("> ");
-        std::io::stdout().flush()?;
-        std::io::stdin().read_line(&mut buffer)?;
+        // Keep the named session on disk up to date
+        sessions.save(&chat_req.messages)?;
+        *turns += 1;
+
+        // Show where the time went, then start timing the next turn afresh
+        let profile = ctx.profile.take_summary(turn_started.elapsed());
+        if ctx.config.profile {
+            ctx.output.profile(*turns, &profile.to_string());
+        }
+
+        // Check if the assistant response is 'exit' to terminate the conversation
+        if let Some(last_message) = chat_req.messages.last()
+            && let MessageContent::Text(text) = &last_message.content
+        {
+            // A streamed answer is already on screen
+            if !ctx.streamed.swap(false, Ordering::Relaxed) {
+                let tool_calls = conversation::turn_tool_calls(&chat_req.messages);
+                ctx.output.answer(text, &tool_calls);
+            }
+            if text.as_str() == "exit" {
+                break SessionEnd::Exit;
+            }
+        }
+    };
+
+    if let SessionEnd::Interrupted = end {
+        ctx.output.end_line();
+        ctx.output.notice("Interrupted, goodbye");
+    }
+
+    // Keep the conversation for the next run
+    if let Some(path) = &ctx.config.history {
+        history::save(path, &chat_req.messages)?;
+    }
+
+    Ok(end)
+}
+
+/// Emit the `session_end` event with the turn count and why the session ended.
+///
+/// Always logged, and with `--json` also written to stdout after the answers.
+fn report_session_end(ctx: &Context, turns: usize, end: SessionEnd) {
+    info!(
+        event = "session_end",
+        turns,
+        reason = end.as_str(),
+        "Session ended"
+    );
+    ctx.output.session_end(turns, end.as_str());
+}
+
+/// Log filter for `-v`, `-vv` or `-vvv`, covering this crate only; `RUST_LOG` otherwise.
+fn log_filter(verbose: u8) -> EnvFilter {
+    let level = match verbose {
+        0 => return EnvFilter::from_default_env(),
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    EnvFilter::new(format!("{}={}", env!("CARGO_CRATE_NAME"), level))
+}
+
+/// Why the session ended
+#[derive(Debug, Clone, Copy)]
+enum SessionEnd {
+    /// The user (or the model) said `exit`
+    Exit,
+    /// Standard input was closed
+    Eof,
+    /// No input arrived within `--idle-timeout`
+    IdleTimeout,
+    /// The user pressed Ctrl-C
+    Interrupted,
+    /// The `--once` question was answered
+    Once,
+    /// A `--replay` went through every turn of the transcript
+    Replayed,
+    /// The session stopped on an error
+    Error,
+}
+
+impl SessionEnd {
+    /// Name reported in the `session_end` log event
+    fn as_str(self) -> &'static str {
+        match self {
+            SessionEnd::Exit => "exit",
+            SessionEnd::Eof => "eof",
+            SessionEnd::IdleTimeout => "idle_timeout",
+            SessionEnd::Interrupted => "interrupted",
+            SessionEnd::Once => "once",
+            SessionEnd::Replayed => "replayed",
+            SessionEnd::Error => "error",
+        }
+    }
+}
+
+/// Handle `/reset`, `/history` and `/tools`, returning the reply to show.
+///
+/// `/reset` forgets the conversation while keeping the system prompt and tools,
+/// `/history` tells how many messages the model currently sees and `/tools` lists the
+/// tools offered to the model with their descriptions. Returns `None` for any other input.
+fn conversation_command(user_request: &str, chat_req: &mut ChatRequest) -> Option<String> {
+    match user_request {
+        "/reset" => {
+            chat_req.messages.clear();
+            Some("Conversation cleared, starting fresh".to_string())
+        }
+        "/history" => Some(format!(
+            "{} messages in the conversation",
+            chat_req.messages.len()
+        )),
+        "/tools" => {
+            let tools: Vec<String> = chat_req
+                .tools
+                .iter()
+                .flatten()
+                .map(|tool| match &tool.description {
+                    Some(description) => format!("- {}: {}", tool.name, description),
+                    None => format!("- {}", tool.name),
+                })
+                .collect();
+            Some(if tools.is_empty() {
+                "No tools enabled".to_string()
+            } else {
+                format!("Available tools:\n{}", tools.join("\n"))
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Handle `/sessions` and `/switch <name>`, returning the reply to show.
+///
+/// Returns `None` for any other input, which is then sent to the model.
+fn session_command(
+    sessions: &mut Sessions,
+    user_request: &str,
+    chat_req: &mut ChatRequest,
+) -> Option<String> {
+    let reply = if user_request == "/sessions" {
+        sessions.list().map(|names| {
+            let active = sessions.active();
+            let names: Vec<String> = names
+                .into_iter()
+                .map(|name| match active {
+                    Some(active) if active == name => format!("{} (active)", name),
+                    _ => name,
+                })
+                .collect();
+            if names.is_empty() {
+                "No sessions".to_string()
+            } else {
+                format!("Sessions: {}", names.join(", "))
+            }
+        })
+    } else if let Some(name) = user_request.strip_prefix("/switch ") {
+        let name = name.trim();
+        sessions.switch(name, &chat_req.messages).map(|messages| {
+            let reply = if messages.is_empty() {
+                format!("Switched to new session '{}'", name)
+            } else {
+                format!(
+                    "Switched to session '{}' ({} messages)",
+                    name,
+                    messages.len()
+                )
+            };
+            chat_req.messages = messages;
+            reply
+        })
+    } else {
+        return None;
+    };
+
+    Some(reply.unwrap_or_else(|e| e.to_string()))
+}
+
+/// Prompt for the next user request, applying the idle timeout.
+///
+/// A line typed ahead while the last answer was being produced is echoed after a fresh
+/// prompt, so it reads as the next question rather than getting lost in the output above.
+///
+/// With `--idle-action reset` the conversation is cleared and the prompt shown again;
+/// otherwise `None` is returned to end the session.
+async fn next_request(
+    ctx: &Context,
+    input: &mut Input,
+    chat_req: &mut ChatRequest,
+) -> Result<Option<String>, AppError> {
+    loop {
+        ctx.output.prompt(&ctx.config.persona.prompt);
+        if let Some(line) = input.typed_ahead()? {
+            // EOF (an empty read) falls through to the blocking read, which reports it again
+            if !line.is_empty() {
+                ctx.output.echo(&line);
+                return Ok(Some(line));
+            }
+        }
+
+        if let Some(line) = input.read_line(ctx.config.idle_timeout).await? {
+            return Ok(Some(line));
+        }
+
+        ctx.output.end_line();
+        match ctx.config.idle_action {
+            IdleAction::Exit => {
+                ctx.output.notice("No input for a while, goodbye");
+                return Ok(None);
+            }
+            IdleAction::Reset => {
+                ctx.output
+                    .notice("No input for a while, starting a new conversation");
+                chat_req.messages.clear();
+            }
+        }
+    }
+}
+
+/// Answer a classified question by calling its tool directly (`--fast-path`).
+///
+/// Returns `None` when the tool fails, so the question goes to the model instead.
+async fn fast_answer(ctx: &Context, intent: &fast_path::Intent) -> Option<String> {
+    let unit = ctx.default_unit(intent.country());
+    let tool_response = make_tool_call(ctx, intent.tool_call(unit)).await;
+    let result: serde_json::Value = serde_json::from_str(&tool_response.content).ok()?;
+    if result.get("error").is_some() {
+        return None;
+    }
+    intent.answer(&result, unit)
+}
+
+/// The text of the last message in the conversation, if it is a text message.
+fn last_text(chat_req: &ChatRequest) -> Option<&str> {
+    chat_req
+        .messages
+        .last()
+        .and_then(|message| message.content.text_as_str())
+}
+
+/// Instruction added to the system prompt for a session-wide `--unit`.
+fn unit_prompt(unit: &str) -> String {
+    let name = if unit == "F" { "Fahrenheit" } else { "Celsius" };
+    format!(
+        "The user prefers {}: use unit \"{}\" unless they ask for another one.",
+        name, unit
+    )
+}
+
+/// Pick a greeting for the given local hour (0-23).
+fn greeting(hour: u32) -> &'static str {
+    match hour {
+        5..=11 => "Good morning",
+        12..=16 => "Good afternoon",
+        17..=21 => "Good evening",
+        _ => "Hello",
+    }
+}
+
+/// Re-run the user turns of a saved transcript with fresh model calls.
+///
+/// Old assistant and tool messages are ignored; each question is sent through
+/// `call_loop` in order and its new answer is shown next to the original one.
+async fn replay(
+    client: &Gemini,
+    ctx: &Context,
+    chat_req: ChatRequest,
+    path: &Path,
+    replayed: &mut usize,
+) -> Result<(), AppError> {
+    let turns = transcript::load(path)?;
+    info!("Replaying {} turns from {}", turns.len(), path.display());
+
+    let mut chat_req = chat_req;
+    for (index, turn) in turns.iter().enumerate() {
+        let turn_started = Instant::now();
+        chat_req = chat_req.append_message(ChatMessage::user(turn.question.clone()));
+        (chat_req, _) = answer_turn(client, ctx, chat_req).await;
+
+        let profile = ctx.profile.take_summary(turn_started.elapsed());
+        if ctx.config.profile {
+            ctx.output.profile(index + 1, &profile.to_string());
+        }
+
+        let old = turn.answer.as_deref().unwrap_or("(no answer)");
+        let new = last_text(&chat_req).unwrap_or("(no answer)");
+        ctx.output.replayed(index + 1, &turn.question, old, new);
+        *replayed += 1;
     }
 
     Ok(())
 }
 
+/// Answer the latest question through `call_loop`, falling back to the failure message.
+///
+/// On failure the technical error is logged and the conversation is rolled back to the
+/// question, followed by `--failure-message` as the answer. The flag tells whether the
+/// model actually answered.
+async fn answer_turn(client: &Gemini, ctx: &Context, chat_req: ChatRequest) -> (ChatRequest, bool) {
+    match call_loop(client, ctx, chat_req.clone())
+        .instrument(span!(tracing::Level::INFO, "call_loop"))
+        .await
+    {
+        Ok(chat_req)
+            if ctx.config.require_natural_language
+                && conversation::echoes_tool_json(&chat_req.messages) =>
+        {
+            (rephrase(client, ctx, chat_req).await, true)
+        }
+        Ok(chat_req) => (chat_req, true),
+        Err(e) => {
+            error!("Failed to answer: {}", e);
+            let failure = ChatMessage::assistant(ctx.config.failure_message.clone());
+            (chat_req.append_message(failure), false)
+        }
+    }
+}
+
+/// Ask the model once more to put an answer that quotes raw tool output into words.
+///
+/// The echoed answer and the follow-up are dropped from the conversation when the new
+/// answer arrives; if the follow-up fails, the echoed answer is kept.
+async fn rephrase(client: &Gemini, ctx: &Context, chat_req: ChatRequest) -> ChatRequest {
+    info!("Answer repeats raw tool output, asking the model to summarize it");
+    let follow_up = chat_req
+        .clone()
+        .append_message(ChatMessage::user(NATURAL_LANGUAGE_PROMPT));
+    // The rephrased answer still has to be shown even if the echoed one was streamed
+    let streamed = ctx.streamed.swap(false, Ordering::Relaxed);
+    match call_loop(client, ctx, follow_up)
+        .instrument(span!(tracing::Level::INFO, "call_loop"))
+        .await
+    {
+        Ok(mut rephrased) => {
+            let is_follow_up = |message: &ChatMessage| {
+                matches!(message.role, ChatRole::User)
+                    && matches!(&message.content, MessageContent::Text(text) if text == NATURAL_LANGUAGE_PROMPT)
+            };
+            if let Some(index) = rephrased.messages.iter().rposition(is_follow_up) {
+                // The echoed answer is gone already if the conversation had to be trimmed
+                let start = match index.checked_sub(1) {
+                    Some(echo) if matches!(rephrased.messages[echo].role, ChatRole::Assistant) => {
+                        echo
+                    }
+                    _ => index,
+                };
+                rephrased.messages.drain(start..=index);
+            }
+            rephrased
+        }
+        Err(e) => {
+            warn!("Failed to rephrase the answer: {}", e);
+            ctx.streamed.store(streamed, Ordering::Relaxed);
+            chat_req
+        }
+    }
+}
+
 /// Continuously make calls to the model until no more tool responses are needed.
 ///
 /// This function handles the complete conversation flow when tools are involved:
@@ -150,12 +768,34 @@ async fn main() -> Result<(), AppError> {
 ///
 /// This approach allows the model to use tools as needed to fulfill the user request
 /// without requiring additional user input during the process.
-async fn call_loop(client: &Client, chat_req: ChatRequest) -> Result<ChatRequest, AppError> {
+async fn call_loop(
+    client: &Gemini,
+    ctx: &Context,
+    chat_req: ChatRequest,
+) -> Result<ChatRequest, AppError> {
     let mut req = chat_req;
 
+    // Keep long sessions within the model's context window, oldest turns first
+    let reserved = req
+        .system
+        .as_deref()
+        .map_or(0, conversation::estimated_tokens);
+    let (messages, dropped) =
+        conversation::trim_to_budget(req.messages, reserved, ctx.config.max_context_tokens);
+    if dropped > 0 {
+        warn!(
+            "Conversation exceeded about {} tokens, dropped the {} oldest messages",
+            ctx.config.max_context_tokens, dropped
+        );
+    }
+    req.messages = messages;
+
+    // Make sure tool calls and responses line up before the model sees them
+    req.messages = conversation::repair(req.messages);
+
     loop {
         // Make a call to the model and get updated request with response
-        req = make_call(client, req).await?;
+        req = make_call(client, ctx, req).await?;
 
         // Break the loop if the last message is not a tool response
         // This indicates the model has completed its processing
@@ -172,179 +812,605 @@ async fn call_loop(client: &Client, chat_req: ChatRequest) -> Result<ChatRequest
     Ok(req)
 }
 
+/// Reads a tool call's arguments into the typed arguments of the tool it names.
+///
+/// The model may send null for a call without arguments, which is treated as an empty
+/// object. Country codes come back normalized, so equal calls parse to equal arguments.
+fn parse_tool_call(tool_call: &ToolCall) -> Result<(ToolKind, ToolArgs), AppError> {
+    let mut args = match &tool_call.fn_arguments {
+        serde_json::Value::Null => serde_json::Map::new(),
+        arguments => arguments.as_object().cloned().ok_or_else(|| {
+            AppError::ResponseParseError("Invalid tool call arguments format".to_string())
+        })?,
+    };
+
+    // Tools look cities up by their English name, so ask the model to translate others,
+    // and countries by ISO code, so ask it to correct anything else
+    script::check_city_names(&args)?;
+    validation::normalize_country_codes(&mut args)?;
+
+    let kind = ToolKind::from_name(&tool_call.fn_name)?;
+    Ok((kind, kind.parse_args(&args)?))
+}
+
 /// Make a tool call to the model.
-async fn make_tool_call(tool_call: ToolCall) -> ToolResponse {
-    info!(
+///
+/// Runs in a `tool_call` span that records the tool, how long it took and whether it succeeded.
+#[tracing::instrument(
+    name = "tool_call",
+    skip_all,
+    fields(tool = %tool_call.fn_name, duration_ms, outcome)
+)]
+async fn make_tool_call(ctx: &Context, tool_call: ToolCall) -> ToolResponse {
+    let call = format!(
         "Tool call: \n\tFunction: {}\n\tArguments: {}",
         tool_call.fn_name, tool_call.fn_arguments
     );
+    info!("{}", colors::paint(Role::Tool, &call, ctx.config.color));
 
-    // Execute a tool call requested by the model and format the response.
-    //
-    // Handles two types of tools:
-    // - get_weather: Fetches current weather conditions for a location
-    // - get_current_time: Fetches current time for a location
-    //
-    // Returns a properly formatted ToolResponse that will be sent back to the model.
-    let tool_response = async {
-        // Extract arguments from the tool call
-        let args = tool_call.fn_arguments.as_object()
-            .ok_or_else(|| AppError::ResponseParseError("Invalid tool call arguments format".to_string()))?;
-
-        match tool_call.fn_name.as_str() {
-            // Weather information tool
-            "get_weather" => {
-                // Extract and validate required parameters
-                let city = args
-                    .get("city")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| AppError::MissingParameter("city".to_string()))?;
-
-                let country = args
-                    .get("country")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| AppError::MissingParameter("country".to_string()))?;
-
-                let unit = args
-                    .get("unit")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| AppError::MissingParameter("temperature unit".to_string()))?;
-
-                let location = format!("{},{}", city, country);
-
-                // Call the weather API to get current conditions
-                let weather_api_key = env::var("WEATHER_API_KEY")
-                    .map_err(|_| AppError::EnvVarNotSet("WEATHER_API_KEY".to_string()))?;
-                let weather_response = weather::get_weather(&weather_api_key, &location).await?;
-
-                // Convert temperature to requested unit
-                let temperature: f64 = match unit {
-                    "C" => weather_response.current.temp_c,
-                    "F" => weather_response.current.temp_f,
-                    _ => weather_response.current.temp_c,
-                };
+    let tool = tool_call.fn_name.clone();
+    metrics::counter!(telemetry::TOOL_REQUESTS, "tool" => tool.clone()).increment(1);
+    let started = Instant::now();
 
-                // Format the response with relevant weather information
-                Ok(ToolResponse::new(
-                    tool_call.call_id.clone(),
-                    json!({
-                        "temperature": temperature,
-                        "condition": weather_response.current.condition.text,
-                        "humidity": weather_response.current.humidity,
-                    })
-                    .to_string(),
-                ))
-            }
+    // Execute the tool call; each tool's declaration and implementation live in `tools`,
+    // and the JSON result is sent back to the model as a ToolResponse
+    let parsed = parse_tool_call(&tool_call);
 
-            // Time information tool
-            "get_current_time" => {
-                // Extract and validate required parameters
-                let city = args
-                    .get("city")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| AppError::MissingParameter("city".to_string()))?;
-
-                let country = args
-                    .get("country")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| AppError::MissingParameter("country".to_string()))?;
-
-                let location = format!("{},{}", city, country);
-
-                // Call the geolocation API to get time information
-                let geo_location_api_key = env::var("IP_GEOLOCATION_API_KEY")
-                    .map_err(|_| AppError::EnvVarNotSet("IP_GEOLOCATION_API_KEY".to_string()))?;
-                let time_response =
-                    geo_location::get_time(&geo_location_api_key, &location).await?;
-
-                // Format the response with date and time information
-                Ok(ToolResponse::new(
-                    tool_call.call_id.clone(),
-                    json!({
-                        "time": format!("{} {}", time_response.date, time_response.time_12),
-                    })
-                    .to_string(),
-                ))
-            }
+    // Composite tools are cached whole, keyed by their normalized arguments so calls that
+    // differ only in spelling, like "gb" and "GB", share a result
+    let cached = parsed.as_ref().ok().and_then(|(kind, args)| {
+        kind.result_cache_ttl(&ctx.config).map(|ttl| {
+            let key = CacheKey::for_args(*kind, args, Local::now().date_naive());
+            (key, ttl)
+        })
+    });
 
-            // Handle unsupported tool calls
-            _ => Err(AppError::UnsupportedToolCall(tool_call.fn_name.clone())),
+    let execute = async {
+        // Unknown tools and unusable arguments are reported back to the model
+        let (_, args) = parsed?;
+        args.execute(ctx).await
+    };
+
+    let tool_response = match cached {
+        Some((key, ttl)) => {
+            ctx.cache
+                .get_or_fetch(key, ttl, ctx.config.negative_cache_ttl, || execute)
+                .await
         }
-    }
-    .await;
+        None => execute.await,
+    };
+    let elapsed = started.elapsed();
+    metrics::histogram!(telemetry::TOOL_LATENCY, "tool" => tool.clone())
+        .record(elapsed.as_secs_f64());
+    ctx.profile.record_tool(&tool, elapsed);
 
     // Handle successful responses or errors
-    match tool_response {
-        Ok(tool_response) => tool_response,
+    let (outcome, result) = tool_result(tool_response, ctx.config.output_precision);
+    if outcome != "ok" {
+        metrics::counter!(telemetry::TOOL_ERRORS, "tool" => tool).increment(1);
+    }
+    let response = ToolResponse::new(tool_call.call_id.clone(), result.to_string());
+
+    // Record the latency on the span as well, so `RUST_LOG` filtering shows the slow tools
+    let span = tracing::Span::current();
+    span.record("duration_ms", elapsed.as_millis() as u64);
+    span.record("outcome", outcome);
+    let timing = format!(
+        "{} took {}ms ({})",
+        tool_call.fn_name,
+        elapsed.as_millis(),
+        outcome
+    );
+    info!("{}", colors::paint(Role::Tool, &timing, ctx.config.color));
+    response
+}
+
+/// The JSON sent back to the model for a tool's outcome, with the outcome's name for the logs.
+///
+/// Results are rounded to `precision` decimals; errors become an object the model can relay,
+/// and a missing API key a structured `"available": false` result.
+fn tool_result(
+    result: Result<serde_json::Value, AppError>,
+    precision: u32,
+) -> (&'static str, serde_json::Value) {
+    match result {
+        // Round numeric fields to avoid floating-point noise in model-facing data
+        Ok(result) => ("ok", rounding::round_json(result, precision)),
+        // A missing API key means the service is unavailable, not that the call went wrong,
+        // so the model can tell the user plainly instead of relaying a technical error
+        Err(AppError::EnvVarNotSet(var)) => {
+            warn!("Tool call unavailable, {} is not set", var);
+            let result = json!({
+                "available": false,
+                "reason": format!("{} service not configured", service_name(&var)),
+            });
+            ("unavailable", result)
+        }
         Err(e) => {
             error!("Failed to make tool call: {}", e);
-            // Return error information in a format the model can understand
-            ToolResponse::new(
-                tool_call.call_id.clone(),
-                json!({
-                    "error": e.to_string(),
-                })
-                .to_string(),
-            )
+            // Return error information in a format the model can understand; rejected keys
+            // and rate limits get a plain explanation instead of the raw response
+            let error = match e {
+                AppError::ApiStatus {
+                    code: 401 | 403, ..
+                } => "The service rejected its API key, so this data is unavailable".to_string(),
+                AppError::ApiStatus { code: 429, .. } => {
+                    "The service is rate limiting requests, try again in a little while".to_string()
+                }
+                e => e.to_string(),
+            };
+            ("error", json!({ "error": error }))
         }
     }
 }
 
+/// Reports the capabilities disabled by API keys that aren't set. Startup goes on either way,
+/// since one of the services may be all a user needs.
+fn preflight(ctx: &Context) {
+    let provider = ctx.config.weather_provider;
+    let disabled: Vec<ToolKind> = ToolKind::ALL
+        .into_iter()
+        .filter(|kind| ctx.api_key(kind.key_var(provider)).is_err())
+        .collect();
+
+    let mut missing: Vec<&str> = disabled.iter().map(|kind| kind.key_var(provider)).collect();
+    missing.sort_unstable();
+    missing.dedup();
+    for var in missing {
+        let tools: Vec<&str> = disabled
+            .iter()
+            .filter(|kind| kind.key_var(provider) == var)
+            .map(|kind| kind.name())
+            .collect();
+        warn!(
+            "{} is not set, so {} questions can't be answered ({} disabled); add it to .env to enable them",
+            var,
+            service_name(var),
+            tools.join(", ")
+        );
+    }
+}
+
+/// Keeps the tools whose API key `has_key` reports as set, in their original order, so the
+/// model is never offered a tool that is bound to fail. Tools with an unknown name are dropped.
+fn enabled_tools(
+    tools: Vec<Tool>,
+    provider: weather::Provider,
+    has_key: impl Fn(&str) -> bool,
+) -> Vec<Tool> {
+    tools
+        .into_iter()
+        .filter(|tool| {
+            ToolKind::from_name(&tool.name).is_ok_and(|kind| has_key(kind.key_var(provider)))
+        })
+        .collect()
+}
+
+/// User-facing name of the service configured by an API key variable.
+fn service_name(var: &str) -> &'static str {
+    match var {
+        "WEATHER_API_KEY" | "OPENWEATHERMAP_API_KEY" => "weather",
+        "IP_GEOLOCATION_API_KEY" => "time",
+        _ => "required",
+    }
+}
+
+/// Reformat a JSON string with indentation, returning it unchanged if it isn't valid JSON.
+fn pretty_json(content: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(content)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| content.to_string())
+}
+
 /// Make a call to the Gemini model and process the response.
 ///
 /// This function:
-/// 1. Sends the current conversation to the model
-/// 2. Processes different types of responses (text or tool calls)
+/// 1. Sends the current conversation to the model, showing text as it arrives with `--stream`
+/// 2. Processes the response: a text answer, or tool calls with any text sent along
 /// 3. For tool calls, executes them in parallel and adds results to conversation
 /// 4. Returns the updated conversation context
-async fn make_call(client: &Client, chat_req: ChatRequest) -> Result<ChatRequest, AppError> {
+async fn make_call(
+    client: &Gemini,
+    ctx: &Context,
+    chat_req: ChatRequest,
+) -> Result<ChatRequest, AppError> {
     // Send the request to the model and log for debugging
     debug!("Sending request to the model: {:?}", chat_req.messages);
-    let response: ChatResponse = client.exec_chat(MODEL, chat_req.clone(), None).await
-        .map_err(|e| AppError::ApiRequestFailed(format!("Failed to call Gemini API: {}", e)))?;
+
+    let api_key = gemini::api_key()?;
+    let mut retries_left = ctx.config.empty_response_retries;
+    let response = loop {
+        metrics::counter!(telemetry::MODEL_REQUESTS).increment(1);
+        let started = Instant::now();
+
+        // With --stream text goes on screen as it arrives, answer or not
+        let mut streamed = String::new();
+        let response = client
+            .generate(
+                &ctx.config.gemini,
+                &api_key,
+                &ctx.config.model,
+                &chat_req,
+                &ctx.config.chat_options,
+                |chunk| {
+                    if !ctx.config.stream {
+                        return;
+                    }
+                    // Skip leading whitespace so the text starts where the prompt was
+                    let chunk = if streamed.is_empty() {
+                        chunk.trim_start()
+                    } else {
+                        chunk
+                    };
+                    ctx.output.chunk(chunk);
+                    streamed.push_str(chunk);
+                },
+            )
+            .await;
+        let elapsed = started.elapsed();
+        metrics::histogram!(telemetry::MODEL_LATENCY).record(elapsed.as_secs_f64());
+        ctx.profile.record_model(elapsed);
+        let response = response.inspect_err(|_| {
+            metrics::counter!(telemetry::MODEL_ERRORS).increment(1);
+        })?;
+        if !streamed.is_empty() {
+            ctx.output.end_stream();
+        }
+
+        // Empty responses are often transient, so resend the same request a few times
+        if response.is_empty() && retries_left > 0 {
+            retries_left -= 1;
+            warn!(
+                "Empty response from the model, retrying ({} retries left)",
+                retries_left
+            );
+            continue;
+        }
+        break (response, !streamed.is_empty());
+    };
+    let (response, streamed) = response;
+    let text = response.text.trim();
 
     // Process different types of model responses
-    let req: ChatRequest = match response.content {
-        // Handle simple text responses
-        Some(MessageContent::Text(text)) => {
-            chat_req.append_message(ChatMessage::assistant(text.trim()))
+    let req: ChatRequest = if !response.tool_calls.is_empty() {
+        let tool_calls = response.tool_calls;
+
+        // Show text sent with the calls right away, unless it was streamed, and keep it
+        // ahead of them
+        let chat_req = if text.is_empty() {
+            chat_req
+        } else {
+            if !streamed {
+                ctx.output.notice(text);
+            }
+            chat_req.append_message(ChatMessage::assistant(text))
+        };
+
+        // Then add the model's tool call request to the conversation
+        let chat_req = chat_req.append_message(ChatMessage::assistant(MessageContent::ToolCalls(
+            tool_calls.clone(),
+        )));
+
+        // Refuse calls beyond the per-turn limit, telling the model to be more focused
+        let mut tool_calls = tool_calls;
+        let rejected = tool_calls.split_off(tool_calls.len().min(ctx.config.max_tool_calls));
+        if !rejected.is_empty() {
+            warn!(
+                "Model requested {} tool calls, rejecting {} over the limit of {}",
+                tool_calls.len() + rejected.len(),
+                rejected.len(),
+                ctx.config.max_tool_calls
+            );
         }
+        let rejected = rejected.into_iter().map(|tool_call| {
+                ToolResponse::new(
+                    tool_call.call_id,
+                    json!({
+                        "error": format!(
+                            "Too many tool calls in one turn (limit {}); this call was not run. Request fewer, more focused calls.",
+                            ctx.config.max_tool_calls
+                        ),
+                    })
+                    .to_string(),
+                )
+            });
 
-        // Handle tool call requests from the model
-        Some(MessageContent::ToolCalls(tool_calls)) => {
-            // First add the model's tool call request to the conversation
-            let chat_req = chat_req.append_message(ChatMessage::assistant(
-                MessageContent::ToolCalls(tool_calls.clone()),
-            ));
+        // Execute tool calls in parallel (up to 3 concurrent calls)
+        let pending = stream::iter(tool_calls)
+            .map(|tool_call| async move { make_tool_call(ctx, tool_call).await });
 
-            // Execute tool calls in parallel (up to 3 concurrent calls)
-            let tool_calls: Vec<ToolResponse> = stream::iter(tool_calls)
-                .map(|tool_call| async move { make_tool_call(tool_call).await })
-                .buffer_unordered(3)
-                .collect::<Vec<ToolResponse>>()
-                .await;
+        // Results arrive in completion order unless the transcript must be deterministic
+        let started = Instant::now();
+        let mut tool_calls: Vec<ToolResponse> = if ctx.config.ordered_tools {
+            pending.buffered(3).collect().await
+        } else {
+            pending.buffer_unordered(3).collect().await
+        };
+        ctx.profile.record_tools(started.elapsed());
+        tool_calls.extend(rejected);
 
-            // Log tool call results for debugging
+        // Log tool call results for debugging
+        if ctx.config.pretty {
+            for tool_response in &tool_calls {
+                debug!(
+                    "Tool response {}:\n{}",
+                    tool_response.call_id,
+                    pretty_json(&tool_response.content)
+                );
+            }
+        } else {
             debug!("Tool calls: {:#?}", tool_calls);
+        }
 
-            // Add all tool responses to the conversation
+        // Add all tool responses to the conversation, one message each or bundled into one;
+        // either way the model matches each response to its call by `call_id`
+        if ctx.config.batch_tool_responses {
+            chat_req.append_message(ChatMessage {
+                role: ChatRole::Tool,
+                content: MessageContent::ToolResponses(tool_calls),
+                options: None,
+            })
+        } else {
             tool_calls
                 .into_iter()
                 .fold(chat_req, |chat_req, next| chat_req.append_message(next))
         }
-
-        // Handle unsupported response types
-        Some(_) => {
-            error!("> Bot: Unsupported response type");
-            chat_req.append_message(ChatMessage::assistant("Unsupported response type"))
-        }
-
+    } else if text.is_empty() {
         // Handle empty responses
-        None => {
-            error!("> Bot: No response");
-            chat_req.append_message(ChatMessage::assistant("No response"))
+        error!("> Bot: No response");
+        chat_req.append_message(ChatMessage::assistant("No response"))
+    } else {
+        // A streamed answer is already on screen
+        if streamed {
+            ctx.streamed.store(true, Ordering::Relaxed);
         }
+        chat_req.append_message(ChatMessage::assistant(text))
     };
 
     Ok(req)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::cache::Cache;
+
+    fn tool_call(fn_name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            call_id: fn_name.to_string(),
+            fn_name: fn_name.to_string(),
+            fn_arguments: arguments,
+        }
+    }
+
+    /// A context configured by `args` on the command line and nothing else, whatever the
+    /// process environment holds
+    fn test_context(args: &[&str]) -> Context {
+        let settings = Settings::from_file_text("");
+        let cli = settings.cli(["cloud-gemini"].iter().chain(args)).unwrap();
+        let config = Config::new(&cli, &settings).unwrap();
+        Context::new(
+            config,
+            Box::new(Terminal::new(std::io::sink(), false, false)),
+        )
+    }
+
+    /// The result cache key of a tool call asked on 2024-05-01
+    fn result_key(tool_call: &ToolCall) -> CacheKey {
+        let (kind, args) = parse_tool_call(tool_call).unwrap();
+        CacheKey::for_args(kind, &args, day("2024-05-01"))
+    }
+
+    #[test]
+    fn equivalent_calls_share_a_result_key() {
+        let lower = tool_call(
+            "get_forecast",
+            json!({ "city": "london", "country": "gb", "unit": "c", "days": 3 }),
+        );
+        let upper = tool_call(
+            "get_forecast",
+            json!({ "days": 3, "unit": "C", "country": "GB", "city": "London" }),
+        );
+        assert_eq!(result_key(&lower), result_key(&upper));
+
+        let other_day = tool_call(
+            "get_forecast",
+            json!({ "city": "London", "country": "GB", "unit": "C", "days": 2 }),
+        );
+        assert_ne!(result_key(&lower), result_key(&other_day));
+    }
+
+    #[test]
+    fn relative_days_are_keyed_by_their_date() {
+        let chance = |day: &str| {
+            tool_call(
+                "chance_of",
+                json!({ "city": "Oslo", "country": "NO", "condition": "rain", "day": day }),
+            )
+        };
+        assert_eq!(
+            result_key(&chance("tomorrow")),
+            result_key(&chance("Tomorrow"))
+        );
+        assert_ne!(
+            result_key(&chance("tomorrow")),
+            result_key(&chance("today"))
+        );
+
+        // The same words asked a day later name another date
+        let (kind, args) = parse_tool_call(&chance("tomorrow")).unwrap();
+        assert_ne!(
+            CacheKey::for_args(kind, &args, day("2024-05-01")),
+            CacheKey::for_args(kind, &args, day("2024-05-02"))
+        );
+    }
+
+    #[test]
+    fn null_arguments_count_as_no_arguments() {
+        // Null reaches the tool's own checks like an empty object, not as a format error
+        for arguments in [serde_json::Value::Null, json!({})] {
+            match parse_tool_call(&tool_call("get_weather", arguments)) {
+                Err(AppError::MissingParameter(name)) => assert_eq!(name, "city"),
+                other => panic!("expected a missing city, got {:?}", other),
+            }
+        }
+
+        // Arguments that aren't an object at all are still malformed
+        assert!(matches!(
+            parse_tool_call(&tool_call("get_weather", json!(["Oslo", "NO"]))),
+            Err(AppError::ResponseParseError(_))
+        ));
+    }
+
+    #[test]
+    fn missing_keys_give_a_structured_unavailable_result() {
+        let (outcome, result) = tool_result(
+            Err(AppError::EnvVarNotSet("WEATHER_API_KEY".to_string())),
+            2,
+        );
+        assert_eq!(outcome, "unavailable");
+        assert_eq!(
+            result,
+            json!({ "available": false, "reason": "weather service not configured" })
+        );
+
+        let (_, result) = tool_result(
+            Err(AppError::EnvVarNotSet("IP_GEOLOCATION_API_KEY".to_string())),
+            2,
+        );
+        assert_eq!(result["reason"], "time service not configured");
+    }
+
+    #[test]
+    fn other_failures_give_an_error_result() {
+        let (outcome, result) = tool_result(
+            Err(AppError::ApiStatus {
+                code: 401,
+                body: "bad key".to_string(),
+            }),
+            2,
+        );
+        assert_eq!(outcome, "error");
+        assert_eq!(
+            result,
+            json!({ "error": "The service rejected its API key, so this data is unavailable" })
+        );
+
+        let (outcome, result) = tool_result(Ok(json!({ "temp_c": 21.456 })), 1);
+        assert_eq!((outcome, result), ("ok", json!({ "temp_c": 21.5 })));
+    }
+
+    #[test]
+    fn missing_unit_falls_back_to_the_configured_default() {
+        let ctx = test_context(&["--unit", "F"]);
+        assert_eq!(ctx.default_unit("GB"), "F");
+        assert_eq!(ctx.default_unit("US"), "F");
+
+        let ctx = test_context(&["--unit", "c"]);
+        assert_eq!(ctx.default_unit("US"), "C");
+    }
+
+    #[test]
+    fn missing_unit_follows_the_country_without_a_default() {
+        let ctx = test_context(&[]);
+        assert_eq!(ctx.default_unit("US"), "F");
+        assert_eq!(ctx.default_unit("GB"), "C");
+    }
+
+    /// Names of the tools `enabled_tools` keeps when only the `keys` are set
+    fn enabled_names(names: &[&str], provider: weather::Provider, keys: &[&str]) -> Vec<String> {
+        let tools = names.iter().map(|name| Tool::new(*name)).collect();
+        enabled_tools(tools, provider, |var| keys.contains(&var))
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect()
+    }
+
+    #[test]
+    fn enabled_tools_follow_the_keys_that_are_set() {
+        let names = [
+            "get_weather",
+            "get_current_time",
+            "get_forecast",
+            "compare_weather",
+        ];
+        let provider = weather::Provider::WeatherApi;
+
+        assert_eq!(
+            enabled_names(
+                &names,
+                provider,
+                &["WEATHER_API_KEY", "IP_GEOLOCATION_API_KEY"]
+            ),
+            names
+        );
+        assert_eq!(
+            enabled_names(&names, provider, &["WEATHER_API_KEY"]),
+            ["get_weather", "get_forecast", "compare_weather"]
+        );
+        assert_eq!(
+            enabled_names(&names, provider, &["IP_GEOLOCATION_API_KEY"]),
+            ["get_current_time"]
+        );
+        assert!(enabled_names(&names, provider, &[]).is_empty());
+    }
+
+    #[test]
+    fn enabled_tools_use_the_weather_provider_key() {
+        let names = ["get_weather", "get_forecast", "compare_weather"];
+        assert_eq!(
+            enabled_names(
+                &names,
+                weather::Provider::OpenWeatherMap,
+                &["OPENWEATHERMAP_API_KEY"]
+            ),
+            ["get_weather", "compare_weather"]
+        );
+    }
+
+    #[test]
+    fn enabled_tools_drop_unknown_names() {
+        let enabled = enabled_names(
+            &["get_weather", "get_horoscope"],
+            weather::Provider::WeatherApi,
+            &["WEATHER_API_KEY"],
+        );
+        assert_eq!(enabled, ["get_weather"]);
+    }
+
+    #[tokio::test]
+    async fn cache_hit_skips_execution() {
+        let cache = Cache::default();
+        let executions = AtomicUsize::new(0);
+        let execute = || async {
+            executions.fetch_add(1, Ordering::Relaxed);
+            Ok(json!({ "chance_percent": 80 }))
+        };
+
+        for arguments in [
+            json!({ "city": "Paris", "country": "fr", "condition": "rain" }),
+            json!({ "city": "paris", "country": "FR", "condition": "rain" }),
+        ] {
+            let result = cache
+                .get_or_fetch(
+                    result_key(&tool_call("chance_of", arguments)),
+                    Duration::from_secs(60),
+                    Duration::from_secs(30),
+                    execute,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result["chance_percent"], 80);
+        }
+        assert_eq!(executions.load(Ordering::Relaxed), 1);
+    }
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+}
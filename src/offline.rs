@@ -0,0 +1,174 @@
+use chrono::{Days, Local, NaiveDate};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use crate::http::HttpResponse;
+use crate::units::fahrenheit;
+use crate::weather;
+
+/// API key passed to the canned endpoints, so tools run without real keys (`--offline`)
+pub const API_KEY: &str = "offline";
+
+// Local time reported by the canned timezone endpoint
+const SAMPLE_TIME: &str = "12:00:00 PM";
+
+// One week of sample forecast days, repeated for longer forecasts:
+// (high °C, low °C, max wind kph, chance of rain, condition code, condition text)
+const SAMPLE_DAYS: [(f64, f64, f64, i32, i32, &str); 7] = [
+    (21.0, 12.0, 10.0, 0, 1000, "Sunny"),
+    (19.0, 11.0, 14.0, 10, 1003, "Partly cloudy"),
+    (16.0, 10.0, 22.0, 60, 1063, "Patchy rain possible"),
+    (14.0, 9.0, 28.0, 85, 1183, "Light rain"),
+    (17.0, 10.0, 16.0, 20, 1006, "Cloudy"),
+    (22.0, 13.0, 8.0, 0, 1000, "Sunny"),
+    (20.0, 12.0, 12.0, 10, 1003, "Partly cloudy"),
+];
+
+/// Canned response for a request to one of the weather or time APIs (`--offline`).
+///
+/// Endpoints are told apart by their path, so custom base URLs and version segments work
+/// too. Every location gets the same readings under its own name; dates start with the
+/// machine's current date. Unknown endpoints get a 404.
+pub fn response(path: &str, query: &[(&str, &str)]) -> HttpResponse {
+    let today = Local::now().date_naive();
+
+    // Echo the queried place back, as the real APIs resolve it
+    let (city, country) = query_param(query, "q")
+        .unwrap_or_default()
+        .split_once(',')
+        .unwrap_or(("Sampleton", "SL"));
+
+    let body = if path.ends_with("/current.json") {
+        current_weather(city, country)
+    } else if path.ends_with("/forecast.json") {
+        let days = query_param(query, "days")
+            .and_then(|days| days.parse().ok())
+            .unwrap_or(1)
+            .clamp(1, weather::MAX_FORECAST_DAYS);
+        forecast(today, days)
+    } else if path.ends_with("/astronomy.json") {
+        astronomy()
+    } else if path.ends_with("/timezone") {
+        timezone(today)
+    } else if path.ends_with("/data/2.5/weather") {
+        openweathermap_weather(city, country)
+    } else {
+        return HttpResponse {
+            status: StatusCode::NOT_FOUND,
+            body: json!({ "error": "no canned response for this endpoint" }).to_string(),
+        };
+    };
+
+    HttpResponse {
+        status: StatusCode::OK,
+        body: body.to_string(),
+    }
+}
+
+/// WeatherAPI current conditions at `city`, with air quality readings for `aqi=yes` requests
+fn current_weather(city: &str, country: &str) -> Value {
+    let now = Local::now().format("%Y-%m-%d %H:%M").to_string();
+    json!({
+        "location": {
+            "name": city,
+            "region": "",
+            "country": country,
+            "localtime": now
+        },
+        "current": {
+            "last_updated": now,
+            "temp_c": 18.0,
+            "temp_f": 64.4,
+            "feelslike_c": 17.0,
+            "feelslike_f": 62.6,
+            "is_day": 1,
+            "condition": { "text": "Partly cloudy", "code": 1003 },
+            "humidity": 60,
+            "wind_kph": 12.0,
+            "wind_mph": 7.5,
+            "wind_dir": "WSW",
+            "pressure_mb": 1015.0,
+            "pressure_in": 29.97,
+            "uv": 4.0,
+            "air_quality": { "pm2_5": 8.5, "pm10": 14.2, "us-epa-index": 1 }
+        }
+    })
+}
+
+/// WeatherAPI daily forecast starting `today`, without any weather alerts
+fn forecast(today: NaiveDate, days: u32) -> Value {
+    let forecastday: Vec<Value> = (0..days)
+        .map(|offset| {
+            let (high, low, wind, rain, code, text) =
+                SAMPLE_DAYS[offset as usize % SAMPLE_DAYS.len()];
+            let date = today + Days::new(u64::from(offset));
+            json!({
+                "date": date.format("%Y-%m-%d").to_string(),
+                "day": {
+                    "maxtemp_c": high,
+                    "maxtemp_f": fahrenheit(high),
+                    "mintemp_c": low,
+                    "mintemp_f": fahrenheit(low),
+                    "maxwind_kph": wind,
+                    "daily_chance_of_rain": rain,
+                    "daily_chance_of_snow": 0,
+                    "condition": { "text": text, "code": code }
+                }
+            })
+        })
+        .collect();
+
+    json!({
+        "forecast": { "forecastday": forecastday },
+        "alerts": { "alert": [] }
+    })
+}
+
+/// WeatherAPI sun and moon times
+fn astronomy() -> Value {
+    json!({
+        "astronomy": {
+            "astro": {
+                "sunrise": "06:45 AM",
+                "sunset": "07:30 PM",
+                "moonrise": "09:12 PM",
+                "moonset": "08:05 AM",
+                "moon_phase": "Waxing Gibbous",
+                "moon_illumination": 78
+            }
+        }
+    })
+}
+
+/// IPGeolocation local time, always noon UTC on `today`
+fn timezone(today: NaiveDate) -> Value {
+    json!({
+        "date": today.format("%Y-%m-%d").to_string(),
+        "time_12": SAMPLE_TIME,
+        "timezone": "UTC",
+        "timezone_offset": 0,
+        "timezone_offset_with_dst": 0,
+        "is_dst": false
+    })
+}
+
+/// OpenWeatherMap current conditions at `city`, matching `current_weather` in metric units
+fn openweathermap_weather(city: &str, country: &str) -> Value {
+    json!({
+        "name": city,
+        "sys": { "country": country },
+        "dt": Local::now().timestamp(),
+        "timezone": 0,
+        "weather": [{ "description": "partly cloudy" }],
+        "main": { "temp": 18.0, "feels_like": 17.0, "pressure": 1015, "humidity": 60 },
+        "wind": { "speed": 3.3, "deg": 250 }
+    })
+}
+
+/// Value of a query parameter, if present
+fn query_param<'a>(query: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    query
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| *value)
+}
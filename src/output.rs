@@ -0,0 +1,321 @@
+use std::io::Write;
+use std::sync::Mutex;
+
+use serde_json::{Value, json};
+use tracing::{debug, info, span};
+
+use crate::accessibility;
+use crate::colors::{self, Role};
+
+/// Where assistant messages are shown to the user.
+///
+/// The conversation logic only talks to this sink, so how messages are presented
+/// (`--json`, `--accessible`) is decided once at startup.
+pub trait Output: Send + Sync {
+    /// Shows the final answer of a turn, with the tool calls made for it
+    fn answer(&self, text: &str, tool_calls: &[Value]);
+    /// Shows an assistant message that isn't an answer, like the banner or a goodbye
+    fn notice(&self, text: &str);
+    /// Shows an interim result while a turn is still being answered (`--stream-progress`)
+    fn progress(&self, text: &str);
+    /// Shows where the time of a turn went (`--profile`)
+    fn profile(&self, turn: usize, summary: &str);
+    /// Shows a replayed question with its recorded and its new answer (`--replay`)
+    fn replayed(&self, turn: usize, question: &str, old: &str, new: &str);
+    /// Shows the input prompt, without ending the line
+    fn prompt(&self, prompt: &str);
+    /// Echoes a line typed ahead after a fresh prompt
+    fn echo(&self, line: &str);
+    /// Ends the prompt line when no input follows it
+    fn end_line(&self);
+    /// Shows the next piece of an answer as it is generated (`--stream`)
+    fn chunk(&self, text: &str);
+    /// Ends a streamed answer
+    fn end_stream(&self);
+    /// Reports that the session ended, after how many turns and why
+    fn session_end(&self, turns: usize, reason: &str);
+}
+
+/// Human-readable output: everything written to `writer` as plain (or colored) lines
+pub struct Terminal<W> {
+    writer: Mutex<W>,
+    accessible: bool,
+    color: bool,
+}
+
+impl<W: Write + Send> Terminal<W> {
+    /// Creates a terminal sink writing to `writer`, spelling units out when `accessible`
+    /// and showing assistant text in green when `color`
+    pub fn new(writer: W, accessible: bool, color: bool) -> Self {
+        Terminal {
+            writer: Mutex::new(writer),
+            accessible,
+            color,
+        }
+    }
+
+    /// Writes `text` as it is, flushing so a prompt shows before the input is read
+    fn write(&self, text: &str) {
+        let mut writer = self.writer.lock().unwrap();
+        let _ = write!(writer, "{}", text);
+        let _ = writer.flush();
+    }
+
+    /// Writes a line of assistant text, honoring accessible mode and color
+    fn assistant_line(&self, text: &str) {
+        let text = display_text(text, self.accessible);
+        self.write(&format!(
+            "{}\n",
+            colors::paint(Role::Assistant, &text, self.color)
+        ));
+    }
+}
+
+impl<W: Write + Send> Output for Terminal<W> {
+    fn answer(&self, text: &str, _tool_calls: &[Value]) {
+        // Still recorded in the logs, which no longer show it on screen
+        span!(tracing::Level::INFO, "chat", role = "assistant").in_scope(|| debug!("{}", text));
+        self.assistant_line(text);
+    }
+
+    fn notice(&self, text: &str) {
+        span!(tracing::Level::INFO, "chat", role = "assistant").in_scope(|| debug!("{}", text));
+        self.assistant_line(text);
+    }
+
+    fn progress(&self, text: &str) {
+        self.assistant_line(text);
+    }
+
+    fn profile(&self, _turn: usize, summary: &str) {
+        self.write(&format!("{}\n", summary));
+    }
+
+    fn replayed(&self, turn: usize, question: &str, old: &str, new: &str) {
+        self.write(&format!(
+            "[{}] {}\n",
+            turn,
+            colors::paint(Role::User, question, self.color)
+        ));
+        if old == new {
+            self.assistant_line(&format!("unchanged: {}", new));
+        } else {
+            self.assistant_line(&format!("old: {}", old));
+            self.assistant_line(&format!("new: {}", new));
+        }
+    }
+
+    fn prompt(&self, prompt: &str) {
+        self.write(&display_text(prompt, self.accessible));
+    }
+
+    fn echo(&self, line: &str) {
+        self.write(&display_text(line, self.accessible));
+    }
+
+    fn end_line(&self) {
+        self.write("\n");
+    }
+
+    fn chunk(&self, text: &str) {
+        self.write(&colors::paint(Role::Assistant, text, self.color));
+    }
+
+    fn end_stream(&self) {
+        self.write("\n");
+    }
+
+    // Already in the logs; the user saw the session end
+    fn session_end(&self, _turns: usize, _reason: &str) {}
+}
+
+/// Output for scripts (`--json`): each answer as one JSON line, everything else as log lines
+pub struct JsonLines<W> {
+    writer: Mutex<W>,
+    accessible: bool,
+    color: bool,
+}
+
+impl<W: Write + Send> JsonLines<W> {
+    /// Creates a JSON sink writing answers to `writer`; only the log lines are colored
+    pub fn new(writer: W, accessible: bool, color: bool) -> Self {
+        JsonLines {
+            writer: Mutex::new(writer),
+            accessible,
+            color,
+        }
+    }
+
+    /// Logs a line of assistant text in `span`, honoring accessible mode and color
+    fn log_line(&self, span: tracing::Span, text: &str) {
+        let text = display_text(text, self.accessible);
+        let text = colors::paint(Role::Assistant, &text, self.color);
+        span.in_scope(|| info!("{}", text));
+    }
+
+    /// Writes `line` as one line of JSON to stdout
+    fn write_line(&self, line: &Value) {
+        let mut writer = self.writer.lock().unwrap();
+        let _ = writeln!(writer, "{}", line);
+        let _ = writer.flush();
+    }
+}
+
+impl<W: Write + Send> Output for JsonLines<W> {
+    /// Writes `{"role":"assistant","text":...,"tool_calls":[...]}`, honoring accessible mode
+    fn answer(&self, text: &str, tool_calls: &[Value]) {
+        let text = display_text(text, self.accessible);
+        self.write_line(&json!({ "role": "assistant", "text": text, "tool_calls": tool_calls }));
+    }
+
+    fn notice(&self, text: &str) {
+        self.log_line(
+            span!(tracing::Level::INFO, "chat", role = "assistant"),
+            text,
+        );
+    }
+
+    fn progress(&self, text: &str) {
+        self.log_line(span!(tracing::Level::INFO, "progress"), text);
+    }
+
+    fn profile(&self, turn: usize, summary: &str) {
+        span!(tracing::Level::INFO, "profile", turn).in_scope(|| info!("{}", summary));
+    }
+
+    // --json conflicts with --replay, so a replay is always shown on a terminal
+    fn replayed(&self, _turn: usize, _question: &str, _old: &str, _new: &str) {}
+
+    // stdout is kept for answers only, so there is no prompt to show or end
+    fn prompt(&self, _prompt: &str) {}
+
+    fn echo(&self, _line: &str) {}
+
+    fn end_line(&self) {}
+
+    // --json conflicts with --stream, so answers always arrive whole
+    fn chunk(&self, _text: &str) {}
+
+    fn end_stream(&self) {}
+
+    /// Writes `{"event":"session_end","turns":...,"reason":...}`, the last line of a session
+    fn session_end(&self, turns: usize, reason: &str) {
+        self.write_line(&json!({ "event": "session_end", "turns": turns, "reason": reason }));
+    }
+}
+
+/// Prepares text for the user, spelling units out in accessible mode.
+///
+/// Trailing whitespace is kept, so a prompt stays apart from the input and an echoed
+/// line still ends.
+fn display_text(text: &str, accessible: bool) -> String {
+    if accessible {
+        let end = text.trim_end().len();
+        accessibility::accessible_text(&text[..end]) + &text[end..]
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `show` against a terminal sink over a buffer and returns what it wrote.
+    fn terminal_output(accessible: bool, show: impl FnOnce(&Terminal<&mut Vec<u8>>)) -> String {
+        let mut buffer = Vec::new();
+        show(&Terminal::new(&mut buffer, accessible, false));
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn terminal_writes_answers_and_notices_to_its_writer() {
+        let written = terminal_output(false, |terminal| {
+            terminal.notice("Hello");
+            terminal.answer("It is 22°C in Paris", &[]);
+        });
+        assert_eq!(written, "Hello\nIt is 22°C in Paris\n");
+    }
+
+    #[test]
+    fn terminal_spells_units_out_in_accessible_mode() {
+        let plain = terminal_output(false, |terminal| terminal.answer("22°C", &[]));
+        let accessible = terminal_output(true, |terminal| terminal.answer("22°C", &[]));
+        assert_eq!(plain, "22°C\n");
+        assert_eq!(accessible, "22 degrees Celsius\n");
+    }
+
+    #[test]
+    fn terminal_writes_progress_prompt_and_replay() {
+        let written = terminal_output(false, |terminal| {
+            terminal.prompt("> ");
+            terminal.echo("weather in Oslo\n");
+            terminal.progress("Oslo: 3°C, cloudy");
+            terminal.replayed(1, "weather in Oslo", "3°C", "4°C");
+            terminal.replayed(2, "and Rome?", "18°C", "18°C");
+        });
+        assert_eq!(
+            written,
+            "> weather in Oslo\nOslo: 3°C, cloudy\n[1] weather in Oslo\nold: 3°C\nnew: 4°C\n\
+             [2] and Rome?\nunchanged: 18°C\n"
+        );
+    }
+
+    #[test]
+    fn json_lines_writes_one_object_per_answer() {
+        let mut buffer = Vec::new();
+        {
+            let json = JsonLines::new(&mut buffer, false, false);
+            json.prompt("> ");
+            json.answer("22°C", &[json!({ "name": "get_weather" })]);
+        }
+        let line: Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(
+            line,
+            json!({ "role": "assistant", "text": "22°C", "tool_calls": [{ "name": "get_weather" }] })
+        );
+    }
+
+    #[test]
+    fn json_lines_spells_units_out_in_accessible_mode() {
+        let mut buffer = Vec::new();
+        JsonLines::new(&mut buffer, true, false).answer("☀ 22°C", &[]);
+        let line: Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(line["text"], "clear sky 22 degrees Celsius");
+    }
+
+    #[test]
+    fn terminal_spells_units_out_in_prompts_and_echoes() {
+        let output = terminal_output(true, |terminal| {
+            terminal.prompt("[22°C] > ");
+            terminal.echo("70%\n");
+        });
+        assert_eq!(output, "[22 degrees Celsius] > 70 percent\n");
+    }
+
+    #[test]
+    fn json_lines_ends_with_the_session_summary() {
+        let mut buffer = Vec::new();
+        {
+            let json = JsonLines::new(&mut buffer, false, false);
+            json.answer("22°C", &[]);
+            json.session_end(1, "eof");
+        }
+        let lines: Vec<Value> = String::from_utf8(buffer)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            json!({ "event": "session_end", "turns": 1, "reason": "eof" })
+        );
+    }
+
+    #[test]
+    fn terminal_leaves_the_session_summary_to_the_logs() {
+        let written = terminal_output(false, |terminal| terminal.session_end(3, "exit"));
+        assert_eq!(written, "");
+    }
+}
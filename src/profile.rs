@@ -0,0 +1,74 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Time spent in each phase of the current turn, for `--profile`
+#[derive(Default)]
+pub struct Profile {
+    phases: Mutex<Phases>,
+}
+
+#[derive(Default)]
+struct Phases {
+    model: Duration,
+    model_calls: u32,
+    tools: Duration,
+    by_tool: Vec<(String, Duration, u32)>,
+}
+
+impl Profile {
+    /// Adds the duration of one model call
+    pub fn record_model(&self, elapsed: Duration) {
+        let mut phases = self.phases.lock().unwrap();
+        phases.model += elapsed;
+        phases.model_calls += 1;
+    }
+
+    /// Adds the wall-clock time of one batch of tool calls run together
+    pub fn record_tools(&self, elapsed: Duration) {
+        self.phases.lock().unwrap().tools += elapsed;
+    }
+
+    /// Adds the duration of one tool call
+    pub fn record_tool(&self, tool: &str, elapsed: Duration) {
+        let mut phases = self.phases.lock().unwrap();
+        match phases.by_tool.iter_mut().find(|(name, _, _)| name == tool) {
+            Some((_, total, count)) => {
+                *total += elapsed;
+                *count += 1;
+            }
+            None => phases.by_tool.push((tool.to_string(), elapsed, 1)),
+        }
+    }
+
+    /// Summarizes the turn that took `total` and starts a new one.
+    ///
+    /// Per-tool times are summed, so they can add up to more than the tools phase when
+    /// calls ran concurrently. Overhead is whatever the model and tools phases don't cover.
+    pub fn take_summary(&self, total: Duration) -> String {
+        let phases = std::mem::take(&mut *self.phases.lock().unwrap());
+        let overhead = total.saturating_sub(phases.model + phases.tools);
+
+        let by_tool: Vec<String> = phases
+            .by_tool
+            .iter()
+            .map(|(name, elapsed, count)| {
+                format!("{} {:.2}s x{}", name, elapsed.as_secs_f64(), count)
+            })
+            .collect();
+        let by_tool = if by_tool.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", by_tool.join(", "))
+        };
+
+        format!(
+            "Turn {:.2}s: model {:.2}s ({} calls), tools {:.2}s{}, overhead {:.2}s",
+            total.as_secs_f64(),
+            phases.model.as_secs_f64(),
+            phases.model_calls,
+            phases.tools.as_secs_f64(),
+            by_tool,
+            overhead.as_secs_f64()
+        )
+    }
+}
@@ -0,0 +1,80 @@
+use std::hash::{BuildHasher, RandomState};
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use tracing::warn;
+
+use crate::error::AppError;
+use crate::http::HttpResponse;
+
+/// How often and how patiently transient API failures are retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one; 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub base_delay: Duration,
+}
+
+/// Runs `operation` until it succeeds, fails permanently or runs out of attempts.
+///
+/// Between attempts it waits with exponential backoff and jitter: the n-th retry waits a
+/// random time between half and all of `base_delay * 2^(n-1)`, so parallel tool calls
+/// hitting the same rate limit don't retry in lockstep.
+///
+/// # Arguments
+/// * `policy` - Attempt count and base delay
+/// * `what` - Name of the operation, used in logs
+/// * `operation` - Produces one attempt
+/// * `is_transient` - Whether an attempt's outcome is worth retrying
+pub async fn with_backoff<T, F, Fut>(
+    policy: &RetryPolicy,
+    what: &str,
+    mut operation: F,
+    is_transient: impl Fn(&Result<T, AppError>) -> bool,
+) -> Result<T, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let mut attempt = 1;
+    loop {
+        let result = operation().await;
+        if attempt >= policy.max_attempts || !is_transient(&result) {
+            return result;
+        }
+
+        let delay = backoff_delay(policy.base_delay, attempt);
+        warn!(
+            "Transient failure fetching {} data (attempt {} of {}), retrying in {:?}",
+            what, attempt, policy.max_attempts, delay
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Whether an HTTP outcome is a rate limit, gateway error or failed connection.
+///
+/// Other failures, like a rejected API key or an unknown location, fail fast.
+pub fn is_transient_response(result: &Result<HttpResponse, AppError>) -> bool {
+    match result {
+        Ok(response) => matches!(
+            response.status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        ),
+        Err(AppError::RequestError(e)) => e.is_connect(),
+        Err(_) => false,
+    }
+}
+
+/// Delay before the retry following `attempt`, with jitter.
+fn backoff_delay(base_delay: Duration, attempt: u32) -> Duration {
+    let ceiling = base_delay.saturating_mul(2u32.saturating_pow(attempt - 1));
+    // A randomly seeded hash is enough randomness for spreading retries out
+    let jitter = RandomState::new().hash_one(attempt) as f64 / u64::MAX as f64;
+    ceiling.mul_f64(0.5 + jitter / 2.0)
+}
@@ -0,0 +1,111 @@
+use serde_json::Value;
+
+/// Number of decimal places used when `OUTPUT_PRECISION` is not set
+pub const DEFAULT_PRECISION: u32 = 1;
+
+/// Upper bound on decimal places; f64 cannot represent more meaningfully
+pub const MAX_PRECISION: u32 = 15;
+
+// Coordinates, which lose the place they point at when rounded
+const COORDINATE_KEYS: [&str; 4] = ["lat", "lon", "latitude", "longitude"];
+
+/// Rounds a number to the given number of decimal places.
+pub fn round_to(value: f64, precision: u32) -> f64 {
+    let factor = 10f64.powi(precision as i32);
+    (value * factor).round() / factor
+}
+
+/// Recursively rounds every floating-point measurement in a JSON value.
+///
+/// Integers are left untouched so counts and percentages keep their integer form, and
+/// coordinates and UTC offsets (any key containing "offset", like `utc_offset_hours`) are
+/// kept exact: -0.25 hours is not -0.3.
+pub fn round_json(value: Value, precision: u32) -> Value {
+    match value {
+        Value::Number(number) if number.is_f64() => number
+            .as_f64()
+            .and_then(|n| serde_json::Number::from_f64(round_to(n, precision)))
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| round_json(item, precision))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, item)| {
+                    if is_exact(&key) {
+                        (key, item)
+                    } else {
+                        (key, round_json(item, precision))
+                    }
+                })
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Whether the value under `key` is kept as reported rather than rounded
+fn is_exact(key: &str) -> bool {
+    COORDINATE_KEYS.contains(&key) || key.contains("offset")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::units::fahrenheit;
+
+    #[test]
+    fn converted_temperatures_are_rounded_to_the_precision() {
+        // 21.7°C is 71.06°F, with floating-point noise past the second decimal
+        let weather = json!({ "temp_c": 21.7, "temp_f": fahrenheit(21.7), "humidity": 40 });
+
+        assert_eq!(
+            round_json(weather.clone(), DEFAULT_PRECISION),
+            json!({ "temp_c": 21.7, "temp_f": 71.1, "humidity": 40 })
+        );
+        assert_eq!(
+            round_json(weather.clone(), 0),
+            json!({ "temp_c": 22.0, "temp_f": 71.0, "humidity": 40 })
+        );
+        assert_eq!(
+            round_json(weather, 2),
+            json!({ "temp_c": 21.7, "temp_f": 71.06, "humidity": 40 })
+        );
+    }
+
+    #[test]
+    fn nested_numbers_are_rounded_and_integers_kept() {
+        let forecast = json!({
+            "days": [{ "max_c": fahrenheit(-40.0), "chance_of_rain": 85 }],
+            "city": "Oslo",
+        });
+        assert_eq!(
+            round_json(forecast, DEFAULT_PRECISION),
+            json!({ "days": [{ "max_c": -40.0, "chance_of_rain": 85 }], "city": "Oslo" })
+        );
+    }
+
+    #[test]
+    fn offsets_and_coordinates_are_kept_exact() {
+        let time = json!({
+            "utc_offset_hours": -0.25,
+            "offset_hours": 5.75,
+            "location": { "lat": 59.9139, "lon": 10.7522, "temp_c": 18.44 },
+        });
+        assert_eq!(
+            round_json(time, DEFAULT_PRECISION),
+            json!({
+                "utc_offset_hours": -0.25,
+                "offset_hours": 5.75,
+                "location": { "lat": 59.9139, "lon": 10.7522, "temp_c": 18.4 },
+            })
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use serde_json::{Map, Value};
+
+use crate::error::AppError;
+
+/// Whether every letter in `text` is from the Latin script.
+///
+/// Accented Latin letters ("São Paulo", "Zürich") count as Latin; spaces, digits and
+/// punctuation such as hyphens and apostrophes are ignored.
+pub fn is_latin(text: &str) -> bool {
+    text.chars().filter(|c| c.is_alphabetic()).all(|c| {
+        matches!(c,
+            'A'..='Z' | 'a'..='z'
+            // Latin-1 Supplement letters, Latin Extended-A and -B
+            | '\u{00C0}'..='\u{024F}'
+            // Latin Extended Additional (Vietnamese and other precomposed letters)
+            | '\u{1E00}'..='\u{1EFF}'
+        )
+    })
+}
+
+/// Rejects tool call arguments with a city name that is not in Latin script.
+///
+/// The tools expect English city names, so a name like "Москва" gets an error asking the
+/// model to retry with the English name instead of being looked up as is. Checks the
+/// `city` argument and the `city` of every entry in `locations`.
+pub fn check_city_names(args: &Map<String, Value>) -> Result<(), AppError> {
+    let nested = args
+        .get("locations")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|location| location.get("city"));
+
+    for city in args.get("city").into_iter().chain(nested) {
+        if let Some(city) = city.as_str()
+            && !is_latin(city)
+        {
+            return Err(AppError::InvalidParameter(format!(
+                "city '{}' must be the English name in Latin script (e.g. \"Moscow\" rather than \"Москва\")",
+                city
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn latin_names_are_accepted_with_accents_and_punctuation() {
+        for city in [
+            "Madrid",
+            "São Paulo",
+            "Zürich",
+            "Hồ Chí Minh",
+            "Saint-Étienne",
+            "L'Aquila",
+        ] {
+            assert!(is_latin(city), "{}", city);
+        }
+        assert!(is_latin(""));
+    }
+
+    #[test]
+    fn other_scripts_are_rejected() {
+        for city in ["Москва", "東京", "القاهرة", "Αθήνα", "Kyiv Київ"] {
+            assert!(!is_latin(city), "{}", city);
+        }
+    }
+
+    #[test]
+    fn city_names_are_checked_in_locations_too() {
+        let args = json!({ "city": "Moscow", "locations": [{ "city": "Madrid" }] });
+        assert!(check_city_names(args.as_object().unwrap()).is_ok());
+
+        let args = json!({ "locations": [{ "city": "Madrid" }, { "city": "Москва" }] });
+        assert!(matches!(
+            check_city_names(args.as_object().unwrap()),
+            Err(AppError::InvalidParameter(_))
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use genai::chat::ChatMessage;
+use tracing::{debug, info};
+
+use crate::error::AppError;
+use crate::transcript;
+
+/// Named conversations saved as `<name>.json` transcripts in one directory
+pub struct Sessions {
+    dir: PathBuf,
+    active: Option<String>,
+}
+
+impl Sessions {
+    /// Creates a session manager, optionally with an active session
+    pub fn new(dir: &Path, active: Option<&str>) -> Result<Self, AppError> {
+        if let Some(name) = active {
+            validate_name(name)?;
+        }
+        Ok(Sessions {
+            dir: dir.to_path_buf(),
+            active: active.map(str::to_string),
+        })
+    }
+
+    /// Name of the active session, if any
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// Loads the messages of the active session, or none for a new session
+    pub fn load(&self) -> Result<Vec<ChatMessage>, AppError> {
+        let Some(name) = &self.active else {
+            return Ok(Vec::new());
+        };
+        let path = self.path(name);
+        if !path.exists() {
+            info!("Starting new session '{}'", name);
+            return Ok(Vec::new());
+        }
+        let messages = transcript::read_messages(&path)?;
+        info!("Loaded session '{}' ({} messages)", name, messages.len());
+        Ok(messages)
+    }
+
+    /// Saves messages to the active session; does nothing without one
+    pub fn save(&self, messages: &[ChatMessage]) -> Result<(), AppError> {
+        let Some(name) = &self.active else {
+            return Ok(());
+        };
+        fs::create_dir_all(&self.dir)?;
+        transcript::write_messages(&self.path(name), messages)?;
+        debug!("Saved session '{}'", name);
+        Ok(())
+    }
+
+    /// Names of the saved sessions and the active one, sorted
+    pub fn list(&self) -> Result<Vec<String>, AppError> {
+        let mut names: Vec<String> = Vec::new();
+        if self.dir.exists() {
+            names = fs::read_dir(&self.dir)?
+                .filter_map(|entry| {
+                    let path = entry.ok()?.path();
+                    if path.extension()? != "json" {
+                        return None;
+                    }
+                    Some(path.file_stem()?.to_str()?.to_string())
+                })
+                .collect();
+        }
+        // The active session may not have been saved yet
+        if let Some(active) = &self.active
+            && !names.contains(active)
+        {
+            names.push(active.clone());
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Saves the current conversation and makes `name` the active session.
+    ///
+    /// # Returns
+    /// * The messages of the new session, empty if it didn't exist yet
+    pub fn switch(
+        &mut self,
+        name: &str,
+        messages: &[ChatMessage],
+    ) -> Result<Vec<ChatMessage>, AppError> {
+        validate_name(name)?;
+        self.save(messages)?;
+        self.active = Some(name.to_string());
+        self.load()
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+/// Accepts names made of letters, digits, `-` and `_`, so they map to plain file names.
+fn validate_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::InvalidParameter(format!(
+            "invalid session name '{}', use letters, digits, '-' and '_'",
+            name
+        )))
+    }
+}
@@ -0,0 +1,47 @@
+use metrics_exporter_statsd::StatsdBuilder;
+use tracing::info;
+
+use crate::error::AppError;
+
+// Prefix added to every metric name
+const METRICS_PREFIX: &str = "cloud_gemini";
+
+/// Number of model calls made
+pub const MODEL_REQUESTS: &str = "model_requests";
+/// Number of model calls that failed
+pub const MODEL_ERRORS: &str = "model_errors";
+/// Model call latency in seconds
+pub const MODEL_LATENCY: &str = "model_latency_seconds";
+/// Number of tool calls made, labeled by tool
+pub const TOOL_REQUESTS: &str = "tool_requests";
+/// Number of tool calls that failed, labeled by tool
+pub const TOOL_ERRORS: &str = "tool_errors";
+/// Tool call latency in seconds, labeled by tool
+pub const TOOL_LATENCY: &str = "tool_latency_seconds";
+
+/// Installs a StatsD exporter as the global metrics recorder.
+///
+/// Metrics are recorded through the `metrics` facade and are no-ops until an exporter
+/// is installed; only one exporter can be active per process.
+///
+/// # Arguments
+/// * `addr` - StatsD server address in `host:port` form
+pub fn install_statsd(addr: &str) -> Result<(), AppError> {
+    let invalid = |reason: String| AppError::InvalidConfig(format!("--statsd-addr: {}", reason));
+
+    let (host, port) = addr
+        .rsplit_once(':')
+        .ok_or_else(|| invalid(format!("expected host:port, got '{}'", addr)))?;
+    let port = port
+        .parse()
+        .map_err(|_| invalid(format!("invalid port '{}'", port)))?;
+
+    let recorder = StatsdBuilder::from(host, port)
+        .build(Some(METRICS_PREFIX))
+        .map_err(|e| invalid(e.to_string()))?;
+    metrics::set_global_recorder(recorder)
+        .map_err(|_| invalid("a metrics exporter is already installed".to_string()))?;
+
+    info!("Sending metrics to StatsD at {}", addr);
+    Ok(())
+}
@@ -0,0 +1,13 @@
+// Largest difference at which two temperatures count as equal
+const TEMP_EPSILON: f64 = 1e-9;
+
+/// Asserts that two temperatures are equal up to floating-point noise from unit conversions.
+#[track_caller]
+pub fn assert_temp_eq(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < TEMP_EPSILON,
+        "temperatures differ: {} != {}",
+        actual,
+        expected
+    );
+}
@@ -0,0 +1,1824 @@
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use chrono::{Days, Local, NaiveDate, TimeDelta, TimeZone};
+use futures::future;
+use genai::chat::Tool;
+use serde_json::{Map, Value, json};
+use tracing::info;
+
+use crate::activity::{self, Activity};
+use crate::cache::CacheKey;
+use crate::cli::MissingValue;
+use crate::comparison::{self, CityWeather};
+use crate::config::Config;
+use crate::context::Context;
+use crate::error::AppError;
+use crate::units::UnitSystem;
+use crate::weather::openweathermap::OpenWeatherMap;
+use crate::weather::response::{Astro, ForecastDay};
+use crate::weather::{
+    self, NormalizedWeather, Provider, WeatherApi, WeatherProvider, WeatherReport,
+};
+use crate::{dates, geo_location};
+
+/// Maximum number of locations in a single weather comparison
+pub const MAX_COMPARE_LOCATIONS: usize = 5;
+
+// Chance of rain or snow, in percent, from which it is reported as likely
+const LIKELY_CHANCE_PERCENT: i32 = 50;
+
+/// Tools the model can call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToolKind {
+    /// `get_weather`: current weather conditions for a location
+    Weather,
+    /// `get_current_time`: current time for a location
+    CurrentTime,
+    /// `get_business_hours_status`: business hours and weekends at a location
+    BusinessHours,
+    /// `get_forecast`: the daily forecast for a location
+    Forecast,
+    /// `best_day`: the most suitable forecast day for an activity
+    BestDay,
+    /// `chance_of`: the chance of rain or snow on a day
+    ChanceOf,
+    /// `compare_weather`: current weather across locations in both units
+    CompareWeather,
+    /// `get_air_quality`: the current air quality for a location
+    AirQuality,
+    /// `get_astronomy`: sunrise, sunset, moon times and the moon phase for a location
+    Astronomy,
+    /// `get_weather_alerts`: active severe weather alerts for a location
+    WeatherAlerts,
+    /// `get_moon_phase`: just the moon phase and illumination for a location
+    MoonPhase,
+}
+
+/// Validated arguments of a tool call, one variant per tool
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ToolArgs {
+    /// Arguments of `get_weather`
+    Weather(WeatherArgs),
+    /// Arguments of `get_current_time`
+    CurrentTime(TimeArgs),
+    /// Arguments of `get_business_hours_status`
+    BusinessHours(LocationArgs),
+    /// Arguments of `get_forecast`
+    Forecast(ForecastArgs),
+    /// Arguments of `best_day`
+    BestDay(BestDayArgs),
+    /// Arguments of `chance_of`
+    ChanceOf(ChanceArgs),
+    /// Arguments of `compare_weather`
+    CompareWeather(CompareArgs),
+    /// Arguments of `get_air_quality`
+    AirQuality(LocationArgs),
+    /// Arguments of `get_astronomy`
+    Astronomy(AstronomyArgs),
+    /// Arguments of `get_weather_alerts`
+    WeatherAlerts(LocationArgs),
+    /// Arguments of `get_moon_phase`
+    MoonPhase(AstronomyArgs),
+}
+
+/// A city and its ISO country code
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocationArgs {
+    /// City name in English
+    pub city: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+}
+
+/// Arguments of `get_weather`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WeatherArgs {
+    /// City name in English
+    pub city: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    /// "C" or "F", if the model asked for one
+    pub unit: Option<&'static str>,
+}
+
+/// Arguments of `get_current_time`
+#[derive(Debug, Clone)]
+pub struct TimeArgs {
+    /// City name in English
+    pub city: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    /// Hours to shift from now, negative for the past
+    pub offset_hours: Option<f64>,
+    /// Moment in the user's own time to convert, as understood by `dates::resolve_moment`
+    pub date: Option<String>,
+}
+
+// Offsets are compared bit for bit, as f64 has no total equality
+impl PartialEq for TimeArgs {
+    fn eq(&self, other: &Self) -> bool {
+        self.city == other.city
+            && self.country == other.country
+            && self.offset_hours.map(f64::to_bits) == other.offset_hours.map(f64::to_bits)
+            && self.date == other.date
+    }
+}
+
+impl Eq for TimeArgs {}
+
+impl Hash for TimeArgs {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.city.hash(state);
+        self.country.hash(state);
+        self.offset_hours.map(f64::to_bits).hash(state);
+        self.date.hash(state);
+    }
+}
+
+/// Arguments of `get_forecast`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForecastArgs {
+    /// City name in English
+    pub city: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    /// "C" or "F", if the model asked for one
+    pub unit: Option<&'static str>,
+    /// A single day to report, as understood by `dates::resolve_day`
+    pub day: Option<String>,
+    /// Number of days to report, before clamping to the forecast horizon
+    pub days: Option<u64>,
+}
+
+/// Arguments of `best_day`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BestDayArgs {
+    /// City name in English
+    pub city: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    /// Activity the day should suit
+    pub activity: Activity,
+    /// "C" or "F", if the model asked for one
+    pub unit: Option<&'static str>,
+    /// First day considered, if limited
+    pub start_date: Option<NaiveDate>,
+    /// Last day considered, if limited; never before `start_date`
+    pub end_date: Option<NaiveDate>,
+}
+
+/// Arguments of `chance_of`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChanceArgs {
+    /// City name in English
+    pub city: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    /// "rain" or "snow"
+    pub condition: String,
+    /// Day to check, "today" unless the model gave one
+    pub day: String,
+}
+
+/// Arguments of `get_astronomy` and `get_moon_phase`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstronomyArgs {
+    /// City name in English
+    pub city: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    /// Day to report, as understood by `dates::resolve_day`, if not today
+    pub date: Option<String>,
+}
+
+/// Arguments of `compare_weather`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompareArgs {
+    /// City and country pairs, at most `MAX_COMPARE_LOCATIONS`
+    pub places: Vec<(String, String)>,
+    /// Whether to add forecast temperature swings
+    pub include_trends: bool,
+}
+
+impl ToolKind {
+    /// Every tool, in the order they are offered to the model
+    pub const ALL: [ToolKind; 11] = [
+        ToolKind::Weather,
+        ToolKind::CurrentTime,
+        ToolKind::BusinessHours,
+        ToolKind::Forecast,
+        ToolKind::BestDay,
+        ToolKind::ChanceOf,
+        ToolKind::CompareWeather,
+        ToolKind::AirQuality,
+        ToolKind::Astronomy,
+        ToolKind::WeatherAlerts,
+        ToolKind::MoonPhase,
+    ];
+
+    /// Looks a tool up by the name the model calls it with.
+    pub fn from_name(name: &str) -> Result<ToolKind, AppError> {
+        match name {
+            "get_weather" => Ok(ToolKind::Weather),
+            "get_current_time" => Ok(ToolKind::CurrentTime),
+            "get_business_hours_status" => Ok(ToolKind::BusinessHours),
+            "get_forecast" => Ok(ToolKind::Forecast),
+            "best_day" => Ok(ToolKind::BestDay),
+            "chance_of" => Ok(ToolKind::ChanceOf),
+            "compare_weather" => Ok(ToolKind::CompareWeather),
+            "get_air_quality" => Ok(ToolKind::AirQuality),
+            "get_astronomy" => Ok(ToolKind::Astronomy),
+            "get_weather_alerts" => Ok(ToolKind::WeatherAlerts),
+            "get_moon_phase" => Ok(ToolKind::MoonPhase),
+            _ => Err(AppError::UnsupportedToolCall(name.to_string())),
+        }
+    }
+
+    /// Name the tool is declared to the model with
+    pub fn name(self) -> &'static str {
+        match self {
+            ToolKind::Weather => "get_weather",
+            ToolKind::CurrentTime => "get_current_time",
+            ToolKind::BusinessHours => "get_business_hours_status",
+            ToolKind::Forecast => "get_forecast",
+            ToolKind::BestDay => "best_day",
+            ToolKind::ChanceOf => "chance_of",
+            ToolKind::CompareWeather => "compare_weather",
+            ToolKind::AirQuality => "get_air_quality",
+            ToolKind::Astronomy => "get_astronomy",
+            ToolKind::WeatherAlerts => "get_weather_alerts",
+            ToolKind::MoonPhase => "get_moon_phase",
+        }
+    }
+
+    /// Environment variable holding the API key the tool can't work without; current
+    /// weather comes from `provider`, everything else weather-related from WeatherAPI
+    pub fn key_var(self, provider: Provider) -> &'static str {
+        match self {
+            ToolKind::Weather | ToolKind::CompareWeather => provider.key_var(),
+            ToolKind::CurrentTime | ToolKind::BusinessHours => "IP_GEOLOCATION_API_KEY",
+            ToolKind::Forecast
+            | ToolKind::BestDay
+            | ToolKind::ChanceOf
+            | ToolKind::AirQuality
+            | ToolKind::Astronomy
+            | ToolKind::WeatherAlerts
+            | ToolKind::MoonPhase => "WEATHER_API_KEY",
+        }
+    }
+
+    /// How long the tool's whole result is reused for identical arguments, if it is cacheable.
+    ///
+    /// Only tools built on uncached forecast requests are listed; the current weather and time
+    /// tools already cache their lookups by location.
+    pub fn result_cache_ttl(self, config: &Config) -> Option<Duration> {
+        match self {
+            ToolKind::Forecast | ToolKind::BestDay | ToolKind::ChanceOf => {
+                Some(config.forecast_cache_ttl)
+            }
+            _ => None,
+        }
+    }
+
+    /// Declaration offered to the model: the tool's name, what it does and the schema of
+    /// its arguments
+    pub fn declaration(self, config: &Config) -> Tool {
+        let (description, schema) = match self {
+            ToolKind::Weather => (
+                "Get the current weather for a location, including wind, air pressure and the UV index",
+                location_schema(json!({ "unit": unit_property() }), &[]),
+            ),
+            ToolKind::CurrentTime => (
+                "Get the current time, IANA timezone and UTC offset for a location, the time a given number of hours ahead or ago, or the local time there at a moment in the user's own time",
+                location_schema(
+                    json!({
+                        "offset_hours": {
+                            "type": "number",
+                            "description": "Hours to shift from now: positive for a time ahead, negative for a time ago (e.g., -1 for an hour ago)."
+                        },
+                        "date": {
+                            "type": "string",
+                            "description": "A moment in the user's own time zone to convert to the location's time: a day (\"today\", \"tomorrow\", a weekday name or YYYY-MM-DD) optionally followed by a time (e.g., \"tomorrow 9am\", \"2025-06-01 15:30\"). Don't combine with offset_hours."
+                        }
+                    }),
+                    &[],
+                ),
+            ),
+            ToolKind::BusinessHours => (
+                "Check whether it is currently business hours and whether it is a weekend at a location",
+                location_schema(json!({}), &[]),
+            ),
+            ToolKind::Forecast => (
+                "Get the daily forecast (high, low, chance of rain) for a location",
+                location_schema(
+                    json!({
+                        "unit": unit_property(),
+                        "days": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "maximum": config.max_forecast_days,
+                            "description": "Number of days to forecast, starting today"
+                        },
+                        "day": {
+                            "type": "string",
+                            "description": "A single day to forecast: \"today\", \"tomorrow\", a weekday (\"saturday\", \"next saturday\") or YYYY-MM-DD"
+                        }
+                    }),
+                    &[],
+                ),
+            ),
+            ToolKind::BestDay => (
+                "Find the most suitable day in the forecast for an outdoor activity, with reasoning",
+                location_schema(
+                    json!({
+                        "activity": {
+                            "type": "string",
+                            "enum": Activity::NAMES,
+                            "description": "Planned outdoor activity"
+                        },
+                        "start_date": {
+                            "type": "string",
+                            "description": "First day to consider in format YYYY-MM-DD (defaults to today)"
+                        },
+                        "end_date": {
+                            "type": "string",
+                            "description": "Last day to consider in format YYYY-MM-DD (defaults to the end of the forecast)"
+                        },
+                        "unit": unit_property()
+                    }),
+                    &["activity"],
+                ),
+            ),
+            ToolKind::ChanceOf => (
+                "Get the forecast chance of rain or snow for a location on a day, with a yes/no answer",
+                location_schema(
+                    json!({
+                        "condition": {
+                            "type": "string",
+                            "enum": ["rain", "snow"],
+                            "description": "Precipitation to check for"
+                        },
+                        "day": {
+                            "type": "string",
+                            "description": "Day to check: \"today\", \"tomorrow\", a weekday (\"saturday\", \"next saturday\") or YYYY-MM-DD (defaults to today)"
+                        }
+                    }),
+                    &["condition"],
+                ),
+            ),
+            ToolKind::CompareWeather => (
+                "Compare the current weather across several locations, with temperatures in both C and F",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "locations": {
+                            "type": "array",
+                            "minItems": 2,
+                            "maxItems": MAX_COMPARE_LOCATIONS,
+                            "items": location_schema(json!({}), &[])
+                        },
+                        "include_trends": {
+                            "type": "boolean",
+                            "description": "Also fetch a short forecast per location and report which one has the largest temperature swing over the next few days (slower, defaults to false)"
+                        }
+                    },
+                    "required": ["locations"]
+                }),
+            ),
+            ToolKind::AirQuality => (
+                "Get the current air quality (PM2.5, PM10 and US EPA index with a label) for a location",
+                location_schema(json!({}), &[]),
+            ),
+            ToolKind::Astronomy => (
+                "Get sunrise, sunset, moonrise and moonset times and the moon phase for a location",
+                location_schema(json!({ "date": report_day_property() }), &[]),
+            ),
+            ToolKind::WeatherAlerts => (
+                "Get the active severe weather alerts (headline, severity, event and expiry) for a location",
+                location_schema(json!({}), &[]),
+            ),
+            ToolKind::MoonPhase => (
+                "Get just the moon phase and how much of the moon is lit for a location, e.g. to tell whether it is a full moon tonight",
+                location_schema(json!({ "date": report_day_property() }), &[]),
+            ),
+        };
+
+        Tool::new(self.name())
+            .with_description(description)
+            .with_schema(schema)
+    }
+
+    /// Extracts and validates this tool's arguments from a tool call.
+    ///
+    /// # Returns
+    /// * The typed arguments, with optional ones left as `None` for the caller to default
+    /// * `MissingParameter` for a required argument that is absent, `InvalidParameter` or
+    ///   `UnsupportedUnit` for one with an unusable value
+    pub fn parse_args(self, args: &Map<String, Value>) -> Result<ToolArgs, AppError> {
+        Ok(match self {
+            ToolKind::Weather => ToolArgs::Weather(WeatherArgs {
+                city: required_str(args, "city")?,
+                country: required_str(args, "country")?,
+                unit: unit_arg(args)?,
+            }),
+            ToolKind::CurrentTime => {
+                let offset_hours = match args.get("offset_hours") {
+                    None | Some(Value::Null) => None,
+                    Some(value) => Some(value.as_f64().ok_or_else(|| {
+                        AppError::InvalidParameter("offset_hours must be a number".to_string())
+                    })?),
+                };
+                let date = optional_str(args, "date");
+                if offset_hours.is_some() && date.is_some() {
+                    return Err(AppError::InvalidParameter(
+                        "give either offset_hours or date, not both".to_string(),
+                    ));
+                }
+
+                ToolArgs::CurrentTime(TimeArgs {
+                    city: required_str(args, "city")?,
+                    country: required_str(args, "country")?,
+                    offset_hours,
+                    date,
+                })
+            }
+            ToolKind::BusinessHours => ToolArgs::BusinessHours(LocationArgs::parse(args)?),
+            ToolKind::Forecast => ToolArgs::Forecast(ForecastArgs {
+                city: required_str(args, "city")?,
+                country: required_str(args, "country")?,
+                unit: unit_arg(args)?,
+                day: optional_str(args, "day"),
+                days: days_arg(args)?,
+            }),
+            ToolKind::BestDay => {
+                let city = required_str(args, "city")?;
+                let country = required_str(args, "country")?;
+                let activity = required_str(args, "activity")?.parse()?;
+                let unit = unit_arg(args)?;
+
+                // Optional date range limiting which forecast days are considered
+                let start_date = date_arg(args, "start_date")?;
+                let end_date = date_arg(args, "end_date")?;
+                if let (Some(start), Some(end)) = (start_date, end_date)
+                    && start > end
+                {
+                    return Err(AppError::InvalidParameter(
+                        "start_date must not be after end_date".to_string(),
+                    ));
+                }
+
+                ToolArgs::BestDay(BestDayArgs {
+                    city,
+                    country,
+                    activity,
+                    unit,
+                    start_date,
+                    end_date,
+                })
+            }
+            ToolKind::ChanceOf => {
+                let city = required_str(args, "city")?;
+                let country = required_str(args, "country")?;
+                let condition = required_str(args, "condition")?;
+                if !matches!(condition.as_str(), "rain" | "snow") {
+                    return Err(AppError::InvalidParameter(format!(
+                        "unsupported condition '{}', expected rain or snow",
+                        condition
+                    )));
+                }
+
+                ToolArgs::ChanceOf(ChanceArgs {
+                    city,
+                    country,
+                    condition,
+                    day: optional_str(args, "day").unwrap_or_else(|| "today".to_string()),
+                })
+            }
+            ToolKind::CompareWeather => {
+                let locations = args
+                    .get("locations")
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| AppError::MissingParameter("locations".to_string()))?;
+                if locations.len() > MAX_COMPARE_LOCATIONS {
+                    return Err(AppError::InvalidParameter(format!(
+                        "at most {} locations can be compared",
+                        MAX_COMPARE_LOCATIONS
+                    )));
+                }
+
+                let mut places = Vec::with_capacity(locations.len());
+                for location in locations {
+                    let field = |name: &str| {
+                        location
+                            .get(name)
+                            .and_then(|v| v.as_str())
+                            .map(str::to_string)
+                            .ok_or_else(|| {
+                                AppError::MissingParameter(format!("locations.{}", name))
+                            })
+                    };
+                    places.push((field("city")?, field("country")?));
+                }
+                let include_trends = match args.get("include_trends") {
+                    None | Some(Value::Null) => false,
+                    Some(value) => value.as_bool().ok_or_else(|| {
+                        AppError::InvalidParameter("include_trends must be a boolean".to_string())
+                    })?,
+                };
+
+                ToolArgs::CompareWeather(CompareArgs {
+                    places,
+                    include_trends,
+                })
+            }
+            ToolKind::AirQuality => ToolArgs::AirQuality(LocationArgs::parse(args)?),
+            ToolKind::Astronomy => ToolArgs::Astronomy(AstronomyArgs::parse(args)?),
+            ToolKind::WeatherAlerts => ToolArgs::WeatherAlerts(LocationArgs::parse(args)?),
+            ToolKind::MoonPhase => ToolArgs::MoonPhase(AstronomyArgs::parse(args)?),
+        })
+    }
+}
+
+impl ToolArgs {
+    /// Runs the tool the arguments belong to, returning the JSON result for the model
+    pub async fn execute(self, ctx: &Context) -> Result<Value, AppError> {
+        match self {
+            ToolArgs::Weather(args) => get_weather(ctx, args).await,
+            ToolArgs::CurrentTime(args) => get_current_time(ctx, args).await,
+            ToolArgs::BusinessHours(args) => get_business_hours_status(ctx, args).await,
+            ToolArgs::Forecast(args) => get_forecast(ctx, args).await,
+            ToolArgs::BestDay(args) => best_day(ctx, args).await,
+            ToolArgs::ChanceOf(args) => chance_of(ctx, args).await,
+            ToolArgs::CompareWeather(args) => compare_weather(ctx, args).await,
+            ToolArgs::AirQuality(args) => get_air_quality(ctx, args).await,
+            ToolArgs::Astronomy(args) => get_astronomy(ctx, args).await,
+            ToolArgs::WeatherAlerts(args) => get_weather_alerts(ctx, args).await,
+            ToolArgs::MoonPhase(args) => get_moon_phase(ctx, args).await,
+        }
+    }
+
+    /// The arguments as a result cache key: names lowercased and days like "tomorrow"
+    /// resolved against `today`, so calls that must give the same result compare equal.
+    ///
+    /// A relative day keeps its words next to the date, as the tools count it from the
+    /// location's date, which can differ from `today`. A day that doesn't resolve is kept
+    /// as given; the call fails before anything is cached.
+    pub fn normalized(&self, today: NaiveDate) -> ToolArgs {
+        let resolved = |day: &mut String| {
+            lowercase(day);
+            if let Ok(date) = dates::resolve_day(day, today) {
+                let date = date.format("%Y-%m-%d").to_string();
+                if date != day.trim() {
+                    *day = format!("{} ({})", date, day.trim());
+                }
+            }
+        };
+
+        let mut args = self.clone();
+        match &mut args {
+            ToolArgs::Weather(WeatherArgs { city, country, .. })
+            | ToolArgs::BusinessHours(LocationArgs { city, country })
+            | ToolArgs::AirQuality(LocationArgs { city, country })
+            | ToolArgs::WeatherAlerts(LocationArgs { city, country })
+            | ToolArgs::BestDay(BestDayArgs { city, country, .. }) => {
+                lowercase(city);
+                lowercase(country);
+            }
+            ToolArgs::CurrentTime(TimeArgs {
+                city,
+                country,
+                date,
+                ..
+            }) => {
+                lowercase(city);
+                lowercase(country);
+                // A moment keeps its time of day, which a day alone can't hold
+                date.iter_mut().for_each(lowercase);
+            }
+            ToolArgs::Forecast(ForecastArgs {
+                city, country, day, ..
+            })
+            | ToolArgs::Astronomy(AstronomyArgs {
+                city,
+                country,
+                date: day,
+            })
+            | ToolArgs::MoonPhase(AstronomyArgs {
+                city,
+                country,
+                date: day,
+            }) => {
+                lowercase(city);
+                lowercase(country);
+                day.iter_mut().for_each(resolved);
+            }
+            ToolArgs::ChanceOf(ChanceArgs {
+                city, country, day, ..
+            }) => {
+                lowercase(city);
+                lowercase(country);
+                resolved(day);
+            }
+            ToolArgs::CompareWeather(CompareArgs { places, .. }) => {
+                for (city, country) in places {
+                    lowercase(city);
+                    lowercase(country);
+                }
+            }
+        }
+        args
+    }
+}
+
+impl AstronomyArgs {
+    /// Extracts the required `city` and `country` and the optional `date` arguments
+    fn parse(args: &Map<String, Value>) -> Result<AstronomyArgs, AppError> {
+        Ok(AstronomyArgs {
+            city: required_str(args, "city")?,
+            country: required_str(args, "country")?,
+            date: optional_str(args, "date"),
+        })
+    }
+}
+
+impl LocationArgs {
+    /// Extracts the required `city` and `country` arguments
+    fn parse(args: &Map<String, Value>) -> Result<LocationArgs, AppError> {
+        Ok(LocationArgs {
+            city: required_str(args, "city")?,
+            country: required_str(args, "country")?,
+        })
+    }
+}
+
+/// `get_weather`: the current conditions in the requested unit, cached per location
+async fn get_weather(ctx: &Context, args: WeatherArgs) -> Result<Value, AppError> {
+    let WeatherArgs {
+        city,
+        country,
+        unit,
+    } = args;
+    let unit = unit.unwrap_or_else(|| ctx.default_unit(&country));
+
+    let location = format!("{},{}", city, country);
+
+    // Serve repeated lookups from the cache, otherwise call the weather API
+    let key = CacheKey::new(ToolKind::Weather.name(), &location, Some(unit));
+    ctx.cache
+        .get_or_fetch(
+            key,
+            ctx.config.weather_cache_ttl,
+            ctx.config.negative_cache_ttl,
+            || async {
+                let current = current_weather(ctx, &location).await?;
+
+                // Convert temperatures to requested unit, with wind and pressure
+                // in the matching metric or imperial units
+                let (temperature, feels_like) = match unit {
+                    "F" => (current.temp_f, current.feelslike_f),
+                    _ => (current.temp_c, current.feelslike_c),
+                };
+                let (wind_speed, wind_unit, pressure, pressure_unit) = match unit {
+                    "F" => (current.wind_mph, "mph", current.pressure_in, "inHg"),
+                    _ => (current.wind_kph, "kph", current.pressure_mb, "mb"),
+                };
+
+                // Format the response with relevant weather information
+                let report = WeatherReport {
+                    location: weather::optional_field(
+                        current.location.as_deref(),
+                        ctx.config.missing_value,
+                    ),
+                    local_time: weather::optional_field(
+                        current.local_time.as_deref(),
+                        ctx.config.missing_value,
+                    ),
+                    observed_at: weather::optional_field(
+                        current.observed_at.as_deref(),
+                        ctx.config.missing_value,
+                    ),
+                    temperature,
+                    feels_like: weather::optional_field(feels_like, ctx.config.missing_value),
+                    condition: current.condition.clone(),
+                    humidity: weather::optional_field(current.humidity, ctx.config.missing_value),
+                    wind_speed: weather::optional_field(wind_speed, ctx.config.missing_value),
+                    wind_unit,
+                    wind_direction: weather::optional_field(
+                        current.wind_dir.as_deref(),
+                        ctx.config.missing_value,
+                    ),
+                    pressure: weather::optional_field(pressure, ctx.config.missing_value),
+                    pressure_unit,
+                    uv: weather::optional_field(current.uv, ctx.config.missing_value),
+                    uv_risk: weather::optional_field(current.uv_risk(), ctx.config.missing_value),
+                    source: current.source.to_string(),
+                };
+                Ok(serde_json::to_value(report)?)
+            },
+        )
+        .await
+}
+
+/// `get_current_time`: the time at a location now, some hours from now, or at a moment in
+/// the user's own time
+async fn get_current_time(ctx: &Context, args: TimeArgs) -> Result<Value, AppError> {
+    let TimeArgs {
+        city,
+        country,
+        offset_hours,
+        date,
+    } = args;
+    match (offset_hours, date) {
+        // Parsing already rejects the pair; neither is silently dropped here
+        (Some(_), Some(_)) => Err(AppError::InvalidParameter(
+            "give either offset_hours or date, not both".to_string(),
+        )),
+        (None, Some(moment)) => time_at_moment(ctx, &city, &country, &moment).await,
+        (offset_hours, None) => shifted_time(ctx, &city, &country, offset_hours).await,
+    }
+}
+
+/// The local time at a location at `moment` in the user's own time zone
+async fn time_at_moment(
+    ctx: &Context,
+    city: &str,
+    country: &str,
+    moment: &str,
+) -> Result<Value, AppError> {
+    let current = local_time(ctx, city, country).await?;
+
+    // The moment is in the user's time zone; the location's offset moves it there
+    let requested = dates::resolve_moment(moment, Local::now().naive_local())?;
+    let utc = Local
+        .from_local_datetime(&requested)
+        .earliest()
+        .ok_or_else(|| {
+            AppError::InvalidParameter(format!("{} does not exist in the local time zone", moment))
+        })?
+        .naive_utc();
+    let offset_hours = current["utc_offset_hours"]
+        .as_f64()
+        .ok_or_else(|| AppError::ResponseParseError("time zone offset not reported".to_string()))?;
+    let there = utc + TimeDelta::minutes((offset_hours * 60.0).round() as i64);
+
+    Ok(json!({
+        "time": there.format("%Y-%m-%d %I:%M:%S %p").to_string(),
+        "weekday": there.format("%A").to_string(),
+        "your_time": requested.format("%Y-%m-%d %I:%M:%S %p").to_string(),
+        "timezone": current["timezone"],
+        "utc_offset": current["utc_offset"],
+        "note": "Uses the current UTC offset, so a daylight saving change before then is not accounted for",
+    }))
+}
+
+/// The local time at a location now, or shifted by `offset_hours` from now
+async fn shifted_time(
+    ctx: &Context,
+    city: &str,
+    country: &str,
+    offset_hours: Option<f64>,
+) -> Result<Value, AppError> {
+    let current = local_time(ctx, city, country).await?;
+
+    let date = current["date"].as_str().unwrap_or_default();
+    let time_12 = current["time_12"].as_str().unwrap_or_default();
+    match offset_hours {
+        // Shift the local time, rolling the date over midnight in either direction
+        Some(hours) if hours != 0.0 => Ok(json!({
+            "time": dates::shift_local_time(date, time_12, hours)?,
+            "offset_hours": hours,
+            "current_time": current["time"],
+            "timezone": current["timezone"],
+            "utc_offset": current["utc_offset"],
+        })),
+        _ => Ok(json!({
+            "time": current["time"],
+            "timezone": current["timezone"],
+            "utc_offset": current["utc_offset"],
+            "is_dst": current["is_dst"],
+        })),
+    }
+}
+
+/// `get_business_hours_status`: whether it is business hours or a weekend, from the local time
+async fn get_business_hours_status(ctx: &Context, args: LocationArgs) -> Result<Value, AppError> {
+    let LocationArgs { city, country } = args;
+    let current = local_time(ctx, &city, &country).await?;
+    let local = dates::parse_local_time(
+        current["date"].as_str().unwrap_or_default(),
+        current["time_12"].as_str().unwrap_or_default(),
+    )?;
+
+    let hours = ctx.config.business_hours;
+    let is_weekend = dates::is_weekend(local.date());
+    let within_hours = hours.contains(local.time());
+    Ok(json!({
+        "time": current["time"],
+        "weekday": local.format("%A").to_string(),
+        "business_hours": hours.to_string(),
+        "within_business_hours": within_hours,
+        "is_weekend": is_weekend,
+        "open": within_hours && !is_weekend,
+    }))
+}
+
+/// `get_forecast`: the daily forecast for a number of days or a single day
+async fn get_forecast(ctx: &Context, args: ForecastArgs) -> Result<Value, AppError> {
+    let ForecastArgs {
+        city,
+        country,
+        unit,
+        day,
+        days: requested_days,
+    } = args;
+    let unit = unit.unwrap_or_else(|| ctx.default_unit(&country));
+
+    // A single requested day is looked up across the whole forecast horizon
+    let day = day.as_deref();
+    let max_days = ctx.config.max_forecast_days;
+    let days = match day {
+        Some(_) => max_days,
+        None => requested_days
+            .unwrap_or(u64::from(max_days))
+            .clamp(1, u64::from(max_days)) as u32,
+    };
+
+    // Ranges beyond the plan's forecast horizon are shortened rather than rejected
+    let clamped_from = requested_days.filter(|&n| day.is_none() && n > u64::from(max_days));
+    if let Some(requested) = clamped_from {
+        info!("Clamped forecast days from {} to {}", requested, max_days);
+    }
+
+    let location = format!("{},{}", city, country);
+
+    // Call the weather API to get the daily forecast
+    let weather_api_key = ctx.api_key("WEATHER_API_KEY")?;
+    let forecast_response = weather::get_forecast(
+        &ctx.http,
+        &ctx.config.weather,
+        &weather_api_key,
+        &location,
+        days,
+    )
+    .await?;
+    let forecast_days = &forecast_response.forecast.forecastday;
+
+    let (Some(first), Some(last)) = (forecast_days.first(), forecast_days.last()) else {
+        return Err(AppError::ResponseParseError(
+            "Forecast contains no days".to_string(),
+        ));
+    };
+
+    let selected: Vec<_> = match day {
+        Some(day) => {
+            // The first forecast day is the location's current date
+            let today = NaiveDate::parse_from_str(&first.date, "%Y-%m-%d").map_err(|_| {
+                AppError::ResponseParseError(format!("Unexpected forecast date: {}", first.date))
+            })?;
+            let date = dates::resolve_day(day, today)?
+                .format("%Y-%m-%d")
+                .to_string();
+
+            match forecast_days.iter().find(|d| d.date == date) {
+                Some(forecast_day) => vec![forecast_day],
+                None => {
+                    return Ok(json!({
+                        "available": false,
+                        "message": format!(
+                            "{} ({}) is outside the forecast range {} to {}",
+                            day, date, first.date, last.date
+                        ),
+                    }));
+                }
+            }
+        }
+        None => forecast_days.iter().collect(),
+    };
+
+    // Format the response with one entry per day in the requested unit
+    let mut result = json!({
+        "days": selected
+            .iter()
+            .map(|forecast_day| {
+                // Days are counted from the first forecast day, the location's today
+                let days_ahead = forecast_days
+                    .iter()
+                    .position(|d| d.date == forecast_day.date)
+                    .unwrap_or_default();
+                forecast_day_json(
+                    forecast_day,
+                    unit,
+                    days_ahead as u32,
+                    ctx.config.missing_value,
+                )
+            })
+            .collect::<Vec<_>>(),
+        "source": weather::SOURCE,
+    });
+    if let Some(requested) = clamped_from {
+        result["note"] = json!(format!(
+            "{} days were requested but only {} forecast days are available",
+            requested, max_days
+        ));
+    }
+
+    Ok(result)
+}
+
+/// `best_day`: the forecast day that suits an activity best within the requested range
+async fn best_day(ctx: &Context, args: BestDayArgs) -> Result<Value, AppError> {
+    let BestDayArgs {
+        city,
+        country,
+        activity,
+        unit,
+        start_date,
+        end_date,
+    } = args;
+    let unit = unit.unwrap_or_else(|| ctx.default_unit(&country));
+
+    let location = format!("{},{}", city, country);
+
+    // Call the weather API to get the daily forecast
+    let weather_api_key = ctx.api_key("WEATHER_API_KEY")?;
+    let forecast_response = weather::get_forecast(
+        &ctx.http,
+        &ctx.config.weather,
+        &weather_api_key,
+        &location,
+        ctx.config.max_forecast_days,
+    )
+    .await?;
+
+    // The range counts from the location's own date, which can differ from ours
+    let today = forecast_response
+        .location
+        .as_ref()
+        .and_then(|location| location.localtime.as_deref())
+        .and_then(|localtime| localtime.get(..10))
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| Local::now().date_naive());
+    let (first_day, last_day) =
+        best_day_window(today, start_date, end_date, ctx.config.max_forecast_days);
+
+    // Keep only the days within the requested range
+    let candidates: Vec<_> = forecast_response
+        .forecast
+        .forecastday
+        .iter()
+        .filter(|day| {
+            let Ok(date) = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d") else {
+                return false;
+            };
+            (first_day..=last_day).contains(&date)
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        return Err(AppError::InvalidParameter(
+            "the requested dates are outside the available forecast".to_string(),
+        ));
+    }
+
+    // Format the response with the top pick or an explicit "no good day" answer
+    let result = match activity::best_day(activity, candidates.iter().copied(), unit) {
+        Some(best) => json!({
+            "best_day": best.date,
+            "score": best.score,
+            "reasons": best.reasons,
+            "source": weather::SOURCE,
+        }),
+        None => json!({
+            "best_day": null,
+            "message": format!(
+                "No good day for {:?} between {} and {}",
+                activity,
+                candidates[0].date,
+                candidates[candidates.len() - 1].date
+            ),
+            "days_considered": candidates.len(),
+            "source": weather::SOURCE,
+        }),
+    };
+
+    Ok(result)
+}
+
+/// `chance_of`: the chance of rain or snow on a day, with a yes/no answer
+async fn chance_of(ctx: &Context, args: ChanceArgs) -> Result<Value, AppError> {
+    let ChanceArgs {
+        city,
+        country,
+        condition,
+        day,
+    } = args;
+    let location = format!("{},{}", city, country);
+
+    // Call the weather API to get the daily forecast
+    let weather_api_key = ctx.api_key("WEATHER_API_KEY")?;
+    let forecast_response = weather::get_forecast(
+        &ctx.http,
+        &ctx.config.weather,
+        &weather_api_key,
+        &location,
+        ctx.config.max_forecast_days,
+    )
+    .await?;
+    let forecast_days = &forecast_response.forecast.forecastday;
+
+    let (Some(first), Some(last)) = (forecast_days.first(), forecast_days.last()) else {
+        return Err(AppError::ResponseParseError(
+            "Forecast contains no days".to_string(),
+        ));
+    };
+
+    // The first forecast day is the location's current date
+    let today = NaiveDate::parse_from_str(&first.date, "%Y-%m-%d").map_err(|_| {
+        AppError::ResponseParseError(format!("Unexpected forecast date: {}", first.date))
+    })?;
+    let date = dates::resolve_day(&day, today)?
+        .format("%Y-%m-%d")
+        .to_string();
+
+    let Some(forecast_day) = forecast_days.iter().find(|d| d.date == date) else {
+        return Ok(json!({
+            "available": false,
+            "message": format!(
+                "{} ({}) is outside the forecast range {} to {}",
+                day, date, first.date, last.date
+            ),
+        }));
+    };
+
+    let chance = match condition.as_str() {
+        "snow" => forecast_day.day.daily_chance_of_snow,
+        _ => forecast_day.day.daily_chance_of_rain,
+    };
+
+    // Format the response with the chance and a yes/no answer for the model
+    let missing = ctx.config.missing_value;
+    Ok(json!({
+        "date": date,
+        "condition": condition,
+        "chance": weather::optional_field(chance, missing),
+        "likely": weather::optional_field(
+            chance.map(|chance| chance >= LIKELY_CHANCE_PERCENT),
+            missing,
+        ),
+        "source": weather::SOURCE,
+    }))
+}
+
+/// `compare_weather`: the current weather at several locations, looked up concurrently
+async fn compare_weather(ctx: &Context, args: CompareArgs) -> Result<Value, AppError> {
+    let CompareArgs {
+        places,
+        include_trends,
+    } = args;
+    // Look up every location concurrently; a failure only affects its own entry
+    let cities = future::join_all(places.iter().map(|(city, country)| async move {
+        let entry = compare_entry(ctx, city, country).await.unwrap_or_else(
+            |e| json!({ "city": city, "country": country, "error": e.to_string() }),
+        );
+        if ctx.config.stream_progress {
+            show_progress(ctx, &entry);
+        }
+        entry
+    }))
+    .await;
+
+    // Summarize the cities that were found, relative to the first one
+    let found: Vec<CityWeather> = cities
+        .iter()
+        .filter_map(|city| serde_json::from_value(city.clone()).ok())
+        .collect();
+    let cities: Vec<serde_json::Value> = cities
+        .into_iter()
+        .map(|mut city| {
+            if let Some(humidity) = city.get_mut("humidity")
+                && humidity.is_null()
+            {
+                *humidity = weather::optional_field(None::<i32>, ctx.config.missing_value);
+            }
+            city
+        })
+        .collect();
+    let units = match found.first() {
+        Some(first) => UnitSystem::from_unit(ctx.default_unit(&first.country)),
+        None => UnitSystem::Metric,
+    };
+
+    let mut result = json!({
+        "cities": cities,
+        "summary": comparison::summarize_comparison(&found, units),
+    });
+
+    // Forecast swings cost one more request per city, so only fetch them on request
+    if include_trends {
+        result["trends"] = compare_trends(ctx, &places, units).await;
+    }
+    Ok(result)
+}
+
+/// `get_air_quality`: the current air quality readings, cached per location
+async fn get_air_quality(ctx: &Context, args: LocationArgs) -> Result<Value, AppError> {
+    let LocationArgs { city, country } = args;
+    let location = format!("{},{}", city, country);
+
+    // Serve repeated lookups from the cache, otherwise call the weather API
+    let key = CacheKey::new(ToolKind::AirQuality.name(), &location, None);
+    ctx.cache
+        .get_or_fetch(
+            key,
+            ctx.config.weather_cache_ttl,
+            ctx.config.negative_cache_ttl,
+            || async {
+                let weather_api_key = ctx.api_key("WEATHER_API_KEY")?;
+                let air_quality = weather::get_air_quality(
+                    &ctx.http,
+                    &ctx.config.weather,
+                    &weather_api_key,
+                    &location,
+                )
+                .await?
+                .current
+                .air_quality;
+
+                // Readings without sensor data are reported as missing, not as 0
+                let missing = ctx.config.missing_value;
+                Ok(json!({
+                    "pm2_5": weather::optional_field(air_quality.pm2_5, missing),
+                    "pm10": weather::optional_field(air_quality.pm10, missing),
+                    "us_epa_index": weather::optional_field(air_quality.us_epa_index, missing),
+                    "label": weather::optional_field(air_quality.epa_label(), missing),
+                    "source": weather::SOURCE,
+                }))
+            },
+        )
+        .await
+}
+
+/// `get_astronomy`: sunrise, sunset, moon times and the moon phase on a day
+async fn get_astronomy(ctx: &Context, args: AstronomyArgs) -> Result<Value, AppError> {
+    let (date, astro) = astronomy(ctx, args).await?;
+    let missing = ctx.config.missing_value;
+    Ok(json!({
+        "date": date,
+        "sunrise": astro.sunrise,
+        "sunset": astro.sunset,
+        "moonrise": weather::optional_field(astro.moonrise, missing),
+        "moonset": weather::optional_field(astro.moonset, missing),
+        "moon_phase": weather::optional_field(astro.moon_phase, missing),
+        "source": weather::SOURCE,
+    }))
+}
+
+/// `get_moon_phase`: the astronomy data without the sun and moon times
+async fn get_moon_phase(ctx: &Context, args: AstronomyArgs) -> Result<Value, AppError> {
+    let (date, astro) = astronomy(ctx, args).await?;
+    let missing = ctx.config.missing_value;
+    Ok(json!({
+        "date": date,
+        "moon_phase": weather::optional_field(astro.moon_phase, missing),
+        "moon_illumination": weather::optional_field(astro.moon_illumination, missing),
+        "source": weather::SOURCE,
+    }))
+}
+
+/// `get_weather_alerts`: the active severe weather alerts, cached per location
+async fn get_weather_alerts(ctx: &Context, args: LocationArgs) -> Result<Value, AppError> {
+    let LocationArgs { city, country } = args;
+    let location = format!("{},{}", city, country);
+
+    // Serve repeated lookups from the cache, otherwise call the weather API
+    let key = CacheKey::new(ToolKind::WeatherAlerts.name(), &location, None);
+    ctx.cache
+        .get_or_fetch(
+            key,
+            ctx.config.weather_cache_ttl,
+            ctx.config.negative_cache_ttl,
+            || async {
+                let weather_api_key = ctx.api_key("WEATHER_API_KEY")?;
+                let alerts_response = weather::get_alerts(
+                    &ctx.http,
+                    &ctx.config.weather,
+                    &weather_api_key,
+                    &location,
+                )
+                .await?;
+
+                // No alerts get an explicit answer so the model doesn't guess
+                let active = alerts_response.active(chrono::Utc::now());
+                if active.is_empty() {
+                    return Ok(json!({
+                        "active_alerts": 0,
+                        "message": "No active weather alerts",
+                        "source": weather::SOURCE,
+                    }));
+                }
+
+                // Fields the issuing service left out come as empty strings
+                let missing = ctx.config.missing_value;
+                let field = |value: &Option<String>| {
+                    weather::optional_field(
+                        value.as_deref().filter(|value| !value.is_empty()),
+                        missing,
+                    )
+                };
+                let alerts: Vec<serde_json::Value> = active
+                    .iter()
+                    .map(|alert| {
+                        json!({
+                            "headline": field(&alert.headline),
+                            "severity": field(&alert.severity),
+                            "event": field(&alert.event),
+                            "expires": field(&alert.expires),
+                        })
+                    })
+                    .collect();
+                Ok(json!({
+                    "active_alerts": alerts.len(),
+                    "alerts": alerts,
+                    "source": weather::SOURCE,
+                }))
+            },
+        )
+        .await
+}
+
+/// Sun and moon data for the day `args` asks for, with that day as YYYY-MM-DD.
+async fn astronomy(ctx: &Context, args: AstronomyArgs) -> Result<(String, Astro), AppError> {
+    let location = format!("{},{}", args.city, args.country);
+    let today = Local::now().date_naive();
+    let date = match args.date {
+        Some(date) => dates::resolve_day(&date, today)?,
+        None => today,
+    }
+    .format("%Y-%m-%d")
+    .to_string();
+
+    // Call the weather API to get the sun and moon times
+    let weather_api_key = ctx.api_key("WEATHER_API_KEY")?;
+    let astro = weather::get_astronomy(
+        &ctx.http,
+        &ctx.config.weather,
+        &weather_api_key,
+        &location,
+        &date,
+    )
+    .await?
+    .astronomy
+    .astro;
+
+    Ok((date, astro))
+}
+
+/// Current weather from the provider selected by `WEATHER_PROVIDER`.
+async fn current_weather(ctx: &Context, location: &str) -> Result<NormalizedWeather, AppError> {
+    let provider = ctx.config.weather_provider;
+    let api_key = ctx.api_key(provider.key_var())?;
+
+    match provider {
+        weather::Provider::WeatherApi => {
+            WeatherApi {
+                http: &ctx.http,
+                api: &ctx.config.weather,
+                api_key: &api_key,
+            }
+            .current(location)
+            .await
+        }
+        weather::Provider::OpenWeatherMap => {
+            OpenWeatherMap {
+                http: &ctx.http,
+                api: &ctx.config.openweathermap,
+                api_key: &api_key,
+            }
+            .current(location)
+            .await
+        }
+    }
+}
+
+/// Current weather for one compared location, with the temperature in both units.
+async fn compare_entry(
+    ctx: &Context,
+    city: &str,
+    country: &str,
+) -> Result<serde_json::Value, AppError> {
+    let location = format!("{},{}", city, country);
+
+    // Serve repeated lookups from the cache, otherwise call the weather API
+    let key = CacheKey::new(ToolKind::CompareWeather.name(), &location, None);
+    ctx.cache
+        .get_or_fetch(
+            key,
+            ctx.config.weather_cache_ttl,
+            ctx.config.negative_cache_ttl,
+            || async {
+                let current = current_weather(ctx, &location).await?;
+
+                let city_weather = CityWeather {
+                    city: city.to_string(),
+                    country: country.to_string(),
+                    temp_c: current.temp_c,
+                    temp_f: current.temp_f,
+                    condition: current.condition,
+                    humidity: current.humidity,
+                    source: current.source.to_string(),
+                };
+                Ok(serde_json::to_value(city_weather)?)
+            },
+        )
+        .await
+}
+
+/// Show one compared location as soon as its lookup finishes (`--stream-progress`).
+///
+/// e.g. "Tokyo: 22°C, sunny"; failed lookups are shown as unavailable.
+fn show_progress(ctx: &Context, entry: &serde_json::Value) {
+    let line = match serde_json::from_value::<CityWeather>(entry.clone()) {
+        Ok(city) => {
+            let unit = ctx.default_unit(&city.country);
+            let temperature = match unit {
+                "F" => city.temp_f,
+                _ => city.temp_c,
+            };
+            format!(
+                "{}: {}°{}, {}",
+                city.city,
+                temperature.round(),
+                unit,
+                city.condition.to_lowercase()
+            )
+        }
+        Err(_) => format!(
+            "{}: unavailable",
+            entry["city"].as_str().unwrap_or("unknown location")
+        ),
+    };
+    ctx.output.progress(&line);
+}
+
+/// Forecast temperature swings of the compared locations, fetched concurrently.
+///
+/// A failed forecast only turns its own entry into an error; the largest swing is picked
+/// from the locations that succeeded.
+async fn compare_trends(
+    ctx: &Context,
+    places: &[(String, String)],
+    units: UnitSystem,
+) -> serde_json::Value {
+    let days = ctx.config.max_forecast_days;
+    let swings = future::join_all(places.iter().map(|(city, country)| async move {
+        let weather_api_key = ctx.api_key("WEATHER_API_KEY")?;
+        let location = format!("{},{}", city, country);
+        let forecast = weather::get_forecast(
+            &ctx.http,
+            &ctx.config.weather,
+            &weather_api_key,
+            &location,
+            days,
+        )
+        .await?;
+        comparison::temperature_swing(city, country, &forecast.forecast.forecastday, units)
+            .ok_or_else(|| AppError::ResponseParseError("Forecast contains no days".to_string()))
+    }))
+    .await;
+
+    let mut entries = Vec::with_capacity(places.len());
+    let mut found = Vec::with_capacity(places.len());
+    for ((city, country), swing) in places.iter().zip(swings) {
+        match swing {
+            Ok(swing) => {
+                entries.push(json!(swing));
+                found.push(swing);
+            }
+            Err(e) => {
+                entries.push(json!({ "city": city, "country": country, "error": e.to_string() }))
+            }
+        }
+    }
+
+    json!({
+        "days": days,
+        "unit": match units {
+            UnitSystem::Metric => "C",
+            UnitSystem::Imperial => "F",
+        },
+        "swings": entries,
+        "largest_swing": comparison::largest_swing(&found),
+    })
+}
+
+/// Format a forecast day for the model with temperatures in the requested unit.
+///
+/// The confidence tells the model how tentatively to phrase forecasts further ahead.
+fn forecast_day_json(
+    forecast_day: &ForecastDay,
+    unit: &str,
+    days_ahead: u32,
+    missing: MissingValue,
+) -> serde_json::Value {
+    let day = &forecast_day.day;
+    let (high, low) = match unit {
+        "F" => (day.maxtemp_f, day.mintemp_f),
+        _ => (day.maxtemp_c, day.mintemp_c),
+    };
+
+    json!({
+        "date": forecast_day.date,
+        "high": high,
+        "low": low,
+        "condition": day.condition.official_text(true),
+        "chance_of_rain": weather::optional_field(day.daily_chance_of_rain, missing),
+        "max_wind_kph": weather::optional_field(day.maxwind_kph, missing),
+        "confidence": weather::forecast_confidence(days_ahead),
+    })
+}
+
+/// First and last day a best-day search covers when the location's date is `today`.
+///
+/// The requested range is cut to the `max_days` days of forecast, today included; it is
+/// empty (first after last) when it lies wholly outside them.
+fn best_day_window(
+    today: NaiveDate,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+    max_days: u32,
+) -> (NaiveDate, NaiveDate) {
+    let last = today + Days::new(u64::from(max_days.max(1)) - 1);
+    (
+        start_date.map_or(today, |start| start.max(today)),
+        end_date.map_or(last, |end| end.min(last)),
+    )
+}
+
+/// Looks up the local date and time at a location, served from the cache when fresh.
+///
+/// Returns an object with `time` ("2025-06-01 08:30:00 PM"), `date` and `time_12`.
+async fn local_time(
+    ctx: &Context,
+    city: &str,
+    country: &str,
+) -> Result<serde_json::Value, AppError> {
+    let location = format!("{},{}", city, country);
+
+    // Serve repeated lookups from the cache, otherwise call the geolocation API
+    let key = CacheKey::new(ToolKind::CurrentTime.name(), &location, None);
+    ctx.cache
+        .get_or_fetch(
+            key,
+            ctx.config.time_cache_ttl,
+            ctx.config.negative_cache_ttl,
+            || async {
+                let geo_location_api_key = ctx.api_key("IP_GEOLOCATION_API_KEY")?;
+                let time_response = geo_location::get_time(
+                    &ctx.http,
+                    &ctx.config.geo,
+                    &geo_location_api_key,
+                    &location,
+                )
+                .await?;
+
+                // Format the response with date, time and timezone information
+                Ok(json!({
+                    "time": format!("{} {}", time_response.date, time_response.time_12),
+                    "date": time_response.date,
+                    "time_12": time_response.time_12,
+                    "timezone": time_response.timezone,
+                    "utc_offset_hours": time_response.utc_offset_hours(),
+                    "utc_offset": time_response.utc_offset(),
+                    "is_dst": time_response.is_dst,
+                }))
+            },
+        )
+        .await
+}
+
+/// Schema of arguments naming a location: the required `city` and `country`, then
+/// `properties`, of which the `required` ones must be given too
+fn location_schema(properties: Value, required: &[&str]) -> Value {
+    let mut schema = json!({
+        "type": "object",
+        "properties": {
+            "city": {
+                "type": "string",
+                "description": "City name in English, Latin script (e.g., \"Seattle\")."
+            },
+            "country": {
+                "type": "string",
+                "description": "ISO‑3166‑1 alpha‑2 country code, e.g., \"US\"."
+            }
+        },
+        "required": ["city", "country"]
+    });
+    if let (Some(all), Value::Object(properties)) =
+        (schema["properties"].as_object_mut(), properties)
+    {
+        all.extend(properties);
+    }
+    if let Some(all) = schema["required"].as_array_mut() {
+        all.extend(required.iter().map(|name| json!(name)));
+    }
+    schema
+}
+
+/// Schema of the optional `unit` argument
+fn unit_property() -> Value {
+    json!({
+        "type": "string",
+        "enum": ["C", "F"],
+        "description": "Temperature unit (C for Celsius, F for Fahrenheit); defaults to the unit customary in the country"
+    })
+}
+
+/// Schema of the optional day the astronomy tools report on
+fn report_day_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Day to report: \"today\", \"tomorrow\", a weekday name (e.g., \"saturday\") or YYYY-MM-DD; defaults to today."
+    })
+}
+
+/// Lowercases a name in place
+fn lowercase(name: &mut String) {
+    *name = name.to_lowercase();
+}
+
+/// A required string argument
+fn required_str(args: &Map<String, Value>, name: &str) -> Result<String, AppError> {
+    optional_str(args, name).ok_or_else(|| AppError::MissingParameter(name.to_string()))
+}
+
+/// An optional string argument; a value of another type counts as absent
+fn optional_str(args: &Map<String, Value>, name: &str) -> Option<String> {
+    args.get(name).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+/// The optional `unit` argument, normalized to "C" or "F"
+fn unit_arg(args: &Map<String, Value>) -> Result<Option<&'static str>, AppError> {
+    let Some(unit) = args.get("unit").and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+
+    match unit.trim() {
+        "C" | "c" => Ok(Some("C")),
+        "F" | "f" => Ok(Some("F")),
+        _ => Err(AppError::UnsupportedUnit(unit.to_string())),
+    }
+}
+
+/// The optional `days` argument; the model may send a whole count as a float like `3.0`
+fn days_arg(args: &Map<String, Value>) -> Result<Option<u64>, AppError> {
+    let days = match args.get("days") {
+        None | Some(Value::Null) => return Ok(None),
+        Some(value) => value.as_u64().or_else(|| {
+            value
+                .as_f64()
+                .filter(|days| days.fract() == 0.0 && *days >= 0.0)
+                .map(|days| days as u64)
+        }),
+    };
+    days.map(Some).ok_or_else(|| {
+        AppError::InvalidParameter("days must be a whole number of days".to_string())
+    })
+}
+
+/// An optional "YYYY-MM-DD" date argument
+fn date_arg(args: &Map<String, Value>, name: &str) -> Result<Option<NaiveDate>, AppError> {
+    args.get(name)
+        .and_then(|v| v.as_str())
+        .map(|value| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+                AppError::InvalidParameter(format!("{} must be in format YYYY-MM-DD", name))
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `args` as the arguments of `kind`
+    fn parse(kind: ToolKind, args: Value) -> Result<ToolArgs, AppError> {
+        kind.parse_args(args.as_object().unwrap())
+    }
+
+    #[test]
+    fn time_takes_an_offset_or_a_date_but_not_both() {
+        let time = |args: Value| parse(ToolKind::CurrentTime, args);
+        match time(
+            json!({ "city": "Oslo", "country": "NO", "offset_hours": -0.5, "date": "tomorrow" }),
+        ) {
+            Err(AppError::InvalidParameter(message)) => {
+                assert_eq!(message, "give either offset_hours or date, not both")
+            }
+            other => panic!("expected the combination to be rejected, got {:?}", other),
+        }
+
+        assert!(matches!(
+            time(json!({ "city": "Oslo", "country": "NO", "offset_hours": -0.5 })),
+            Ok(ToolArgs::CurrentTime(TimeArgs {
+                offset_hours: Some(_),
+                date: None,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            time(
+                json!({ "city": "Oslo", "country": "NO", "offset_hours": null, "date": "tomorrow" })
+            ),
+            Ok(ToolArgs::CurrentTime(TimeArgs {
+                offset_hours: None,
+                date: Some(_),
+                ..
+            }))
+        ));
+    }
+
+    /// The `days` of parsed forecast arguments
+    fn forecast_days(days: Value) -> Result<Option<u64>, AppError> {
+        let args = json!({ "city": "Oslo", "country": "NO", "days": days });
+        match parse(ToolKind::Forecast, args)? {
+            ToolArgs::Forecast(forecast) => Ok(forecast.days),
+            other => panic!("unexpected arguments {:?}", other),
+        }
+    }
+
+    #[test]
+    fn days_accepts_whole_numbers_sent_as_floats() {
+        assert_eq!(forecast_days(json!(3)).unwrap(), Some(3));
+        assert_eq!(forecast_days(json!(3.0)).unwrap(), Some(3));
+        assert_eq!(forecast_days(Value::Null).unwrap(), None);
+    }
+
+    #[test]
+    fn days_rejects_fractional_and_negative_counts() {
+        for days in [json!(2.5), json!(-1), json!(-2.0), json!("three")] {
+            assert!(
+                matches!(
+                    forecast_days(days.clone()),
+                    Err(AppError::InvalidParameter(_))
+                ),
+                "days {} was accepted",
+                days
+            );
+        }
+    }
+
+    #[test]
+    fn missing_required_fields_are_named() {
+        let missing = |kind, args| match parse(kind, args) {
+            Err(AppError::MissingParameter(name)) => name,
+            other => panic!("expected a missing parameter, got {:?}", other),
+        };
+        assert_eq!(
+            missing(ToolKind::Weather, json!({ "city": "Oslo" })),
+            "country"
+        );
+        assert_eq!(
+            missing(ToolKind::Forecast, json!({ "country": "NO" })),
+            "city"
+        );
+        assert_eq!(
+            missing(
+                ToolKind::BestDay,
+                json!({ "city": "Oslo", "country": "NO" })
+            ),
+            "activity"
+        );
+        assert_eq!(missing(ToolKind::CompareWeather, json!({})), "locations");
+        assert_eq!(
+            missing(
+                ToolKind::CompareWeather,
+                json!({ "locations": [{ "city": "Oslo" }, { "city": "Rome", "country": "IT" }] })
+            ),
+            "locations.country"
+        );
+    }
+
+    #[test]
+    fn unrecognized_units_are_unsupported() {
+        for unit in ["K", "kelvin", "celsius", ""] {
+            for kind in [ToolKind::Weather, ToolKind::Forecast, ToolKind::BestDay] {
+                let args = json!({
+                    "city": "Oslo",
+                    "country": "NO",
+                    "activity": "hike",
+                    "unit": unit,
+                });
+                match parse(kind, args) {
+                    Err(error @ AppError::UnsupportedUnit(_)) => assert_eq!(
+                        error.to_string(),
+                        format!("Unsupported temperature unit: {} (expected C or F)", unit)
+                    ),
+                    other => panic!("unit {:?} of {:?} gave {:?}", unit, kind, other),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn optional_fields_are_validated() {
+        let weather = parse(
+            ToolKind::Weather,
+            json!({ "city": "Oslo", "country": "NO", "unit": "f" }),
+        );
+        assert!(matches!(
+            weather,
+            Ok(ToolArgs::Weather(WeatherArgs {
+                unit: Some("F"),
+                ..
+            }))
+        ));
+        assert!(matches!(
+            parse(
+                ToolKind::CurrentTime,
+                json!({ "city": "Oslo", "country": "NO", "offset_hours": 2, "date": "2024-05-01" })
+            ),
+            Err(AppError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            parse(
+                ToolKind::BestDay,
+                json!({
+                    "city": "Oslo",
+                    "country": "NO",
+                    "activity": "hiking",
+                    "start_date": "2024-05-03",
+                    "end_date": "2024-05-01"
+                })
+            ),
+            Err(AppError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn declarations_share_the_location_arguments() {
+        let settings = crate::config::Settings::from_file_text("");
+        let cli = settings.cli(["cloud-gemini"]).unwrap();
+        let config = Config::new(&cli, &settings).unwrap();
+
+        for kind in ToolKind::ALL {
+            let tool = kind.declaration(&config);
+            assert_eq!(tool.name, kind.name());
+
+            // Compared locations are listed, every other tool takes one
+            let schema = tool.schema.unwrap();
+            let location = match kind {
+                ToolKind::CompareWeather => &schema["properties"]["locations"]["items"],
+                _ => &schema,
+            };
+            assert_eq!(
+                location["properties"]["city"]["type"],
+                "string",
+                "{}",
+                kind.name()
+            );
+            assert_eq!(location["required"][0], "city", "{}", kind.name());
+            assert_eq!(location["required"][1], "country", "{}", kind.name());
+        }
+
+        let best_day = ToolKind::BestDay.declaration(&config).schema.unwrap();
+        assert_eq!(best_day["required"], json!(["city", "country", "activity"]));
+        let forecast = ToolKind::Forecast.declaration(&config).schema.unwrap();
+        assert_eq!(
+            forecast["properties"]["days"]["maximum"],
+            config.max_forecast_days
+        );
+    }
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn best_day_window_keeps_a_range_ending_on_the_last_forecast_day() {
+        let window = best_day_window(
+            day("2024-05-01"),
+            Some(day("2024-05-02")),
+            Some(day("2024-05-03")),
+            3,
+        );
+        assert_eq!(window, (day("2024-05-02"), day("2024-05-03")));
+    }
+
+    #[test]
+    fn best_day_window_is_cut_to_the_forecast() {
+        let today = day("2024-05-01");
+        assert_eq!(
+            best_day_window(today, None, Some(day("2024-05-09")), 3),
+            (today, day("2024-05-03"))
+        );
+        assert_eq!(
+            best_day_window(today, Some(day("2024-04-28")), None, 3),
+            (today, day("2024-05-03"))
+        );
+
+        let (first, last) = best_day_window(today, Some(day("2024-05-04")), None, 3);
+        assert!(first > last);
+    }
+}
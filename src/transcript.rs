@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::Path;
+
+use genai::chat::{ChatMessage, ChatRole};
+
+use crate::error::AppError;
+
+/// One user turn of a saved conversation with the answer it originally received
+#[derive(Debug)]
+pub struct Turn {
+    /// The user's message
+    pub question: String,
+    /// The last assistant text before the next user message, if any
+    pub answer: Option<String>,
+}
+
+/// Loads the user turns of a transcript saved as a JSON array of chat messages.
+///
+/// Tool calls and tool responses are skipped; only user text and the final assistant
+/// text of each turn are kept.
+pub fn load(path: &Path) -> Result<Vec<Turn>, AppError> {
+    let messages = read_messages(path)?;
+    let mut turns: Vec<Turn> = Vec::new();
+
+    for message in messages {
+        let Some(text) = message.content.text_into_string() else {
+            continue;
+        };
+        match message.role {
+            ChatRole::User => turns.push(Turn {
+                question: text,
+                answer: None,
+            }),
+            ChatRole::Assistant => {
+                if let Some(turn) = turns.last_mut() {
+                    turn.answer = Some(text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(turns)
+}
+
+/// Reads a conversation saved as a JSON array of chat messages.
+pub fn read_messages(path: &Path) -> Result<Vec<ChatMessage>, AppError> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Saves a conversation as a JSON array of chat messages, replacing the file.
+pub fn write_messages(path: &Path, messages: &[ChatMessage]) -> Result<(), AppError> {
+    fs::write(path, serde_json::to_string_pretty(messages)?)?;
+    Ok(())
+}
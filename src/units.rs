@@ -0,0 +1,80 @@
+// Countries and territories that report temperatures in Fahrenheit (ISO-3166-1 alpha-2)
+const FAHRENHEIT_COUNTRIES: [&str; 12] = [
+    "US", "BS", "KY", "LR", "PW", "FM", "MH", "PR", "GU", "VI", "AS", "MP",
+];
+
+/// Temperature unit customary in a country, as "C" or "F".
+///
+/// Unknown or malformed country codes get Celsius.
+pub fn default_unit_for_country(country: &str) -> &'static str {
+    let country = country.trim().to_uppercase();
+    if FAHRENHEIT_COUNTRIES.contains(&country.as_str()) {
+        "F"
+    } else {
+        "C"
+    }
+}
+
+/// Converts a Celsius temperature to Fahrenheit
+pub fn fahrenheit(celsius: f64) -> f64 {
+    celsius * 9.0 / 5.0 + 32.0
+}
+
+/// Converts a Fahrenheit temperature to Celsius
+pub fn celsius(fahrenheit: f64) -> f64 {
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+/// Unit system used for temperatures in model-facing summaries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitSystem {
+    /// Degrees Celsius
+    Metric,
+    /// Degrees Fahrenheit
+    Imperial,
+}
+
+impl UnitSystem {
+    /// The unit system of a temperature unit, "C" or "F"
+    pub fn from_unit(unit: &str) -> Self {
+        match unit {
+            "F" => UnitSystem::Imperial,
+            _ => UnitSystem::Metric,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_temp_eq;
+
+    #[test]
+    fn fahrenheit_converts_celsius() {
+        assert_temp_eq(fahrenheit(0.0), 32.0);
+        assert_temp_eq(fahrenheit(100.0), 212.0);
+        assert_temp_eq(fahrenheit(-40.0), -40.0);
+        assert_temp_eq(fahrenheit(18.5), 65.3);
+    }
+
+    #[test]
+    fn celsius_converts_fahrenheit() {
+        assert_temp_eq(celsius(32.0), 0.0);
+        assert_temp_eq(celsius(212.0), 100.0);
+        assert_temp_eq(celsius(fahrenheit(21.7)), 21.7);
+    }
+
+    #[test]
+    fn fahrenheit_countries_default_to_fahrenheit() {
+        for country in ["US", "us", " BS ", "KY", "PR"] {
+            assert_eq!(default_unit_for_country(country), "F", "{}", country);
+        }
+    }
+
+    #[test]
+    fn other_countries_default_to_celsius() {
+        for country in ["GB", "CA", "MX", "NO", "", "USA", "United States"] {
+            assert_eq!(default_unit_for_country(country), "C", "{}", country);
+        }
+    }
+}
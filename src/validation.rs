@@ -0,0 +1,91 @@
+use serde_json::{Map, Value};
+
+use crate::error::AppError;
+
+/// Checks that a country is a two-letter ISO 3166-1 alpha-2 code and uppercases it.
+///
+/// Full names like "United States" are rejected with guidance instead of being passed on,
+/// since the APIs may resolve them to a different place.
+pub fn country_code(country: &str) -> Result<String, AppError> {
+    let code = country.trim();
+    if code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(code.to_ascii_uppercase())
+    } else {
+        Err(AppError::InvalidParameter(format!(
+            "country must be a two-letter ISO 3166-1 alpha-2 code like \"US\" or \"GB\", got \"{}\"",
+            country
+        )))
+    }
+}
+
+/// Validates and uppercases the `country` argument and the `country` of every entry in
+/// `locations`, leaving other arguments untouched.
+pub fn normalize_country_codes(args: &mut Map<String, Value>) -> Result<(), AppError> {
+    if let Some(locations) = args.get_mut("locations").and_then(Value::as_array_mut) {
+        for country in locations
+            .iter_mut()
+            .filter_map(|location| location.get_mut("country"))
+        {
+            normalize(country)?;
+        }
+    }
+    if let Some(country) = args.get_mut("country") {
+        normalize(country)?;
+    }
+    Ok(())
+}
+
+/// Replaces a string country with its validated code; other values are left for the
+/// tool's own parameter checks to report.
+fn normalize(country: &mut Value) -> Result<(), AppError> {
+    if let Some(code) = country.as_str() {
+        *country = Value::String(country_code(code)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn country_codes_are_uppercased() {
+        assert_eq!(country_code("US").unwrap(), "US");
+        assert_eq!(country_code("us").unwrap(), "US");
+        assert_eq!(country_code(" gb ").unwrap(), "GB");
+    }
+
+    #[test]
+    fn country_names_and_empty_countries_are_rejected() {
+        for country in ["United States", "", "USA", "U1"] {
+            assert!(
+                matches!(country_code(country), Err(AppError::InvalidParameter(_))),
+                "country {:?} was accepted",
+                country
+            );
+        }
+    }
+
+    #[test]
+    fn countries_in_arguments_and_locations_are_normalized() {
+        let mut args = json!({
+            "country": "no",
+            "locations": [{ "city": "Rome", "country": "it" }, { "city": "Paris" }],
+            "city": "Oslo",
+        });
+        normalize_country_codes(args.as_object_mut().unwrap()).unwrap();
+        assert_eq!(
+            args,
+            json!({
+                "country": "NO",
+                "locations": [{ "city": "Rome", "country": "IT" }, { "city": "Paris" }],
+                "city": "Oslo",
+            })
+        );
+
+        let mut args = json!({ "locations": [{ "country": "Italy" }] });
+        assert!(normalize_country_codes(args.as_object_mut().unwrap()).is_err());
+    }
+}
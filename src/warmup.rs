@@ -0,0 +1,66 @@
+use tracing::info;
+
+use crate::context::Context;
+use crate::error::AppError;
+use crate::weather::WeatherProvider;
+use crate::weather::openweathermap::OpenWeatherMap;
+use crate::{geo_location, weather};
+
+// Location used for every startup probe
+const PROBE_LOCATION: &str = "London,GB";
+
+/// Makes one small request to each configured API so a rejected key fails at startup.
+///
+/// APIs without a key are skipped; they report themselves unavailable when a tool needs
+/// them. An unknown probe location still proves the key works.
+pub async fn warm_up(ctx: &Context) -> Result<(), AppError> {
+    match ctx.api_key("WEATHER_API_KEY") {
+        Ok(key) => {
+            let result =
+                weather::get_weather(&ctx.http, &ctx.config.weather, &key, PROBE_LOCATION).await;
+            check("WeatherAPI", result.map(|_| ()))?;
+        }
+        Err(_) => info!("Warm-up: WEATHER_API_KEY not set, skipping WeatherAPI"),
+    }
+
+    // OpenWeatherMap only answers current weather lookups, so it is probed only when selected
+    if ctx.config.weather_provider == weather::Provider::OpenWeatherMap {
+        match ctx.api_key("OPENWEATHERMAP_API_KEY") {
+            Ok(key) => {
+                let provider = OpenWeatherMap {
+                    http: &ctx.http,
+                    api: &ctx.config.openweathermap,
+                    api_key: &key,
+                };
+                let result = provider.current(PROBE_LOCATION).await;
+                check("OpenWeatherMap", result.map(|_| ()))?;
+            }
+            Err(_) => info!("Warm-up: OPENWEATHERMAP_API_KEY not set, skipping OpenWeatherMap"),
+        }
+    }
+
+    match ctx.api_key("IP_GEOLOCATION_API_KEY") {
+        Ok(key) => {
+            let result =
+                geo_location::get_time(&ctx.http, &ctx.config.geo, &key, PROBE_LOCATION).await;
+            check("IPGeolocation", result.map(|_| ()))?;
+        }
+        Err(_) => info!("Warm-up: IP_GEOLOCATION_API_KEY not set, skipping IPGeolocation"),
+    }
+
+    Ok(())
+}
+
+/// Turns a probe result into a startup error naming the API.
+fn check(api: &str, result: Result<(), AppError>) -> Result<(), AppError> {
+    match result {
+        Ok(()) | Err(AppError::LocationNotFound(_)) => {
+            info!("Warm-up: {} is reachable", api);
+            Ok(())
+        }
+        Err(e) => Err(AppError::InvalidConfig(format!(
+            "warm-up request to {} failed, check its API key and settings: {}",
+            api, e
+        ))),
+    }
+}
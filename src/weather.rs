@@ -1,15 +1,246 @@
+use crate::cli::MissingValue;
+use crate::config::ApiConfig;
 use crate::error::AppError;
+use crate::http::{Http, HttpResponse};
+use crate::lenient::{self, Lenient};
+use crate::retry;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt::Debug;
 use tracing::{debug, error, info};
 
 // Module containing response data structures for weather information
-mod response;
+pub mod response;
 
-// API endpoint for the WeatherAPI current weather data
-const WEATHER_ENDPOINT: &str = "https://api.weatherapi.com/v1/current.json";
+// OpenWeatherMap client, an alternative provider of the current weather
+pub mod openweathermap;
+
+/// Identifier reported as the `source` of data fetched from WeatherAPI
+pub const SOURCE: &str = "weatherapi";
+
+/// Most forecast days WeatherAPI serves on any plan
+pub const MAX_FORECAST_DAYS: u32 = 14;
+
+// WeatherAPI error code for "No matching location found"
+const LOCATION_NOT_FOUND_CODE: i32 = 1006;
+
+/// Default base URL of the WeatherAPI service; the version segment comes from configuration
+pub const WEATHER_BASE_URL: &str = "https://api.weatherapi.com";
+
+// API path for the WeatherAPI current weather data
+const WEATHER_PATH: &str = "current.json";
+
+// API path for the WeatherAPI sun and moon data
+const ASTRONOMY_PATH: &str = "astronomy.json";
+
+// API path for the WeatherAPI daily forecast data
+const FORECAST_PATH: &str = "forecast.json";
+
+/// Current weather as reported to the model
+#[derive(serde::Serialize, Debug)]
+pub struct WeatherReport {
+    /// Place the provider resolved the query to, or the missing-value sentinel
+    pub location: Value,
+    /// Local date and time at the location, or the missing-value sentinel
+    pub local_time: Value,
+    /// Local time the readings were taken, or the missing-value sentinel
+    pub observed_at: Value,
+    /// Temperature in the requested unit
+    pub temperature: f64,
+    /// Feels-like temperature in the requested unit, or the missing-value sentinel
+    pub feels_like: Value,
+    /// Human-readable description of the weather condition
+    pub condition: String,
+    /// Humidity percentage (0-100), or the missing-value sentinel
+    pub humidity: Value,
+    /// Wind speed in `wind_unit`, or the missing-value sentinel
+    pub wind_speed: Value,
+    /// "kph" for Celsius reports, "mph" for Fahrenheit ones
+    pub wind_unit: &'static str,
+    /// Compass direction the wind blows from, or the missing-value sentinel
+    pub wind_direction: Value,
+    /// Air pressure in `pressure_unit`, or the missing-value sentinel
+    pub pressure: Value,
+    /// "mb" for Celsius reports, "inHg" for Fahrenheit ones
+    pub pressure_unit: &'static str,
+    /// UV index, or the missing-value sentinel
+    pub uv: Value,
+    /// Risk category of the UV index ("Low" to "Extreme"), or the missing-value sentinel
+    pub uv_risk: Value,
+    /// Where the data came from (e.g. "weatherapi" or "cache (fetched ... from weatherapi)")
+    pub source: String,
+}
+
+/// Service answering current weather lookups (`WEATHER_PROVIDER`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    /// WeatherAPI, the default
+    WeatherApi,
+    /// OpenWeatherMap
+    OpenWeatherMap,
+}
+
+impl Provider {
+    /// Looks a provider up by its `WEATHER_PROVIDER` name
+    pub fn from_name(name: &str) -> Option<Provider> {
+        match name.trim().to_lowercase().as_str() {
+            "weatherapi" => Some(Provider::WeatherApi),
+            "openweathermap" => Some(Provider::OpenWeatherMap),
+            _ => None,
+        }
+    }
+
+    /// Environment variable holding the provider's API key
+    pub fn key_var(self) -> &'static str {
+        match self {
+            Provider::WeatherApi => "WEATHER_API_KEY",
+            Provider::OpenWeatherMap => "OPENWEATHERMAP_API_KEY",
+        }
+    }
+}
+
+/// Current weather in the same shape whichever provider served it
+#[derive(Debug)]
+pub struct NormalizedWeather {
+    /// Place the provider resolved the query to (e.g. "London, United Kingdom"), if reported
+    pub location: Option<String>,
+    /// Local date and time at the location, like "2025-06-01 14:35", if reported
+    pub local_time: Option<String>,
+    /// Local time the readings were taken, like "2025-06-01 14:30", if reported
+    pub observed_at: Option<String>,
+    /// Temperature in Celsius
+    pub temp_c: f64,
+    /// Temperature in Fahrenheit
+    pub temp_f: f64,
+    /// Feels-like temperature in Celsius, if reported
+    pub feelslike_c: Option<f64>,
+    /// Feels-like temperature in Fahrenheit, if reported
+    pub feelslike_f: Option<f64>,
+    /// Human-readable description of the weather condition (e.g., "Partly cloudy")
+    pub condition: String,
+    /// Humidity percentage (0-100), if reported
+    pub humidity: Option<i32>,
+    /// Wind speed in kilometers per hour, if reported
+    pub wind_kph: Option<f64>,
+    /// Wind speed in miles per hour, if reported
+    pub wind_mph: Option<f64>,
+    /// Compass direction the wind blows from (e.g. "WSW"), if reported
+    pub wind_dir: Option<String>,
+    /// Air pressure in millibars, if reported
+    pub pressure_mb: Option<f64>,
+    /// Air pressure in inches of mercury, if reported
+    pub pressure_in: Option<f64>,
+    /// UV index, if reported
+    pub uv: Option<f64>,
+    /// Identifier of the provider, reported as the `source` of the data
+    pub source: &'static str,
+}
+
+impl NormalizedWeather {
+    /// Sun protection risk category of the UV index, on the WHO scale
+    pub fn uv_risk(&self) -> Option<&'static str> {
+        let uv = self.uv?;
+        Some(if uv < 3.0 {
+            "Low"
+        } else if uv < 6.0 {
+            "Moderate"
+        } else if uv < 8.0 {
+            "High"
+        } else if uv < 11.0 {
+            "Very High"
+        } else {
+            "Extreme"
+        })
+    }
+}
+
+/// A service that reports the current weather.
+///
+/// Tools only see `NormalizedWeather`, so they don't depend on which provider is configured.
+pub trait WeatherProvider {
+    /// Fetches the current weather for a location string like "London,GB"
+    async fn current(&self, location: &str) -> Result<NormalizedWeather, AppError>;
+}
+
+/// The WeatherAPI current weather endpoint as a `WeatherProvider`
+pub struct WeatherApi<'a> {
+    /// Shared HTTP client
+    pub http: &'a Http,
+    /// Connection settings for the API
+    pub api: &'a ApiConfig,
+    /// The API key for accessing the WeatherAPI service
+    pub api_key: &'a str,
+}
+
+impl WeatherProvider for WeatherApi<'_> {
+    async fn current(&self, location: &str) -> Result<NormalizedWeather, AppError> {
+        let response = get_weather(self.http, self.api, self.api_key, location).await?;
+        let current = response.current;
+
+        Ok(NormalizedWeather {
+            location: response
+                .location
+                .as_ref()
+                .map(response::Location::display_name),
+            local_time: response.location.and_then(|location| location.localtime),
+            observed_at: current.last_updated.clone(),
+            temp_c: current.temp_c,
+            temp_f: current.temp_f,
+            feelslike_c: current.feelslike_c,
+            feelslike_f: current.feelslike_f,
+            condition: current.condition_text(),
+            humidity: current.humidity,
+            wind_kph: current.wind_kph,
+            wind_mph: current.wind_mph,
+            wind_dir: current.wind_dir,
+            pressure_mb: current.pressure_mb,
+            pressure_in: current.pressure_in,
+            uv: current.uv,
+            source: SOURCE,
+        })
+    }
+}
+
+/// Renders an optional weather field for the model.
+///
+/// Every field the API may leave out goes through here, so a missing value is always
+/// reported the same way (`--missing-value`) instead of being omitted or defaulted to 0.
+pub fn optional_field<T: serde::Serialize>(value: Option<T>, missing: MissingValue) -> Value {
+    match value.map(serde_json::to_value) {
+        Some(Ok(value)) => value,
+        _ => match missing {
+            MissingValue::Null => Value::Null,
+            MissingValue::Unavailable => Value::String("unavailable".to_string()),
+        },
+    }
+}
+
+/// How far a forecast can be trusted, based on how many days ahead it is
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    /// Today and tomorrow
+    High,
+    /// Two to six days ahead
+    Medium,
+    /// A week or more ahead
+    Low,
+}
+
+/// Heuristic confidence of a forecast `days_ahead` of the location's current date.
+pub fn forecast_confidence(days_ahead: u32) -> Confidence {
+    match days_ahead {
+        0..=1 => Confidence::High,
+        2..=6 => Confidence::Medium,
+        _ => Confidence::Low,
+    }
+}
 
 /// Fetches current weather information for a specific location using the WeatherAPI.
 ///
 /// # Arguments
+/// * `http` - Shared HTTP client
+/// * `api` - Connection settings for the API
 /// * `api_key` - The API key for accessing the WeatherAPI service
 /// * `location` - Location string in format "city,country" (e.g., "London,GB")
 ///
@@ -17,26 +248,373 @@ const WEATHER_ENDPOINT: &str = "https://api.weatherapi.com/v1/current.json";
 /// * `WeatherResponse` containing temperature, condition, and humidity information
 /// * Error if the API request fails or returns an unsuccessful status code
 pub async fn get_weather(
+    http: &Http,
+    api: &ApiConfig,
     api_key: &str,
     location: &str,
 ) -> Result<response::WeatherResponse, AppError> {
     info!("Fetching weather data for location: {}", location);
+    fetch(http, api, api_key, WEATHER_PATH, location, &[], "weather").await
+}
+
+/// Fetches the current air quality for a specific location using the WeatherAPI.
+///
+/// # Arguments
+/// * `http` - Shared HTTP client
+/// * `api` - Connection settings for the API
+/// * `api_key` - The API key for accessing the WeatherAPI service
+/// * `location` - Location string in format "city,country" (e.g., "London,GB")
+///
+/// # Returns
+/// * `AirQualityResponse` with the PM2.5, PM10 and US EPA index readings that are available
+/// * Error if the API request fails or returns an unsuccessful status code
+pub async fn get_air_quality(
+    http: &Http,
+    api: &ApiConfig,
+    api_key: &str,
+    location: &str,
+) -> Result<response::AirQualityResponse, AppError> {
+    info!("Fetching air quality data for location: {}", location);
+
+    // Air quality comes with the current weather when asked for with aqi=yes
+    let query = [("aqi", "yes")];
+    fetch(
+        http,
+        api,
+        api_key,
+        WEATHER_PATH,
+        location,
+        &query,
+        "air quality",
+    )
+    .await
+}
+
+/// Fetches sunrise, sunset, moonrise, moonset and the moon phase for a location and date.
+///
+/// # Arguments
+/// * `http` - Shared HTTP client
+/// * `api` - Connection settings for the API
+/// * `api_key` - The API key for accessing the WeatherAPI service
+/// * `location` - Location string in format "city,country" (e.g., "London,GB")
+/// * `date` - Date in format "YYYY-MM-DD"
+///
+/// # Returns
+/// * `AstronomyResponse` with the times in the location's local time
+/// * Error if the API request fails or returns an unsuccessful status code
+pub async fn get_astronomy(
+    http: &Http,
+    api: &ApiConfig,
+    api_key: &str,
+    location: &str,
+    date: &str,
+) -> Result<response::AstronomyResponse, AppError> {
+    info!(
+        "Fetching astronomy data for location: {} on {}",
+        location, date
+    );
 
-    // Construct the API URL with query parameters
-    let url = format!("{}?key={}&q={}", WEATHER_ENDPOINT, api_key, location);
+    let query = [("dt", date)];
+    fetch(
+        http,
+        api,
+        api_key,
+        ASTRONOMY_PATH,
+        location,
+        &query,
+        "astronomy",
+    )
+    .await
+}
+
+/// Fetches the severe weather alerts for a specific location using the WeatherAPI.
+///
+/// # Arguments
+/// * `http` - Shared HTTP client
+/// * `api` - Connection settings for the API
+/// * `api_key` - The API key for accessing the WeatherAPI service
+/// * `location` - Location string in format "city,country" (e.g., "London,GB")
+///
+/// # Returns
+/// * `AlertsResponse` with every alert issued for the location, expired ones included
+/// * Error if the API request fails or returns an unsuccessful status code
+pub async fn get_alerts(
+    http: &Http,
+    api: &ApiConfig,
+    api_key: &str,
+    location: &str,
+) -> Result<response::AlertsResponse, AppError> {
+    info!("Fetching weather alerts for location: {}", location);
+
+    // Alerts come with the forecast when asked for with alerts=yes; one day is enough
+    let query = [("days", "1"), ("alerts", "yes")];
+    fetch(
+        http,
+        api,
+        api_key,
+        FORECAST_PATH,
+        location,
+        &query,
+        "alerts",
+    )
+    .await
+}
+
+/// Fetches a daily weather forecast for a specific location using the WeatherAPI.
+///
+/// # Arguments
+/// * `http` - Shared HTTP client
+/// * `api` - Connection settings for the API
+/// * `api_key` - The API key for accessing the WeatherAPI service
+/// * `location` - Location string in format "city,country" (e.g., "London,GB")
+/// * `days` - Number of forecast days to request, starting with the location's current date
+///
+/// # Returns
+/// * `ForecastResponse` containing one entry per forecast day
+/// * Error if the API request fails or returns an unsuccessful status code
+pub async fn get_forecast(
+    http: &Http,
+    api: &ApiConfig,
+    api_key: &str,
+    location: &str,
+    days: u32,
+) -> Result<response::ForecastResponse, AppError> {
+    info!("Fetching {} day forecast for location: {}", days, location);
+
+    let days = days.to_string();
+    let query = [("days", days.as_str())];
+    fetch(
+        http,
+        api,
+        api_key,
+        FORECAST_PATH,
+        location,
+        &query,
+        "forecast",
+    )
+    .await
+}
+
+/// Sends a WeatherAPI request for `location` and parses the successful response.
+///
+/// The key, the location and `extra_query` are sent as percent-encoded query parameters,
+/// and transient failures are retried.
+///
+/// # Arguments
+/// * `path` - API path of the endpoint, below the version segment
+/// * `extra_query` - Query parameters the endpoint takes besides the key and location
+/// * `what` - Name of the data, used in logs and errors
+async fn fetch<T: DeserializeOwned + Lenient + Debug>(
+    http: &Http,
+    api: &ApiConfig,
+    api_key: &str,
+    path: &str,
+    location: &str,
+    extra_query: &[(&str, &str)],
+    what: &str,
+) -> Result<T, AppError> {
+    let url = api.endpoint(path);
+    let mut query = vec![("key", api_key), ("q", location)];
+    query.extend_from_slice(extra_query);
 
-    // Create HTTP client and send the request
-    let client = reqwest::Client::new();
-    let response = client.get(&url).send().await?;
+    // Send the request through the shared client, retrying transient failures
+    let response = retry::with_backoff(
+        &api.retry,
+        what,
+        || http.get(&url, &query, &api.headers),
+        retry::is_transient_response,
+    )
+    .await?;
 
-    if response.status().is_success() {
-        // Parse successful response into WeatherResponse struct
-        let weather_response: response::WeatherResponse = response.json().await?;
-        debug!("Weather data fetched successfully: {:?}", weather_response);
-        Ok(weather_response)
+    if response.status.is_success() {
+        let parsed: T = lenient::parse(&response.body, api.lenient, what)?;
+        debug!("{} data fetched successfully: {:?}", what, parsed);
+        Ok(parsed)
     } else {
-        // Log and return error for unsuccessful responses
-        error!("Failed to fetch weather data: {}", response.status());
-        Err(AppError::ApiRequestFailed(format!("Failed to fetch weather data: {}", response.status())))
+        // Log and return error for unsuccessful responses, unknown locations included
+        Err(api_error(&response, what))
+    }
+}
+
+/// Converts an unsuccessful WeatherAPI response into an `AppError`.
+///
+/// Unknown locations become `AppError::LocationNotFound` so they can be told apart from
+/// other failures; everything else becomes `AppError::ApiStatus` with the status code and
+/// the API's own error message, or the raw body if it isn't a WeatherAPI error.
+fn api_error(response: &HttpResponse, what: &str) -> AppError {
+    let status = response.status;
+    error!("Failed to fetch {} data: {}", what, status);
+
+    match serde_json::from_str::<response::ApiErrorResponse>(&response.body) {
+        Ok(body) if body.error.code == LOCATION_NOT_FOUND_CODE => {
+            AppError::LocationNotFound(body.error.message)
+        }
+        // e.g. "API key is invalid." rather than the JSON it came in
+        Ok(body) => AppError::ApiStatus {
+            code: status.as_u16(),
+            body: body.error.message,
+        },
+        Err(_) => AppError::ApiStatus {
+            code: status.as_u16(),
+            body: response.body.clone(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::header::HeaderMap;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::retry::RetryPolicy;
+
+    /// WeatherAPI settings pointing at `server`, without retries
+    fn mock_api(server: &MockServer) -> ApiConfig {
+        ApiConfig {
+            base_url: server.uri(),
+            version: Some("v1".to_string()),
+            headers: HeaderMap::new(),
+            lenient: false,
+            retry: RetryPolicy {
+                max_attempts: 1,
+                base_delay: Duration::ZERO,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn get_weather_parses_current_conditions() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/current.json"))
+            .and(query_param("key", "test-key"))
+            .and(query_param("q", "London,GB"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "location": { "name": "London", "country": "United Kingdom" },
+                "current": {
+                    "temp_c": 18.0,
+                    "temp_f": 64.4,
+                    "condition": { "text": "Partly cloudy", "code": 1003 },
+                    "humidity": 60,
+                    "last_updated": "2025-06-01 14:30"
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = Http::new(1, None, false);
+        let weather = get_weather(&http, &mock_api(&server), "test-key", "London,GB")
+            .await
+            .unwrap();
+
+        assert_eq!(weather.current.temp_c, 18.0);
+        assert_eq!(weather.current.temp_f, 64.4);
+        assert_eq!(weather.current.humidity, Some(60));
+        assert_eq!(weather.current.condition.code, 1003);
+        assert_eq!(
+            weather.current.last_updated.as_deref(),
+            Some("2025-06-01 14:30")
+        );
+        assert_eq!(weather.location.unwrap().name, "London");
+    }
+
+    #[tokio::test]
+    async fn get_weather_maps_unsuccessful_status_to_api_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/current.json"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": { "code": 2006, "message": "API key is invalid." }
+            })))
+            .mount(&server)
+            .await;
+
+        let http = Http::new(1, None, false);
+        let error = get_weather(&http, &mock_api(&server), "bad-key", "London,GB")
+            .await
+            .unwrap_err();
+
+        match error {
+            AppError::ApiStatus { code, body } => {
+                assert_eq!(code, 401);
+                assert_eq!(body, "API key is invalid.");
+            }
+            other => panic!("expected ApiStatus, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_weather_maps_unknown_location_to_location_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/current.json"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": { "code": 1006, "message": "No matching location found." }
+            })))
+            .mount(&server)
+            .await;
+
+        let http = Http::new(1, None, false);
+        let error = get_weather(&http, &mock_api(&server), "test-key", "Nowhere,XX")
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(error, AppError::LocationNotFound(_)),
+            "{:?}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn get_forecast_encodes_the_location_and_extra_parameters() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/forecast.json"))
+            .and(query_param("key", "test-key"))
+            .and(query_param("q", "São Paulo,BR&days=9"))
+            .and(query_param("days", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "forecast": { "forecastday": [] }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = Http::new(1, None, false);
+        let forecast = get_forecast(
+            &http,
+            &mock_api(&server),
+            "test-key",
+            "São Paulo,BR&days=9",
+            2,
+        )
+        .await
+        .unwrap();
+
+        assert!(forecast.forecast.forecastday.is_empty());
+    }
+
+    #[test]
+    fn confidence_degrades_with_days_ahead() {
+        assert_eq!(forecast_confidence(0), Confidence::High);
+        assert_eq!(forecast_confidence(1), Confidence::High);
+        assert_eq!(forecast_confidence(2), Confidence::Medium);
+        assert_eq!(forecast_confidence(6), Confidence::Medium);
+        assert_eq!(forecast_confidence(7), Confidence::Low);
+        assert_eq!(forecast_confidence(13), Confidence::Low);
+    }
+
+    #[test]
+    fn confidence_is_reported_in_lowercase() {
+        assert_eq!(json!(forecast_confidence(0)), json!("high"));
+        assert_eq!(json!(forecast_confidence(3)), json!("medium"));
+        assert_eq!(json!(forecast_confidence(9)), json!("low"));
     }
 }
@@ -0,0 +1,304 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use tracing::{debug, error, info};
+
+use super::{NormalizedWeather, WeatherProvider};
+use crate::config::ApiConfig;
+use crate::error::AppError;
+use crate::http::{Http, HttpResponse};
+use crate::lenient::{self, Lenient, Recovered};
+use crate::retry;
+use crate::units::fahrenheit;
+
+/// Identifier reported as the `source` of data fetched from OpenWeatherMap
+pub const SOURCE: &str = "openweathermap";
+
+/// Default base URL of the OpenWeatherMap service
+pub const OPENWEATHERMAP_BASE_URL: &str = "https://api.openweathermap.org";
+
+// API path for the OpenWeatherMap current weather data
+const WEATHER_PATH: &str = "data/2.5/weather";
+
+// Unit conversions from the metric units OpenWeatherMap reports in
+const KPH_PER_METER_PER_SECOND: f64 = 3.6;
+const MPH_PER_METER_PER_SECOND: f64 = 2.236_936;
+const INHG_PER_HPA: f64 = 0.029_53;
+
+// 16-point compass, starting at north and going clockwise
+const COMPASS_POINTS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW",
+    "NNW",
+];
+
+/// The OpenWeatherMap current weather endpoint as a `WeatherProvider`
+pub struct OpenWeatherMap<'a> {
+    /// Shared HTTP client
+    pub http: &'a Http,
+    /// Connection settings for the API
+    pub api: &'a ApiConfig,
+    /// The API key for accessing the OpenWeatherMap service
+    pub api_key: &'a str,
+}
+
+impl WeatherProvider for OpenWeatherMap<'_> {
+    /// Fetches the current weather in metric units and converts it to the normalized shape.
+    ///
+    /// OpenWeatherMap doesn't report the UV index with the current weather, so it is missing.
+    async fn current(&self, location: &str) -> Result<NormalizedWeather, AppError> {
+        info!(
+            "Fetching OpenWeatherMap weather data for location: {}",
+            location
+        );
+
+        let url = self.api.endpoint(WEATHER_PATH);
+        let query = [
+            ("q", location),
+            ("appid", self.api_key),
+            ("units", "metric"),
+        ];
+
+        // Send the request through the shared client, retrying transient failures
+        let response = retry::with_backoff(
+            &self.api.retry,
+            "weather",
+            || self.http.get(&url, &query, &self.api.headers),
+            retry::is_transient_response,
+        )
+        .await?;
+
+        if !response.status.is_success() {
+            return Err(api_error(&response));
+        }
+
+        let weather_response: WeatherResponse =
+            lenient::parse(&response.body, self.api.lenient, "weather")?;
+        debug!("Weather data fetched successfully: {:?}", weather_response);
+        Ok(weather_response.normalize())
+    }
+}
+
+/// Response structure for the OpenWeatherMap current weather endpoint
+/// Represents the JSON structure returned by api.openweathermap.org/data/2.5/weather
+#[derive(serde::Deserialize, Debug)]
+pub struct WeatherResponse {
+    /// Name of the city the query resolved to, if reported
+    pub name: Option<String>,
+    /// Country of the city, if reported
+    pub sys: Option<System>,
+    /// Time of the readings as a Unix timestamp, if reported
+    pub dt: Option<i64>,
+    /// Offset of the city's local time from UTC in seconds, if reported
+    pub timezone: Option<i64>,
+    /// Condition descriptions, most significant first
+    #[serde(default)]
+    pub weather: Vec<Condition>,
+    /// Temperature, humidity and pressure readings
+    pub main: MainReadings,
+    /// Wind readings, if reported
+    pub wind: Option<Wind>,
+}
+
+/// Country of the city the query resolved to
+#[derive(serde::Deserialize, Debug)]
+pub struct System {
+    /// ISO 3166-1 alpha-2 country code (e.g. "GB"), if reported
+    pub country: Option<String>,
+}
+
+/// Weather condition description
+#[derive(serde::Deserialize, Debug)]
+pub struct Condition {
+    /// Lowercase description of the condition (e.g., "light rain")
+    pub description: String,
+}
+
+/// Main readings, in metric units
+#[derive(serde::Deserialize, Debug)]
+pub struct MainReadings {
+    /// Temperature in Celsius
+    pub temp: f64,
+    /// Feels-like temperature in Celsius, if reported
+    pub feels_like: Option<f64>,
+    /// Air pressure at sea level in hectopascals, if reported
+    pub pressure: Option<f64>,
+    /// Humidity percentage (0-100), if reported
+    pub humidity: Option<f64>,
+}
+
+/// Wind readings, in metric units
+#[derive(serde::Deserialize, Debug)]
+pub struct Wind {
+    /// Wind speed in meters per second, if reported
+    pub speed: Option<f64>,
+    /// Meteorological direction the wind blows from in degrees, if reported
+    pub deg: Option<f64>,
+}
+
+/// Error body returned by OpenWeatherMap for unsuccessful requests
+/// e.g. `{"cod":"404","message":"city not found"}`
+#[derive(serde::Deserialize, Debug)]
+pub struct ApiErrorResponse {
+    /// Human-readable error message
+    pub message: String,
+}
+
+impl WeatherResponse {
+    /// Converts the metric readings into the normalized shape, deriving the imperial ones
+    fn normalize(self) -> NormalizedWeather {
+        let wind_speed = self.wind.as_ref().and_then(|wind| wind.speed);
+        let wind_deg = self.wind.as_ref().and_then(|wind| wind.deg);
+        let country = self.sys.and_then(|sys| sys.country);
+        let location = self.name.map(|name| match country {
+            Some(country) => format!("{}, {}", name, country),
+            None => name,
+        });
+
+        NormalizedWeather {
+            location,
+            local_time: self
+                .timezone
+                .and_then(|offset| local_time(Utc::now().timestamp(), offset)),
+            observed_at: self
+                .dt
+                .zip(self.timezone)
+                .and_then(|(dt, offset)| local_time(dt, offset)),
+            temp_c: self.main.temp,
+            temp_f: fahrenheit(self.main.temp),
+            feelslike_c: self.main.feels_like,
+            feelslike_f: self.main.feels_like.map(fahrenheit),
+            condition: self
+                .weather
+                .first()
+                .map(|condition| capitalize(&condition.description))
+                .unwrap_or_default(),
+            humidity: self.main.humidity.map(|humidity| humidity.round() as i32),
+            wind_kph: wind_speed.map(|speed| speed * KPH_PER_METER_PER_SECOND),
+            wind_mph: wind_speed.map(|speed| speed * MPH_PER_METER_PER_SECOND),
+            wind_dir: wind_deg.map(|deg| compass_point(deg).to_string()),
+            // Hectopascals and millibars are the same unit
+            pressure_mb: self.main.pressure,
+            pressure_in: self.main.pressure.map(|pressure| pressure * INHG_PER_HPA),
+            uv: None,
+            source: SOURCE,
+        }
+    }
+}
+
+/// Formats a Unix timestamp as local time at a UTC offset in seconds, like WeatherAPI does
+fn local_time(timestamp: i64, offset_secs: i64) -> Option<String> {
+    let local = DateTime::from_timestamp(timestamp + offset_secs, 0)?;
+    Some(local.format("%Y-%m-%d %H:%M").to_string())
+}
+
+/// Nearest of the 16 compass points to a direction in degrees (e.g. 250 becomes "WSW")
+fn compass_point(deg: f64) -> &'static str {
+    let index = (deg.rem_euclid(360.0) / 22.5).round() as usize % COMPASS_POINTS.len();
+    COMPASS_POINTS[index]
+}
+
+/// Upper-cases the first letter, so "light rain" reads like WeatherAPI's "Light rain"
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Converts an unsuccessful OpenWeatherMap response into an `AppError`.
+///
+/// OpenWeatherMap answers unknown cities with 404, which becomes `AppError::LocationNotFound`;
+/// everything else becomes `AppError::ApiStatus` with the status code and the API's own error
+/// message, or the raw body if it has none.
+fn api_error(response: &HttpResponse) -> AppError {
+    let status = response.status;
+    error!("Failed to fetch weather data: {}", status);
+
+    match serde_json::from_str::<ApiErrorResponse>(&response.body) {
+        Ok(body) if status.as_u16() == 404 => AppError::LocationNotFound(body.message),
+        Ok(body) => AppError::ApiStatus {
+            code: status.as_u16(),
+            body: body.message,
+        },
+        Err(_) => AppError::ApiStatus {
+            code: status.as_u16(),
+            body: response.body.clone(),
+        },
+    }
+}
+
+// Lenient recovery for responses whose shape changed upstream (`--lenient-parse`)
+
+impl Lenient for WeatherResponse {
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self> {
+        // Accept the readings either nested under "main" or at the top level
+        let main = body.get("main").unwrap_or(body);
+        let wind = body.get("wind").unwrap_or(body);
+        let description = body
+            .get("weather")
+            .and_then(|weather| weather.get(0))
+            .and_then(|condition| recovered.string(condition, &["description"]))
+            .unwrap_or_else(|| {
+                recovered.note("weather.description (unknown)");
+                String::new()
+            });
+
+        Some(WeatherResponse {
+            name: recovered.string(body, &["name"]),
+            sys: Some(System {
+                country: recovered.string(body, &["sys", "country"]),
+            }),
+            dt: recovered.number(body, &["dt"]).map(|dt| dt as i64),
+            timezone: recovered
+                .number(body, &["timezone"])
+                .map(|offset| offset as i64),
+            weather: vec![Condition { description }],
+            main: MainReadings {
+                temp: recovered.number(main, &["temp"])?,
+                feels_like: recovered.number(main, &["feels_like"]),
+                pressure: recovered.number(main, &["pressure"]),
+                humidity: recovered.number(main, &["humidity"]),
+            },
+            wind: Some(Wind {
+                speed: recovered.number(wind, &["speed"]),
+                deg: recovered.number(wind, &["deg"]),
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_temp_eq;
+
+    #[test]
+    fn normalize_derives_imperial_readings() {
+        let weather = WeatherResponse {
+            name: None,
+            sys: None,
+            dt: None,
+            timezone: None,
+            weather: vec![Condition {
+                description: "light rain".to_string(),
+            }],
+            main: MainReadings {
+                temp: 18.0,
+                feels_like: Some(17.0),
+                pressure: Some(1015.0),
+                humidity: Some(60.0),
+            },
+            wind: Some(Wind {
+                speed: Some(10.0),
+                deg: Some(250.0),
+            }),
+        }
+        .normalize();
+
+        assert_temp_eq(weather.temp_f, 64.4);
+        assert_temp_eq(weather.feelslike_f.unwrap(), 62.6);
+        assert_temp_eq(weather.wind_kph.unwrap(), 36.0);
+        assert_eq!(weather.wind_dir.as_deref(), Some("WSW"));
+        assert_eq!(weather.condition, "Light rain");
+    }
+}
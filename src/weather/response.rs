@@ -1,11 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::conditions;
+use crate::lenient::{Lenient, Recovered};
+
 /// Response structure for the WeatherAPI current weather endpoint
 /// Represents the JSON structure returned by api.weatherapi.com/v1/current.json
 #[derive(serde::Deserialize, Debug)]
 pub struct WeatherResponse {
+    /// The location the query resolved to, if reported
+    pub location: Option<Location>,
     /// Current weather conditions
     pub current: CurrentWeather,
 }
 
+/// Location a WeatherAPI query resolved to
+#[derive(serde::Deserialize, Debug)]
+pub struct Location {
+    /// Place name (e.g. "London")
+    pub name: String,
+    /// Region or state, if reported
+    pub region: Option<String>,
+    /// Country name (e.g. "United Kingdom"), if reported
+    pub country: Option<String>,
+    /// Local date and time at the location (e.g. "2025-06-01 14:35"), if reported
+    pub localtime: Option<String>,
+}
+
+impl Location {
+    /// Name with the region and country, like "Portland, Oregon, United States of America",
+    /// which tells apart places sharing a name
+    pub fn display_name(&self) -> String {
+        [
+            Some(&self.name),
+            self.region.as_ref(),
+            self.country.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
 /// Contains the current weather data including temperature and conditions
 #[derive(serde::Deserialize, Debug)]
 pub struct CurrentWeather {
@@ -13,10 +52,37 @@ pub struct CurrentWeather {
     pub temp_c: f64,
     /// Temperature in Fahrenheit
     pub temp_f: f64,
+    /// Feels-like temperature in Celsius, if reported
+    pub feelslike_c: Option<f64>,
+    /// Feels-like temperature in Fahrenheit, if reported
+    pub feelslike_f: Option<f64>,
     /// Text description of the current weather condition
     pub condition: WeatherCondition,
-    /// Humidity percentage (0-100)
-    pub humidity: i32,
+    /// Humidity percentage (0-100), if reported
+    pub humidity: Option<i32>,
+    /// Wind speed in kilometers per hour, if reported
+    pub wind_kph: Option<f64>,
+    /// Wind speed in miles per hour, if reported
+    pub wind_mph: Option<f64>,
+    /// Compass direction the wind blows from (e.g. "WSW"), if reported
+    pub wind_dir: Option<String>,
+    /// Air pressure in millibars, if reported
+    pub pressure_mb: Option<f64>,
+    /// Air pressure in inches of mercury, if reported
+    pub pressure_in: Option<f64>,
+    /// UV index, if reported
+    pub uv: Option<f64>,
+    /// 1 during daylight at the location, 0 at night
+    pub is_day: Option<i32>,
+    /// Local time the readings were taken (e.g. "2025-06-01 14:30"), if reported
+    pub last_updated: Option<String>,
+}
+
+impl CurrentWeather {
+    /// Condition text matching the time of day at the location
+    pub fn condition_text(&self) -> String {
+        self.condition.official_text(self.is_day != Some(0))
+    }
 }
 
 /// Weather condition description
@@ -24,4 +90,406 @@ pub struct CurrentWeather {
 pub struct WeatherCondition {
     /// Human-readable description of the weather condition (e.g., "Partly cloudy")
     pub text: String,
+    /// WeatherAPI condition code (e.g., 1000 for "Sunny"/"Clear")
+    pub code: i32,
+}
+
+impl WeatherCondition {
+    /// Official text for the condition code, falling back to the text sent by the API
+    pub fn official_text(&self, is_day: bool) -> String {
+        conditions::lookup_condition(self.code, is_day)
+            .map(str::to_string)
+            .unwrap_or_else(|| self.text.clone())
+    }
+}
+
+/// Response structure for the WeatherAPI current weather endpoint queried with `aqi=yes`
+#[derive(serde::Deserialize, Debug)]
+pub struct AirQualityResponse {
+    /// Current conditions, of which only the air quality is used
+    pub current: AirQualityCurrent,
+}
+
+/// Current conditions holding the air quality readings
+#[derive(serde::Deserialize, Debug)]
+pub struct AirQualityCurrent {
+    /// Air quality readings; missing for locations without measurements
+    #[serde(default)]
+    pub air_quality: AirQuality,
+}
+
+/// Air quality readings at a location, each omitted when there is no sensor data
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct AirQuality {
+    /// Fine particulate matter (PM2.5) in μg/m³
+    pub pm2_5: Option<f64>,
+    /// Coarse particulate matter (PM10) in μg/m³
+    pub pm10: Option<f64>,
+    /// US EPA air quality index band, from 1 (Good) to 6 (Hazardous)
+    #[serde(rename = "us-epa-index")]
+    pub us_epa_index: Option<i32>,
+}
+
+impl AirQuality {
+    /// Human-readable label of the US EPA index band
+    pub fn epa_label(&self) -> Option<&'static str> {
+        match self.us_epa_index? {
+            1 => Some("Good"),
+            2 => Some("Moderate"),
+            3 => Some("Unhealthy for sensitive groups"),
+            4 => Some("Unhealthy"),
+            5 => Some("Very unhealthy"),
+            6 => Some("Hazardous"),
+            _ => None,
+        }
+    }
+}
+
+/// Response structure for the WeatherAPI astronomy endpoint
+/// Represents the JSON structure returned by api.weatherapi.com/v1/astronomy.json
+#[derive(serde::Deserialize, Debug)]
+pub struct AstronomyResponse {
+    /// Astronomical data for the requested date
+    pub astronomy: Astronomy,
+}
+
+/// Container for the astronomical data
+#[derive(serde::Deserialize, Debug)]
+pub struct Astronomy {
+    /// Sun and moon times and the moon phase
+    pub astro: Astro,
+}
+
+/// Sun and moon times in local time (e.g. "05:43 AM"), and the moon phase
+///
+/// The moon times are texts like "No moonrise" on days without one.
+#[derive(serde::Deserialize, Debug)]
+pub struct Astro {
+    /// Time of sunrise
+    pub sunrise: String,
+    /// Time of sunset
+    pub sunset: String,
+    /// Time of moonrise, if reported
+    pub moonrise: Option<String>,
+    /// Time of moonset, if reported
+    pub moonset: Option<String>,
+    /// Moon phase (e.g. "Waxing Crescent"), if reported
+    pub moon_phase: Option<String>,
+    /// Percentage of the moon's disc that is lit (0-100), if reported
+    pub moon_illumination: Option<f64>,
+}
+
+/// Response structure for the WeatherAPI forecast endpoint queried with `alerts=yes`
+#[derive(serde::Deserialize, Debug)]
+pub struct AlertsResponse {
+    /// Weather alerts; missing for locations without an alert service
+    #[serde(default)]
+    pub alerts: Alerts,
+}
+
+/// Container for the list of weather alerts
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct Alerts {
+    /// Alerts issued for the location, empty when there are none
+    #[serde(default)]
+    pub alert: Vec<Alert>,
+}
+
+/// A severe weather alert issued by a national weather service
+///
+/// WeatherAPI sends empty strings for fields the issuing service leaves out.
+#[derive(serde::Deserialize, Debug)]
+pub struct Alert {
+    /// Short summary of the alert (e.g. "Flood Warning issued January 05 at 9:47PM EST")
+    pub headline: Option<String>,
+    /// Severity of the alert (e.g. "Moderate", "Severe", "Extreme")
+    pub severity: Option<String>,
+    /// Kind of event the alert is for (e.g. "Flood Warning")
+    pub event: Option<String>,
+    /// When the alert expires, in RFC 3339 format
+    pub expires: Option<String>,
+}
+
+impl Alert {
+    /// Whether the alert is still in effect at `now`, counting one without a readable expiry
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires
+            .as_deref()
+            .and_then(|expires| DateTime::parse_from_rfc3339(expires).ok())
+            .is_none_or(|expires| expires > now)
+    }
+}
+
+impl AlertsResponse {
+    /// Alerts still in effect at `now`
+    pub fn active(&self, now: DateTime<Utc>) -> Vec<&Alert> {
+        self.alerts
+            .alert
+            .iter()
+            .filter(|alert| alert.is_active(now))
+            .collect()
+    }
+}
+
+/// Response structure for the WeatherAPI forecast endpoint
+/// Represents the JSON structure returned by api.weatherapi.com/v1/forecast.json
+#[derive(serde::Deserialize, Debug)]
+pub struct ForecastResponse {
+    /// The location the query resolved to, if reported
+    pub location: Option<Location>,
+    /// Forecast data grouped by day
+    pub forecast: Forecast,
+}
+
+/// Container for the list of forecast days
+#[derive(serde::Deserialize, Debug)]
+pub struct Forecast {
+    /// One entry per forecast day, starting with the location's current date
+    pub forecastday: Vec<ForecastDay>,
+}
+
+/// Forecast for a single day
+#[derive(serde::Deserialize, Debug)]
+pub struct ForecastDay {
+    /// Forecast date in format "YYYY-MM-DD"
+    pub date: String,
+    /// Aggregated weather data for the day
+    pub day: DayForecast,
+}
+
+/// Aggregated weather data for a single forecast day
+#[derive(serde::Deserialize, Debug)]
+pub struct DayForecast {
+    /// Maximum temperature in Celsius
+    pub maxtemp_c: f64,
+    /// Maximum temperature in Fahrenheit
+    pub maxtemp_f: f64,
+    /// Minimum temperature in Celsius
+    pub mintemp_c: f64,
+    /// Minimum temperature in Fahrenheit
+    pub mintemp_f: f64,
+    /// Maximum wind speed in kilometers per hour, if reported
+    pub maxwind_kph: Option<f64>,
+    /// Chance of rain as a percentage (0-100), if reported
+    pub daily_chance_of_rain: Option<i32>,
+    /// Chance of snow as a percentage (0-100), if reported
+    pub daily_chance_of_snow: Option<i32>,
+    /// Text description of the expected weather condition
+    pub condition: WeatherCondition,
+}
+
+/// Error body returned by WeatherAPI for unsuccessful requests
+/// e.g. `{"error":{"code":1006,"message":"No matching location found."}}`
+#[derive(serde::Deserialize, Debug)]
+pub struct ApiErrorResponse {
+    /// Error details
+    pub error: ApiError,
+}
+
+/// WeatherAPI error details
+#[derive(serde::Deserialize, Debug)]
+pub struct ApiError {
+    /// WeatherAPI error code (e.g., 1006 for "No matching location found")
+    pub code: i32,
+    /// Human-readable error message
+    pub message: String,
+}
+
+// Lenient recovery for responses whose shape changed upstream (`--lenient-parse`)
+
+impl Lenient for WeatherResponse {
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self> {
+        // Accept the current conditions either nested under "current" or at the top level
+        let current = body.get("current").unwrap_or(body);
+        let (temp_c, temp_f) = recovered.temperature_pair(current, "temp_c", "temp_f")?;
+        let location = body
+            .get("location")
+            .and_then(|location| Location::recover(location, recovered));
+
+        Some(WeatherResponse {
+            location,
+            current: CurrentWeather {
+                temp_c,
+                temp_f,
+                feelslike_c: recovered.number(current, &["feelslike_c"]),
+                feelslike_f: recovered.number(current, &["feelslike_f"]),
+                condition: WeatherCondition::recover(current, recovered)?,
+                humidity: recovered
+                    .number(current, &["humidity"])
+                    .map(|humidity| humidity.round() as i32),
+                wind_kph: recovered.number(current, &["wind_kph"]),
+                wind_mph: recovered.number(current, &["wind_mph"]),
+                wind_dir: recovered.string(current, &["wind_dir"]),
+                pressure_mb: recovered.number(current, &["pressure_mb"]),
+                pressure_in: recovered.number(current, &["pressure_in"]),
+                uv: recovered.number(current, &["uv"]),
+                is_day: recovered
+                    .number(current, &["is_day"])
+                    .map(|is_day| is_day as i32),
+                last_updated: recovered.string(current, &["last_updated"]),
+            },
+        })
+    }
+}
+
+impl Lenient for AstronomyResponse {
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self> {
+        // Accept the times under "astronomy.astro", "astro" or at the top level
+        let astro = body
+            .get("astronomy")
+            .unwrap_or(body)
+            .get("astro")
+            .unwrap_or(body);
+
+        Some(AstronomyResponse {
+            astronomy: Astronomy {
+                astro: Astro {
+                    sunrise: recovered.string(astro, &["sunrise"])?,
+                    sunset: recovered.string(astro, &["sunset"])?,
+                    moonrise: recovered.string(astro, &["moonrise"]),
+                    moonset: recovered.string(astro, &["moonset"]),
+                    moon_phase: recovered.string(astro, &["moon_phase"]),
+                    moon_illumination: recovered.number(astro, &["moon_illumination"]),
+                },
+            },
+        })
+    }
+}
+
+impl Lenient for AirQualityResponse {
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self> {
+        // Accept the readings under "current" or at the top level, as for the weather
+        let current = body.get("current").unwrap_or(body);
+        let air_quality = current.get("air_quality")?;
+
+        Some(AirQualityResponse {
+            current: AirQualityCurrent {
+                air_quality: AirQuality {
+                    pm2_5: recovered.number(air_quality, &["pm2_5"]),
+                    pm10: recovered.number(air_quality, &["pm10"]),
+                    us_epa_index: recovered
+                        .number(air_quality, &["us-epa-index"])
+                        .map(|index| index as i32),
+                },
+            },
+        })
+    }
+}
+
+impl Lenient for WeatherCondition {
+    /// Recovers the condition from the object holding the `condition` field
+    fn recover(parent: &Value, recovered: &mut Recovered) -> Option<Self> {
+        // Accept either a condition object or a bare condition text
+        let text = recovered
+            .string(parent, &["condition", "text"])
+            .or_else(|| recovered.string(parent, &["condition"]))?;
+        let code = recovered
+            .number(parent, &["condition", "code"])
+            .map(|code| code as i32)
+            .unwrap_or_else(|| {
+                recovered.note("condition.code (unknown)");
+                0
+            });
+
+        Some(WeatherCondition { text, code })
+    }
+}
+
+impl Lenient for AlertsResponse {
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self> {
+        // Accept the alerts under "alerts.alert", "alerts" or "alert"
+        let alerts = body.get("alerts").unwrap_or(body);
+        let alerts = alerts
+            .get("alert")
+            .unwrap_or(alerts)
+            .as_array()
+            .cloned()
+            .unwrap_or_else(|| {
+                recovered.note("alerts (none)");
+                Vec::new()
+            });
+
+        let mut field = |alert: &Value, name: &str| recovered.string(alert, &[name]);
+        let alert = alerts
+            .iter()
+            .map(|alert| Alert {
+                headline: field(alert, "headline"),
+                severity: field(alert, "severity"),
+                event: field(alert, "event"),
+                expires: field(alert, "expires"),
+            })
+            .collect();
+
+        Some(AlertsResponse {
+            alerts: Alerts { alert },
+        })
+    }
+}
+
+impl Lenient for ForecastResponse {
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self> {
+        let days = body
+            .get("forecast")
+            .and_then(|forecast| forecast.get("forecastday"))
+            .and_then(Value::as_array)?;
+
+        // Keep the days that can be recovered, skipping the rest
+        let forecastday: Vec<ForecastDay> = days
+            .iter()
+            .filter_map(|day| ForecastDay::recover(day, recovered))
+            .collect();
+        if forecastday.len() < days.len() {
+            recovered.note(format!(
+                "forecastday ({} of {} days)",
+                forecastday.len(),
+                days.len()
+            ));
+        }
+
+        Some(ForecastResponse {
+            location: body
+                .get("location")
+                .and_then(|location| Location::recover(location, recovered)),
+            forecast: Forecast { forecastday },
+        })
+    }
+}
+
+impl Lenient for Location {
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self> {
+        Some(Location {
+            name: recovered.string(body, &["name"])?,
+            region: recovered.string(body, &["region"]),
+            country: recovered.string(body, &["country"]),
+            localtime: recovered.string(body, &["localtime"]),
+        })
+    }
+}
+
+impl Lenient for ForecastDay {
+    fn recover(body: &Value, recovered: &mut Recovered) -> Option<Self> {
+        let date = recovered.string(body, &["date"])?;
+        let day = body.get("day")?;
+        let (maxtemp_c, maxtemp_f) = recovered.temperature_pair(day, "maxtemp_c", "maxtemp_f")?;
+        let (mintemp_c, mintemp_f) = recovered.temperature_pair(day, "mintemp_c", "mintemp_f")?;
+        let chance = |recovered: &mut Recovered, field: &str| {
+            recovered
+                .number(day, &[field])
+                .map(|chance| chance.round() as i32)
+        };
+
+        Some(ForecastDay {
+            date,
+            day: DayForecast {
+                maxtemp_c,
+                maxtemp_f,
+                mintemp_c,
+                mintemp_f,
+                maxwind_kph: recovered.number(day, &["maxwind_kph"]),
+                daily_chance_of_rain: chance(recovered, "daily_chance_of_rain"),
+                daily_chance_of_snow: chance(recovered, "daily_chance_of_snow"),
+                condition: WeatherCondition::recover(day, recovered)?,
+            },
+        })
+    }
 }